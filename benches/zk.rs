@@ -0,0 +1,32 @@
+use ark_bn254::Fr;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::{ceremony, ZKProver};
+
+fn prover() -> ZKProver {
+    let mut rng = StdRng::seed_from_u64(0);
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut rng)).unwrap();
+    ZKProver::from_keys(params.proving_key)
+}
+
+fn bench_zk(c: &mut Criterion) {
+    let prover = prover();
+    let inputs = [Fr::from(1u64), Fr::from(2u64)];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+
+    c.bench_function("groth16_prove", |b| {
+        b.iter(|| prover.create_proof(black_box(inputs[0]), black_box(inputs[1])).unwrap())
+    });
+    c.bench_function("groth16_verify", |b| {
+        b.iter(|| assert!(prover.verify_proof(black_box(&proof), black_box(&inputs))))
+    });
+
+    let batch: Vec<(&[u8], &[Fr])> = (0..32).map(|_| (proof.as_slice(), &inputs[..])).collect();
+    c.bench_function("groth16_verify_batch_32", |b| {
+        b.iter(|| assert!(prover.verify_proofs(black_box(&batch)).iter().all(|valid| *valid)))
+    });
+}
+
+criterion_group!(benches, bench_zk);
+criterion_main!(benches);
@@ -0,0 +1,4 @@
+fn main() {
+    // the migrations are embedded, so new ones must rebuild the binary
+    println!("cargo:rerun-if-changed=migrations");
+}
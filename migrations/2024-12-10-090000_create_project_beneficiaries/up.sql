@@ -0,0 +1,12 @@
+-- Your SQL goes here
+
+CREATE TABLE project_beneficiaries (
+    id SERIAL PRIMARY KEY,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    wallet VARCHAR NOT NULL,
+    share_bps INTEGER NOT NULL CHECK (share_bps > 0 AND share_bps <= 10000),
+    label VARCHAR,
+    UNIQUE (project_id, wallet)
+);
+
+CREATE INDEX idx_project_beneficiaries_project_id ON project_beneficiaries(project_id);
@@ -0,0 +1,30 @@
+-- Your SQL goes here
+
+CREATE TABLE pledges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    identity_id UUID NOT NULL REFERENCES identities(id) ON DELETE CASCADE,
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    amount DOUBLE PRECISION NOT NULL CHECK (amount > 0),
+    interval_days INTEGER NOT NULL CHECK (interval_days > 0),
+    notify_channel VARCHAR NOT NULL,
+    notify_target VARCHAR NOT NULL,
+    next_due_at TIMESTAMP NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE pledge_reminders (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    pledge_id UUID NOT NULL REFERENCES pledges(id) ON DELETE CASCADE,
+    due_at TIMESTAMP NOT NULL,
+    action_url VARCHAR NOT NULL,
+    delivered_at TIMESTAMP,
+    delivery_error TEXT,
+    fulfilled_at TIMESTAMP,
+    signature VARCHAR UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (pledge_id, due_at)
+);
+
+CREATE INDEX idx_pledges_due ON pledges(next_due_at) WHERE active;
+CREATE INDEX idx_pledge_reminders_pledge_id ON pledge_reminders(pledge_id);
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+
+CREATE TABLE donations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    project_id INTEGER NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    donor_wallet VARCHAR NOT NULL,
+    amount_lamports BIGINT NOT NULL CHECK (amount_lamports > 0),
+    signature VARCHAR NOT NULL UNIQUE,
+    slot BIGINT NOT NULL,
+    confirmed_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_donations_project_id_confirmed_at ON donations(project_id, confirmed_at);
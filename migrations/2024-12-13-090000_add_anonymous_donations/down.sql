@@ -0,0 +1,10 @@
+-- This file should undo anything in `up.sql`
+
+DELETE FROM donations WHERE verified_anonymous;
+
+ALTER TABLE donations
+    DROP CONSTRAINT donations_donor_check,
+    DROP COLUMN verified_anonymous,
+    DROP COLUMN signature_hash,
+    ALTER COLUMN signature SET NOT NULL,
+    ALTER COLUMN donor_wallet SET NOT NULL;
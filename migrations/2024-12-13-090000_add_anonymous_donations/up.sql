@@ -0,0 +1,17 @@
+-- Your SQL goes here
+
+ALTER TABLE donations
+    ALTER COLUMN donor_wallet DROP NOT NULL,
+    ALTER COLUMN signature DROP NOT NULL,
+    ADD COLUMN signature_hash VARCHAR,
+    ADD COLUMN verified_anonymous BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE donations SET signature_hash = encode(sha256(convert_to(signature, 'UTF8')), 'hex');
+
+ALTER TABLE donations
+    ALTER COLUMN signature_hash SET NOT NULL,
+    ADD CONSTRAINT donations_signature_hash_key UNIQUE (signature_hash),
+    ADD CONSTRAINT donations_donor_check CHECK (
+        (verified_anonymous AND donor_wallet IS NULL AND signature IS NULL)
+        OR (NOT verified_anonymous AND donor_wallet IS NOT NULL AND signature IS NOT NULL)
+    );
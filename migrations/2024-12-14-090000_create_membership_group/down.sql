@@ -0,0 +1,8 @@
+-- This file should undo anything in `up.sql`
+
+DROP TABLE membership_nullifiers;
+
+ALTER TABLE identities
+    DROP CONSTRAINT identities_commitment_check,
+    DROP COLUMN commitment_index,
+    DROP COLUMN commitment;
@@ -0,0 +1,15 @@
+-- Your SQL goes here
+
+-- commitments are numbered in registration order, giving each its leaf in the group tree
+ALTER TABLE identities
+    ADD COLUMN commitment VARCHAR UNIQUE,
+    ADD COLUMN commitment_index BIGINT UNIQUE,
+    ADD CONSTRAINT identities_commitment_check CHECK ((commitment IS NULL) = (commitment_index IS NULL));
+
+CREATE TABLE membership_nullifiers (
+    external_nullifier VARCHAR NOT NULL,
+    nullifier_hash VARCHAR NOT NULL,
+    signal_hash VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (external_nullifier, nullifier_hash)
+);
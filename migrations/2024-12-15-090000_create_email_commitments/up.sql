@@ -0,0 +1,7 @@
+-- Your SQL goes here
+
+-- commitments issued to verified emails; the emails themselves are not stored
+CREATE TABLE email_commitments (
+    commitment VARCHAR PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
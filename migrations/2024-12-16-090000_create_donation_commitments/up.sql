@@ -0,0 +1,8 @@
+-- Your SQL goes here
+
+-- commitments issued to donors for their confirmed donations; which donation a
+-- commitment belongs to is not stored
+CREATE TABLE donation_commitments (
+    commitment VARCHAR PRIMARY KEY,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+DROP INDEX idx_donations_project_id_donor_nullifier;
+
+UPDATE donations SET slot = 0 WHERE slot IS NULL;
+
+ALTER TABLE donations
+    DROP CONSTRAINT donations_donor_nullifier_check,
+    ALTER COLUMN slot SET NOT NULL,
+    DROP COLUMN donor_nullifier;
@@ -0,0 +1,19 @@
+-- Anonymous donations are backed by a membership proof; its nullifier hash is the same for
+-- every donation an identity makes to a project, so donors can be counted without knowing who
+-- they are. Anonymous rows keep no slot and only the day they were confirmed on.
+ALTER TABLE donations
+    ADD COLUMN donor_nullifier VARCHAR,
+    ALTER COLUMN slot DROP NOT NULL,
+    ADD CONSTRAINT donations_donor_nullifier_check CHECK (donor_nullifier IS NULL OR verified_anonymous);
+
+CREATE INDEX idx_donations_project_id_donor_nullifier ON donations(project_id, donor_nullifier);
+
+-- Signature hashes are now keyed with a server secret. The unkeyed SHA-256 of an anonymous
+-- donation's signature could be matched against the chain to find the donor, so it is dropped;
+-- those donations can't be deduplicated any more. Public donations keep their signature, which
+-- stays unique.
+UPDATE donations
+SET signature_hash = 'legacy:' || gen_random_uuid()::TEXT,
+    slot = NULL,
+    confirmed_at = date_trunc('day', confirmed_at)
+WHERE verified_anonymous;
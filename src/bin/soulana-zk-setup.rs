@@ -0,0 +1,191 @@
+//! Trusted setup ceremony for the Soulana ZK keys.
+//!
+//! ```text
+//! soulana-zk-setup init <ptau> <out> [circuit-id]
+//! soulana-zk-setup contribute <in> <out> <name>
+//! soulana-zk-setup beacon <in> <out> <beacon-hex> <iterations>
+//! soulana-zk-setup verify <before> <after>
+//! soulana-zk-setup verify-transcript <ptau> <params>
+//! soulana-zk-setup export <ptau> <params> <keys-dir>
+//! soulana-zk-setup export-solana <keys-dir> <circuit-id> <out.rs>
+//! soulana-zk-setup import-snarkjs <verification_key.json> <keys-dir> <circuit-id>
+//! ```
+//!
+//! `init` derives the keys of the current identity circuit, unless another circuit id such as
+//! `membership@v1` is given, from a snarkjs `.ptau` file of a public powers of tau ceremony.
+//! Later steps read the circuit from the ceremony file.
+//! `export-solana` writes an exported verifying key as a groth16-solana constant, for
+//! including in a Solana program. `import-snarkjs` installs the verifying key of a circom
+//! circuit, as exported by `snarkjs zkey export verificationkey`, into the key registry.
+
+use std::path::Path;
+use std::process::ExitCode;
+use ark_serialize::CanonicalSerialize;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use soulana_backend::zk::ceremony::{self, CeremonyParams};
+use soulana_backend::zk::circuits::{CircuitError, CircuitId};
+use soulana_backend::zk::keys::{read_key_file_versioned, write_key_file_for, KeyKind};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::snarkjs::SnarkjsVerifyingKey;
+use soulana_backend::zk::CircuitVerifier;
+
+const USAGE: &str = "usage:
+  soulana-zk-setup init <ptau> <out> [circuit-id]
+  soulana-zk-setup contribute <in> <out> <name>
+  soulana-zk-setup beacon <in> <out> <beacon-hex> <iterations>
+  soulana-zk-setup verify <before> <after>
+  soulana-zk-setup verify-transcript <ptau> <params>
+  soulana-zk-setup export <ptau> <params> <keys-dir>
+  soulana-zk-setup export-solana <keys-dir> <circuit-id> <out.rs>
+  soulana-zk-setup import-snarkjs <verification_key.json> <keys-dir> <circuit-id>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["init", ptau, out] => init(ptau, out, &CircuitId::current().to_string()),
+        ["init", ptau, out, circuit] => init(ptau, out, circuit),
+        ["contribute", input, out, name] => contribute(input, out, name),
+        ["beacon", input, out, seed, iterations] => beacon(input, out, seed, iterations),
+        ["verify", before, after] => verify(before, after),
+        ["verify-transcript", ptau, params] => verify_transcript(ptau, params),
+        ["export", ptau, params, keys_dir] => export(ptau, params, keys_dir),
+        ["export-solana", keys_dir, circuit, out] => export_solana(keys_dir, circuit, out),
+        ["import-snarkjs", json, keys_dir, circuit] => import_snarkjs(json, keys_dir, circuit),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(path: &str) -> Result<CeremonyParams, String> {
+    let (version, bytes) = read_key_file_versioned(Path::new(path), KeyKind::Ceremony).map_err(|e| e.to_string())?;
+    let params = CeremonyParams::from_bytes(&bytes).map_err(|e| e.to_string())?;
+    let circuit = params.circuit_id().map_err(|e| e.to_string())?;
+    if circuit.version != version {
+        return Err(format!("{} holds {} keys but is labelled circuit version {}", path, circuit, version));
+    }
+    Ok(params)
+}
+
+fn save(path: &str, params: &CeremonyParams) -> Result<(), String> {
+    let circuit = params.circuit_id().map_err(|e| e.to_string())?;
+    write_key_file_for(Path::new(path), KeyKind::Ceremony, circuit.version, &params.to_bytes())
+        .map_err(|e| e.to_string())
+}
+
+/// Reads a `.ptau` file, printing its hash to compare with the one its ceremony published.
+fn load_ptau(path: &str) -> Result<PowersOfTau, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let ptau = PowersOfTau::read(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+    println!("Powers of tau 2^{} from {}, sha256 {}", ptau.power, path, hex::encode(Sha256::digest(&bytes)));
+    Ok(ptau)
+}
+
+fn init(ptau: &str, out: &str, circuit: &str) -> Result<(), String> {
+    let circuit: CircuitId = circuit.parse().map_err(|e: CircuitError| e.to_string())?;
+    let ptau = load_ptau(ptau)?;
+    let params = ceremony::init_for(&circuit, &ptau).map_err(|e| e.to_string())?;
+    save(out, &params)?;
+    println!("Initialized {} ceremony in {}", circuit, out);
+    println!("Transcript hash: {}", hex::encode(params.transcript_hash()));
+    Ok(())
+}
+
+fn contribute(input: &str, out: &str, name: &str) -> Result<(), String> {
+    let mut params = load(input)?;
+    let hash = ceremony::contribute(&mut params, name, &mut OsRng);
+    save(out, &params)?;
+    println!("Contribution #{} by {} written to {}", params.contributions.len(), name, out);
+    println!("Transcript hash: {}", hex::encode(hash));
+    Ok(())
+}
+
+fn beacon(input: &str, out: &str, beacon: &str, iterations: &str) -> Result<(), String> {
+    let beacon = hex::decode(beacon).map_err(|e| format!("Invalid beacon: {}", e))?;
+    let iterations: u8 = iterations.parse().map_err(|_| "Iterations must be a small number")?;
+
+    let mut params = load(input)?;
+    let hash = ceremony::contribute_beacon(&mut params, &beacon, iterations).map_err(|e| e.to_string())?;
+    save(out, &params)?;
+    println!("Beacon contribution written to {}", out);
+    println!("Transcript hash: {}", hex::encode(hash));
+    Ok(())
+}
+
+fn verify(before: &str, after: &str) -> Result<(), String> {
+    let before = load(before)?;
+    let after = load(after)?;
+    ceremony::verify_contribution(&before, &after).map_err(|e| e.to_string())?;
+    let last = after.contributions.last().ok_or("No contribution to verify")?;
+    println!("Contribution by {} is valid", last.name);
+    println!("Transcript hash: {}", hex::encode(last.transcript_hash));
+    Ok(())
+}
+
+fn verify_transcript(ptau: &str, path: &str) -> Result<(), String> {
+    let ptau = load_ptau(ptau)?;
+    let params = load(path)?;
+    ceremony::verify_transcript(&params, &ptau).map_err(|e| e.to_string())?;
+    for (i, contribution) in params.contributions.iter().enumerate() {
+        println!("#{} {} {}", i + 1, hex::encode(contribution.transcript_hash), contribution.name);
+    }
+    println!("Transcript is valid");
+    Ok(())
+}
+
+fn export(ptau: &str, path: &str, keys_dir: &str) -> Result<(), String> {
+    let ptau = load_ptau(ptau)?;
+    let params = load(path)?;
+    ceremony::verify_transcript(&params, &ptau).map_err(|e| e.to_string())?;
+
+    let mut proving_key = Vec::new();
+    params.proving_key.serialize_compressed(&mut proving_key).unwrap();
+    let mut verifying_key = Vec::new();
+    params.proving_key.vk.serialize_compressed(&mut verifying_key).unwrap();
+
+    let circuit = params.circuit_id().map_err(|e| e.to_string())?;
+    let dir = circuit.key_dir(Path::new(keys_dir));
+    write_key_file_for(&dir.join("proving_key.bin"), KeyKind::Proving, circuit.version, &proving_key)
+        .map_err(|e| e.to_string())?;
+    write_key_file_for(&dir.join("verifying_key.bin"), KeyKind::Verifying, circuit.version, &verifying_key)
+        .map_err(|e| e.to_string())?;
+    println!("Exported {} keys for transcript {} to {}", circuit, hex::encode(params.transcript_hash()), dir.display());
+    Ok(())
+}
+
+fn export_solana(keys_dir: &str, circuit: &str, out: &str) -> Result<(), String> {
+    let circuit: CircuitId = circuit.parse().map_err(|e: CircuitError| e.to_string())?;
+    let path = circuit.key_dir(Path::new(keys_dir)).join("verifying_key.bin");
+    let verifier = CircuitVerifier::load(&path, circuit.version).map_err(|e| e.to_string())?;
+    let vk = verifier.solana_verifying_key();
+    std::fs::write(out, vk.to_rust_source("VERIFYINGKEY")).map_err(|e| format!("Failed to write {}: {}", out, e))?;
+    println!("Exported {} verifying key with {} public inputs to {}", circuit, vk.public_inputs(), out);
+    Ok(())
+}
+
+fn import_snarkjs(json: &str, keys_dir: &str, circuit: &str) -> Result<(), String> {
+    let circuit: CircuitId = circuit.parse().map_err(|e: CircuitError| e.to_string())?;
+    let contents = std::fs::read_to_string(json).map_err(|e| format!("Failed to read {}: {}", json, e))?;
+    let vk = SnarkjsVerifyingKey::from_json(&contents)
+        .and_then(|vk| vk.to_vk())
+        .map_err(|e| format!("{}: {}", json, e))?;
+
+    let mut verifying_key = Vec::new();
+    vk.serialize_compressed(&mut verifying_key).unwrap();
+    let path = circuit.key_dir(Path::new(keys_dir)).join("verifying_key.bin");
+    write_key_file_for(&path, KeyKind::Verifying, circuit.version, &verifying_key).map_err(|e| e.to_string())?;
+    println!("Imported {} verifying key with {} public inputs to {}", circuit, vk.gamma_abc_g1.len() - 1, path.display());
+    Ok(())
+}
@@ -0,0 +1,40 @@
+//! The address a request came from, which per-client limits are keyed on.
+//!
+//! Behind a reverse proxy every request arrives from the proxy, so the address is read
+//! from the header named by `[server] client_ip_header` when one is configured, and from
+//! the peer address otherwise.
+
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+use actix_web::dev::Payload;
+use actix_web::http::header::HeaderName;
+use actix_web::{web, FromRequest, HttpRequest};
+
+/// Header a trusted reverse proxy puts the client address in, registered as app data.
+/// Only set it when every request comes through that proxy, or clients choose their own
+/// address.
+#[derive(Debug, Clone, Default)]
+pub struct ClientIpHeader(pub Option<HeaderName>);
+
+/// Address of the client that sent the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIp(pub String);
+
+impl FromRequest for ClientIp {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Infallible>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let forwarded = req
+            .app_data::<web::Data<ClientIpHeader>>()
+            .and_then(|header| header.0.as_ref())
+            .and_then(|header| req.headers().get_all(header).last())
+            .and_then(|value| value.to_str().ok())
+            // proxies append the address they saw, so the last one is the one ours vouches for
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse::<IpAddr>().ok());
+        let ip = forwarded.or_else(|| req.peer_addr().map(|addr| addr.ip()));
+        ready(Ok(Self(ip.map(|ip| ip.to_string()).unwrap_or_default())))
+    }
+}
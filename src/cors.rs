@@ -0,0 +1,62 @@
+//! CORS for the origins in `[cors] allowed_origins`.
+//!
+//! Preflight requests from allowed origins are answered here, for every route. Responses
+//! that already set `Access-Control-Allow-Origin` are left alone.
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::http::Method;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse};
+use crate::config::CorsConfig;
+use crate::error::REQUEST_ID_HEADER;
+
+const ALLOWED_METHODS: &str = "GET, POST, DELETE, OPTIONS";
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, x-request-id";
+const MAX_AGE_SECS: &str = "3600";
+
+pub async fn cors(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let config = req.app_data::<web::Data<CorsConfig>>().cloned();
+    let origin = req.headers().get(header::ORIGIN).cloned();
+    let allowed = match (&config, &origin) {
+        (Some(config), Some(origin)) => origin
+            .to_str()
+            .ok()
+            .filter(|origin| config.allows(origin))
+            .map(|_| if config.allows_any() { HeaderValue::from_static("*") } else { origin.clone() }),
+        _ => None,
+    };
+    let Some(allow_origin) = allowed else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    if req.method() == Method::OPTIONS && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+        let mut preflight = HttpResponse::NoContent();
+        preflight
+            .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, ALLOWED_METHODS))
+            .insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, ALLOWED_HEADERS))
+            .insert_header((header::ACCESS_CONTROL_MAX_AGE, MAX_AGE_SECS));
+        let mut res = req.into_response(preflight.finish());
+        allow(res.headers_mut(), allow_origin);
+        return Ok(res.map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    if !res.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN) {
+        allow(res.headers_mut(), allow_origin);
+    }
+    Ok(res.map_into_left_body())
+}
+
+fn allow(headers: &mut HeaderMap, origin: HeaderValue) {
+    if origin != "*" {
+        // the answer depends on the origin, so caches must keep them apart
+        headers.insert(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(REQUEST_ID_HEADER));
+}
@@ -0,0 +1,318 @@
+//! Errors handlers return, and how they are answered.
+//!
+//! Every [`AppError`] is answered with `{code, message, request_id}`. `code` is stable and
+//! meant for clients to match on, `message` is for humans, and `request_id` matches the
+//! `x-request-id` header set by [`request_id`], so a report can be found in the logs.
+//! Internal failures are logged in full but answered with a generic message.
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::BlockingError;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::middleware::Next;
+use actix_web::{web, HttpResponse, ResponseError};
+use serde::Serialize;
+use solana_client::client_error::ClientError;
+use uuid::Uuid;
+use crate::repos::RepoError;
+use crate::services::challenges::ChallengeError;
+use crate::services::donation_commitments::DonationCommitmentError;
+use crate::services::donations::DonationError;
+use crate::services::email_commitments::EmailCommitmentError;
+use crate::services::email_verification::EmailVerificationError;
+use crate::services::membership::MembershipError;
+use crate::services::pledges::FulfilmentError;
+use crate::services::wallet_signatures::WalletSignatureError;
+use crate::zk::circuits::CircuitError;
+use crate::zk::error::ZkError;
+
+/// Header a request id is read from and echoed in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request id accepted from a client, anything else is replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    BadRequest(String),
+    #[error("{0}")]
+    Validation(String),
+    #[error("{0}")]
+    Conflict(String),
+    #[error("Solana RPC error: {0}")]
+    Rpc(Box<ClientError>),
+    #[error(transparent)]
+    Zk(#[from] ZkError),
+    #[error("{0}")]
+    Unauthorized(String),
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("{0}")]
+    TooManyRequests(String),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("Failed to sign auth token: {0}")]
+    Token(#[from] jsonwebtoken::errors::Error),
+    #[error("Blocking task failed: {0}")]
+    Blocking(#[from] BlockingError),
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::NotFound(message.into())
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::Validation(message.into())
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::Conflict(message.into())
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::Unauthorized(message.into())
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::Forbidden(message.into())
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Pool(_) => "database_unavailable",
+            Self::Db(_) => "database_error",
+            Self::NotFound(_) => "not_found",
+            Self::BadRequest(_) => "bad_request",
+            Self::Validation(_) => "invalid_request",
+            Self::Conflict(_) => "conflict",
+            Self::Rpc(_) => "rpc_error",
+            Self::Zk(error) => error.code(),
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::TooManyRequests(_) => "too_many_requests",
+            Self::Unavailable(_) => "unavailable",
+            Self::Token(_) | Self::Blocking(_) | Self::Internal(_) => "internal_error",
+        }
+    }
+
+    /// What the client is told; internal details stay in the logs.
+    pub fn message(&self) -> String {
+        match self {
+            Self::Pool(_) => "Database connection error".to_string(),
+            Self::Db(_) => "Database error".to_string(),
+            Self::Rpc(_) => "Solana RPC request failed".to_string(),
+            Self::Zk(ZkError::Proving(_)) => "Failed to create proof".to_string(),
+            Self::Token(_) | Self::Blocking(_) | Self::Internal(_) => "Internal server error".to_string(),
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl From<ClientError> for AppError {
+    fn from(error: ClientError) -> Self {
+        Self::Rpc(Box::new(error))
+    }
+}
+
+impl From<CircuitError> for AppError {
+    fn from(error: CircuitError) -> Self {
+        Self::Zk(error.into())
+    }
+}
+
+impl From<RepoError> for AppError {
+    fn from(error: RepoError) -> Self {
+        match error {
+            RepoError::Pool(e) => Self::Pool(e),
+            RepoError::Db(e) => Self::Db(e),
+            RepoError::Blocking(e) => Self::Blocking(e),
+        }
+    }
+}
+
+impl From<DonationError> for AppError {
+    fn from(error: DonationError) -> Self {
+        match error {
+            DonationError::ProjectNotFound => Self::NotFound(error.to_string()),
+            DonationError::AlreadyRecorded => Self::Conflict(error.to_string()),
+            DonationError::NotTheDonor => Self::Forbidden(error.to_string()),
+            DonationError::NotConfirmed | DonationError::Failed(_) | DonationError::NotADonation => {
+                Self::Validation(error.to_string())
+            }
+            DonationError::Rpc(e) => Self::Rpc(e),
+            DonationError::Pool(e) => Self::Pool(e),
+            DonationError::Db(e) => Self::Db(e),
+            DonationError::Blocking(e) => Self::Blocking(e),
+        }
+    }
+}
+
+impl From<FulfilmentError> for AppError {
+    fn from(error: FulfilmentError) -> Self {
+        Self::Validation(error.to_string())
+    }
+}
+
+impl From<WalletSignatureError> for AppError {
+    fn from(error: WalletSignatureError) -> Self {
+        match error {
+            WalletSignatureError::MalformedChallenge => Self::Validation(error.to_string()),
+            WalletSignatureError::InvalidChallenge | WalletSignatureError::InvalidSignature => {
+                Self::Unauthorized(error.to_string())
+            }
+        }
+    }
+}
+
+impl From<ChallengeError> for AppError {
+    fn from(error: ChallengeError) -> Self {
+        Self::TooManyRequests(error.to_string())
+    }
+}
+
+impl From<MembershipError> for AppError {
+    fn from(error: MembershipError) -> Self {
+        match error {
+            MembershipError::UnknownIdentity => Self::NotFound(error.to_string()),
+            MembershipError::AlreadyRegistered
+            | MembershipError::DuplicateCommitment
+            | MembershipError::NullifierUsed => Self::Conflict(error.to_string()),
+            MembershipError::GroupFull => Self::Unavailable(error.to_string()),
+            MembershipError::CorruptCommitment(_) => Self::Internal(error.to_string()),
+            MembershipError::Pool(e) => Self::Pool(e),
+            MembershipError::Db(e) => Self::Db(e),
+            MembershipError::Blocking(e) => Self::Blocking(e),
+        }
+    }
+}
+
+impl From<EmailCommitmentError> for AppError {
+    fn from(error: EmailCommitmentError) -> Self {
+        match error {
+            EmailCommitmentError::Pool(e) => Self::Pool(e),
+            EmailCommitmentError::Db(e) => Self::Db(e),
+        }
+    }
+}
+
+impl From<EmailVerificationError> for AppError {
+    fn from(error: EmailVerificationError) -> Self {
+        match error {
+            EmailVerificationError::TooSoon | EmailVerificationError::Full => Self::TooManyRequests(error.to_string()),
+            EmailVerificationError::InvalidCode => Self::Unauthorized(error.to_string()),
+            EmailVerificationError::Send(_) => Self::Internal(error.to_string()),
+        }
+    }
+}
+
+impl From<DonationCommitmentError> for AppError {
+    fn from(error: DonationCommitmentError) -> Self {
+        match error {
+            DonationCommitmentError::Pool(e) => Self::Pool(e),
+            DonationCommitmentError::Db(e) => Self::Db(e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    pub request_id: String,
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::BadRequest(_) | Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Rpc(_) => StatusCode::BAD_GATEWAY,
+            Self::Zk(error) => match error {
+                ZkError::PairingCheckFailed => StatusCode::UNAUTHORIZED,
+                ZkError::Circuit(CircuitError::Retired(..)) => StatusCode::GONE,
+                ZkError::KeyNotLoaded(_) => StatusCode::SERVICE_UNAVAILABLE,
+                ZkError::Proving(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                ZkError::MalformedBase64
+                | ZkError::MalformedProof
+                | ZkError::WrongProofSystem { .. }
+                | ZkError::Circuit(_)
+                | ZkError::PublicInputs(_)
+                | ZkError::InputCount { .. }
+                | ZkError::Snarkjs(_) => StatusCode::BAD_REQUEST,
+            },
+            Self::Pool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Db(_) | Self::Token(_) | Self::Blocking(_) | Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let request_id = current_request_id();
+        let status = self.status_code();
+        if status.is_server_error() {
+            log::error!("[{}] Request failed: {:?}", request_id, self);
+        } else {
+            log::debug!("[{}] Request rejected: {}", request_id, self.code());
+        }
+        HttpResponse::build(status).json(ErrorBody {
+            code: self.code(),
+            message: self.message(),
+            request_id,
+        })
+    }
+}
+
+/// Answers requests the JSON, path and query extractors reject with an [`AppError`] body,
+/// like every other error, instead of actix's plain text.
+pub fn configure_extractors(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::JsonConfig::default().error_handler(|e, _| AppError::BadRequest(e.to_string()).into()))
+        .app_data(web::PathConfig::default().error_handler(|e, _| AppError::BadRequest(e.to_string()).into()))
+        .app_data(web::QueryConfig::default().error_handler(|e, _| AppError::BadRequest(e.to_string()).into()));
+}
+
+/// Id of the request being handled, or a fresh one outside of [`request_id`].
+pub fn current_request_id() -> String {
+    REQUEST_ID
+        .try_with(|id| id.clone())
+        .unwrap_or_else(|_| Uuid::new_v4().to_string())
+}
+
+/// Middleware giving every request an id, taken from `x-request-id` when the client (or
+/// a proxy in front of us) sent a usable one, and echoing it in the response.
+pub async fn request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= MAX_REQUEST_ID_LEN)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let mut res = REQUEST_ID.scope(id.clone(), next.call(req)).await?;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        res.headers_mut().insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
@@ -0,0 +1,17 @@
+use diesel::prelude::*;
+use diesel::r2d2::{self, ConnectionManager};
+
+pub mod client_ip;
+pub mod config;
+pub mod cors;
+pub mod error;
+pub mod metrics;
+pub mod migrations;
+pub mod routes;
+pub mod schema;
+pub mod models;
+pub mod repos;
+pub mod services;
+pub mod zk;
+
+pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
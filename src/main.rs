@@ -1,47 +1,176 @@
-use actix_web::{web, App, HttpServer};
+use actix_web::{middleware, web, App, HttpServer};
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use dotenv::dotenv;
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use env_logger::Env;
-use crate::zk::real::RealZKVerifier;
-
-mod routes;
-mod schema;
-mod models;
-mod services;
-mod zk;
-
-pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+use soulana_backend::{cors, error, metrics, migrations, routes, services};
+use soulana_backend::config::Config;
+use soulana_backend::zk;
+use soulana_backend::zk::membership::MEMBERSHIP_DEPTH;
+use soulana_backend::services::notifier::{
+    CodeSender, LogNotifier, NoCodeSender, Notifiers, WebhookNotifier, EMAIL_CHANNEL, WEBHOOK_CHANNEL,
+};
+use soulana_backend::repos::{IdentityRepo, PledgeRepo, ProjectRepo, UserRepo};
+use soulana_backend::services::donations::SignatureKey;
+use soulana_backend::services::cache::ProjectCache;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::donation_commitments::{DonationCommitmentStore, PgDonationCommitmentStore};
+use soulana_backend::services::email_commitments::{EmailCommitmentStore, PgEmailCommitmentStore};
+use soulana_backend::services::email_verification::{EmailVerifications, CODE_TTL};
+use soulana_backend::services::membership::{MembershipGroup, NullifierStore, PgNullifierStore};
+use soulana_backend::services::pledges::PledgeScheduler;
+use soulana_backend::services::readiness::ReadinessChecks;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init_from_env(Env::default().default_filter_or("debug"));
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let jwt_secret = env::var("JWT_SECRET").unwrap_or_else(|_| "test_secret_key".to_string());
+    // --migrate-only applies pending migrations and exits, for deploy pipelines
+    let migrate_only = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("--migrate-only") => true,
+        Some(other) => {
+            eprintln!("Unknown argument '{}', usage: soulana-backend [--migrate-only]", other);
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "unknown argument"));
+        }
+    };
+
+    let config = Config::load().map_err(|e| {
+        eprintln!("Refusing to start: {}", e);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?;
+    log::info!("Running in {:?} mode against {}", config.environment, config.solana.cluster.rpc_url());
+    if config.uses_dev_jwt_secret() {
+        log::warn!("JWT_SECRET not set, using the development secret");
+    }
+    if config.uses_dev_donation_signature_key() {
+        log::warn!("DONATION_SIGNATURE_KEY not set, using the development key");
+    }
 
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
+    let manager = ConnectionManager::<PgConnection>::new(config.database.url.clone());
     let pool = r2d2::Pool::builder()
+        .max_size(config.database.pool_size)
         .build(manager)
         .expect("Failed to create pool");
 
-    let auth_service = web::Data::new(services::auth::AuthService::new(pool.clone(), jwt_secret));
+    // nothing may touch the database before its schema matches the code
+    let applied = migrations::prepare(&pool, migrate_only || config.database.run_migrations).map_err(|e| {
+        eprintln!("Refusing to start: {}", e);
+        std::io::Error::other(e.to_string())
+    })?;
+    if !applied.is_empty() {
+        log::info!("Applied migrations: {}", applied.join(", "));
+    }
+    if migrate_only {
+        log::info!("Database schema is up to date");
+        return Ok(());
+    }
+
+    let auth_service = web::Data::new(services::auth::AuthService::new(pool.clone(), config.auth.jwt_secret.clone()));
     
-    // create real zk verifier
-    let zk_verifier = web::Data::new(RealZKVerifier::new());
+    // the mock backend skips the Groth16 setup for local development
+    log::info!("Using {:?} ZK backend", config.zk.backend);
+    let (zk_verifier, zk_prover) = zk::build_backend(config.zk.backend, &config.zk.keys_dir).map_err(|e| {
+        eprintln!("Refusing to start: {}", e);
+        std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string())
+    })?;
+    let zk_verifier = web::Data::from(zk_verifier);
+    let zk_prover = web::Data::from(zk_prover);
+
+    // pledge reminders are delivered by email when SMTP is configured
+    let notifiers = Notifiers::new().register(WEBHOOK_CHANNEL, WebhookNotifier::new());
+    // and so are the codes proving an email login owns its address
+    let email_notifier = config.smtp.email_notifier().map_err(|e| {
+        eprintln!("Refusing to start: {}", e);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?;
+    let (notifiers, code_sender): (_, Arc<dyn CodeSender>) = match email_notifier {
+        Some(email) => (notifiers.register(EMAIL_CHANNEL, email.clone()), Arc::new(email)),
+        None => {
+            log::warn!("SMTP_URL/SMTP_FROM not set, pledge emails will only be logged and email commitments can't be issued");
+            (notifiers.register(EMAIL_CHANNEL, LogNotifier), Arc::new(NoCodeSender))
+        }
+    };
+    let notifiers = web::Data::from(Arc::new(notifiers));
+    let code_sender = web::Data::from(code_sender);
+    let email_verifications = web::Data::new(EmailVerifications::new(CODE_TTL));
+
+    PledgeScheduler::new(pool.clone(), notifiers.clone().into_inner(), config.server.public_base_url.clone())
+        .start(Duration::from_secs(60));
+
+    // project stats and donor leaderboards are cached briefly
+    let project_cache = web::Data::new(ProjectCache::<serde_json::Value>::new(Duration::from_secs(60)));
+    let challenges = web::Data::new(ChallengeStore::new(Duration::from_secs(300)));
+
+    // identities that registered a commitment form the membership group
+    let membership_group = {
+        let mut conn = pool.get().expect("Failed to get a database connection");
+        MembershipGroup::load(&mut conn, MEMBERSHIP_DEPTH).expect("Failed to load the membership group")
+    };
+    log::info!("Membership group has {} members", membership_group.size());
+    let membership_group = web::Data::new(membership_group);
+    let nullifiers: Arc<dyn NullifierStore> = Arc::new(PgNullifierStore::new(pool.clone()));
+    let nullifiers = web::Data::from(nullifiers);
+    let email_commitments: Arc<dyn EmailCommitmentStore> = Arc::new(PgEmailCommitmentStore::new(pool.clone()));
+    let email_commitments = web::Data::from(email_commitments);
+    let donation_commitments: Arc<dyn DonationCommitmentStore> = Arc::new(PgDonationCommitmentStore::new(pool.clone()));
+    let donation_commitments = web::Data::from(donation_commitments);
+
+    let projects = web::Data::new(ProjectRepo::new(pool.clone(), SignatureKey::new(&config.donations.signature_key)));
+    let pledges = web::Data::new(PledgeRepo::new(pool.clone()));
+    let users = web::Data::new(UserRepo::new(pool.clone()));
+    let identities = web::Data::new(IdentityRepo::new(pool.clone()));
+
+    let cluster = web::Data::new(config.solana.cluster.clone());
+    let readiness = web::Data::new(ReadinessChecks::new(
+        pool.clone(),
+        zk_verifier.clone().into_inner(),
+        config.solana.cluster.clone(),
+    ));
+    let cors_config = web::Data::new(config.cors.clone());
+    let client_ip_header = web::Data::new(config.server.client_ip_header().map_err(|e| {
+        eprintln!("Refusing to start: {}", e);
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+    })?);
 
-    println!("Server running at http://localhost:8080");
+    log::info!("Server running at http://{}", config.server.bind_address);
 
-    HttpServer::new(move || {
+    let server = HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(cors::cors))
+            .wrap(middleware::from_fn(error::request_id))
+            .wrap(middleware::from_fn(metrics::track_requests))
             .app_data(web::Data::new(pool.clone()))
+            .app_data(projects.clone())
+            .app_data(pledges.clone())
+            .app_data(users.clone())
+            .app_data(identities.clone())
+            .app_data(cluster.clone())
+            .app_data(readiness.clone())
+            .app_data(cors_config.clone())
+            .app_data(client_ip_header.clone())
             .app_data(auth_service.clone())
             .app_data(zk_verifier.clone())
+            .app_data(zk_prover.clone())
+            .app_data(notifiers.clone())
+            .app_data(project_cache.clone())
+            .app_data(challenges.clone())
+            .app_data(membership_group.clone())
+            .app_data(nullifiers.clone())
+            .app_data(email_commitments.clone())
+            .app_data(email_verifications.clone())
+            .app_data(code_sender.clone())
+            .app_data(donation_commitments.clone())
+            .configure(error::configure_extractors)
             .configure(routes::configure_routes)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    });
+    let server = match config.server.workers {
+        Some(workers) => server.workers(workers),
+        None => server,
+    };
+    server.bind(&config.server.bind_address)?.run().await
 }
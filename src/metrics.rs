@@ -0,0 +1,181 @@
+//! Prometheus metrics, served at `/metrics`.
+//!
+//! Metrics live in one process-wide registry, so the request middleware, the ZK backend and
+//! the Solana RPC call sites record them without being handed anything. The database pool is
+//! sampled when scraped.
+
+use std::sync::OnceLock;
+use std::time::Instant;
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use crate::DbPool;
+
+/// Route label of requests no route matched, so scanners can't blow up the label set.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    db_connections: IntGaugeVec,
+    db_max_connections: IntGauge,
+    zk_proofs: IntCounterVec,
+    zk_duration: HistogramVec,
+    rpc_requests: IntCounterVec,
+    rpc_duration: HistogramVec,
+    donation_transactions: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "Time taken to answer HTTP requests"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            db_connections: IntGaugeVec::new(
+                Opts::new("db_pool_connections", "Database connections held by the pool"),
+                &["state"],
+            )
+            .unwrap(),
+            db_max_connections: IntGauge::new("db_pool_max_connections", "Size limit of the database pool").unwrap(),
+            zk_proofs: IntCounterVec::new(
+                Opts::new("zk_proofs_total", "Proofs verified or created"),
+                &["operation", "kind", "outcome"],
+            )
+            .unwrap(),
+            zk_duration: HistogramVec::new(
+                HistogramOpts::new("zk_proof_duration_seconds", "Time taken to verify or create proofs")
+                    .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+                &["operation", "kind"],
+            )
+            .unwrap(),
+            rpc_requests: IntCounterVec::new(
+                Opts::new("solana_rpc_requests_total", "Solana RPC requests made"),
+                &["method", "outcome"],
+            )
+            .unwrap(),
+            rpc_duration: HistogramVec::new(
+                HistogramOpts::new("solana_rpc_request_duration_seconds", "Time taken by Solana RPC requests"),
+                &["method"],
+            )
+            .unwrap(),
+            donation_transactions: IntCounterVec::new(
+                Opts::new("donation_transactions_built_total", "Unsigned donation transactions handed to wallets"),
+                &["project_id"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 9] = [
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_duration.clone()),
+            Box::new(metrics.db_connections.clone()),
+            Box::new(metrics.db_max_connections.clone()),
+            Box::new(metrics.zk_proofs.clone()),
+            Box::new(metrics.zk_duration.clone()),
+            Box::new(metrics.rpc_requests.clone()),
+            Box::new(metrics.rpc_duration.clone()),
+            Box::new(metrics.donation_transactions.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+        metrics
+    }
+}
+
+fn metrics() -> &'static Metrics {
+    static METRICS: OnceLock<Metrics> = OnceLock::new();
+    METRICS.get_or_init(Metrics::new)
+}
+
+fn outcome(ok: bool) -> &'static str {
+    if ok {
+        "ok"
+    } else {
+        "error"
+    }
+}
+
+/// Records a proof verified (`operation` "verify") or created ("prove") since `started`.
+pub fn observe_proof(operation: &str, kind: &str, started: Instant, ok: bool) {
+    let metrics = metrics();
+    metrics.zk_proofs.with_label_values(&[operation, kind, outcome(ok)]).inc();
+    metrics
+        .zk_duration
+        .with_label_values(&[operation, kind])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+/// Records a Solana RPC request to `method` made since `started`.
+pub fn observe_rpc(method: &str, started: Instant, ok: bool) {
+    let metrics = metrics();
+    metrics.rpc_requests.with_label_values(&[method, outcome(ok)]).inc();
+    metrics
+        .rpc_duration
+        .with_label_values(&[method])
+        .observe(started.elapsed().as_secs_f64());
+}
+
+pub fn donation_transaction_built(project_id: i32) {
+    metrics()
+        .donation_transactions
+        .with_label_values(&[&project_id.to_string()])
+        .inc();
+}
+
+/// Middleware counting and timing requests by method, route pattern and status.
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let res = next.call(req).await?;
+
+    let route = res
+        .request()
+        .match_pattern()
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let status = res.status().as_str().to_string();
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    let metrics = metrics();
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics
+        .http_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    Ok(res)
+}
+
+/// Every metric in the Prometheus text format, with the pool state sampled now.
+pub fn render(pool: &DbPool) -> String {
+    let metrics = metrics();
+    let state = pool.state();
+    let idle = i64::from(state.idle_connections);
+    metrics.db_connections.with_label_values(&["idle"]).set(idle);
+    metrics
+        .db_connections
+        .with_label_values(&["in_use"])
+        .set(i64::from(state.connections) - idle);
+    metrics.db_max_connections.set(i64::from(pool.max_size()));
+
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&metrics.registry.gather(), &mut buffer)
+        .expect("metrics are valid text");
+    String::from_utf8(buffer).expect("metrics are valid UTF-8")
+}
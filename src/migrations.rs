@@ -0,0 +1,68 @@
+//! Database migrations, embedded in the binary.
+//!
+//! The server applies pending migrations on startup when `database.run_migrations` is set,
+//! and `--migrate-only` applies them and exits, for deploy pipelines. Either way it refuses
+//! to serve traffic while the schema is behind what the code expects.
+
+use diesel::pg::Pg;
+use diesel::PgConnection;
+use diesel::migration::MigrationSource;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use crate::DbPool;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+type HarnessError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Failed to run migrations: {0}")]
+    Harness(HarnessError),
+    #[error("Database schema is behind, pending migrations: {}", .0.join(", "))]
+    Pending(Vec<String>),
+}
+
+impl From<HarnessError> for MigrationError {
+    fn from(error: HarnessError) -> Self {
+        Self::Harness(error)
+    }
+}
+
+/// Names of every embedded migration, oldest first.
+pub fn embedded() -> Result<Vec<String>, MigrationError> {
+    let migrations = MigrationSource::<Pg>::migrations(&MIGRATIONS)?;
+    Ok(migrations.iter().map(|migration| migration.name().to_string()).collect())
+}
+
+/// Names of the embedded migrations the database hasn't applied yet, oldest first.
+pub fn pending(conn: &mut PgConnection) -> Result<Vec<String>, MigrationError> {
+    let migrations = conn.pending_migrations(MIGRATIONS)?;
+    Ok(migrations.iter().map(|migration| migration.name().to_string()).collect())
+}
+
+/// Applies the pending migrations in one go, returning the versions applied.
+pub fn run_pending(conn: &mut PgConnection) -> Result<Vec<String>, MigrationError> {
+    let applied = conn.run_pending_migrations(MIGRATIONS)?;
+    Ok(applied.iter().map(|version| version.to_string()).collect())
+}
+
+/// Fails with the pending migrations unless the schema is up to date.
+pub fn ensure_up_to_date(conn: &mut PgConnection) -> Result<(), MigrationError> {
+    let pending = pending(conn)?;
+    if pending.is_empty() {
+        Ok(())
+    } else {
+        Err(MigrationError::Pending(pending))
+    }
+}
+
+/// What startup does with the schema: applies pending migrations if `apply` is set, then
+/// checks nothing is left. Returns the versions applied.
+pub fn prepare(pool: &DbPool, apply: bool) -> Result<Vec<String>, MigrationError> {
+    let mut conn = pool.get()?;
+    let applied = if apply { run_pending(&mut conn)? } else { Vec::new() };
+    ensure_up_to_date(&mut conn)?;
+    Ok(applied)
+}
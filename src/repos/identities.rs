@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use ark_bn254::Fr;
+use diesel::prelude::*;
+use uuid::Uuid;
+use crate::DbPool;
+use crate::schema::{email_identities, wallet_identities};
+use crate::services::membership::{MembershipError, MembershipGroup};
+use super::{run, RepoError};
+
+/// Identities and the wallets and emails they log in with.
+pub struct IdentityRepo {
+    pool: DbPool,
+}
+
+impl IdentityRepo {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    /// The identity the wallet address or email `subject` belongs to.
+    pub async fn find_by_subject(&self, subject: &str) -> Result<Option<Uuid>, RepoError> {
+        let subject = subject.to_string();
+        run(&self.pool, move |conn| Ok(find_by_subject(conn, &subject)?)).await
+    }
+
+    /// Registers `commitment` for the identity of `subject` in `group`, returning its leaf
+    /// index and the new root, see [`MembershipGroup::register`].
+    pub async fn register_commitment(
+        &self,
+        group: Arc<MembershipGroup>,
+        subject: &str,
+        commitment: Fr,
+    ) -> Result<(usize, Fr), MembershipError> {
+        let subject = subject.to_string();
+        run(&self.pool, move |conn| {
+            let leaf_index = group.register(conn, &subject, &commitment)?;
+            Ok((leaf_index, group.root()))
+        })
+        .await
+    }
+}
+
+/// The identity a wallet address or email belongs to.
+pub(crate) fn find_by_subject(conn: &mut PgConnection, subject: &str) -> Result<Option<Uuid>, diesel::result::Error> {
+    let wallet: Option<Option<Uuid>> = wallet_identities::table
+        .filter(wallet_identities::wallet_address.eq(subject))
+        .select(wallet_identities::identity_id)
+        .first(conn)
+        .optional()?;
+    if let Some(identity_id) = wallet.flatten() {
+        return Ok(Some(identity_id));
+    }
+
+    let email: Option<Option<Uuid>> = email_identities::table
+        .filter(email_identities::email.eq(subject))
+        .select(email_identities::identity_id)
+        .first(conn)
+        .optional()?;
+    Ok(email.flatten())
+}
@@ -0,0 +1,45 @@
+//! Database access for the route handlers.
+//!
+//! Diesel is synchronous, so every repository method checks out a pooled connection and
+//! runs its queries on actix's blocking thread pool; handlers only ever await them.
+
+use actix_web::error::BlockingError;
+use actix_web::web;
+use diesel::r2d2::PoolError;
+use diesel::PgConnection;
+use crate::DbPool;
+
+pub mod identities;
+pub mod pledges;
+pub mod projects;
+pub mod users;
+
+pub use identities::IdentityRepo;
+pub use pledges::PledgeRepo;
+pub use projects::ProjectRepo;
+pub use users::UserRepo;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RepoError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+    #[error("Blocking task failed: {0}")]
+    Blocking(#[from] BlockingError),
+}
+
+/// Runs `f` with a connection from `pool` on the blocking thread pool.
+async fn run<T, E, F>(pool: &DbPool, f: F) -> Result<T, E>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: From<PoolError> + From<BlockingError> + Send + 'static,
+{
+    let pool = pool.clone();
+    web::block(move || {
+        let mut conn = pool.get()?;
+        f(&mut conn)
+    })
+    .await?
+}
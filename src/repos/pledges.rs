@@ -0,0 +1,82 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use solana_sdk::signature::Signature;
+use uuid::Uuid;
+use crate::DbPool;
+use crate::routes::pledges::models::{NewPledge, Pledge, PledgeReminder};
+use crate::schema::{pledge_reminders, pledges};
+use super::{run, RepoError};
+
+/// Recurring donation pledges and their reminders.
+pub struct PledgeRepo {
+    pool: DbPool,
+}
+
+impl PledgeRepo {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, pledge: NewPledge) -> Result<Pledge, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(diesel::insert_into(pledges::table)
+                .values(&pledge)
+                .get_result::<Pledge>(conn)?)
+        })
+        .await
+    }
+
+    /// The pledge with its reminders, newest first.
+    pub async fn find_with_reminders(&self, pledge_id: Uuid) -> Result<Option<(Pledge, Vec<PledgeReminder>)>, RepoError> {
+        run(&self.pool, move |conn| {
+            let Some(pledge) = pledges::table.find(pledge_id).first::<Pledge>(conn).optional()? else {
+                return Ok(None);
+            };
+            let reminders = pledge_reminders::table
+                .filter(pledge_reminders::pledge_id.eq(pledge_id))
+                .order(pledge_reminders::due_at.desc())
+                .load::<PledgeReminder>(conn)?;
+            Ok(Some((pledge, reminders)))
+        })
+        .await
+    }
+
+    /// Stops further reminders; `None` if `identity_id` has no such pledge.
+    pub async fn cancel(&self, pledge_id: Uuid, identity_id: Uuid) -> Result<Option<Pledge>, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(diesel::update(pledges::table.find(pledge_id).filter(pledges::identity_id.eq(identity_id)))
+                .set(pledges::active.eq(false))
+                .get_result::<Pledge>(conn)
+                .optional()?)
+        })
+        .await
+    }
+
+    /// The reminder with the pledge it was sent for.
+    pub async fn find_reminder(&self, reminder_id: Uuid) -> Result<Option<(PledgeReminder, Pledge)>, RepoError> {
+        run(&self.pool, move |conn| {
+            let Some(reminder) = pledge_reminders::table
+                .find(reminder_id)
+                .first::<PledgeReminder>(conn)
+                .optional()? else {
+                return Ok(None);
+            };
+            let pledge = pledges::table.find(reminder.pledge_id).first::<Pledge>(conn)?;
+            Ok(Some((reminder, pledge)))
+        })
+        .await
+    }
+
+    /// Marks the reminder fulfilled by the donation `signature`.
+    pub async fn fulfil_reminder(&self, reminder_id: Uuid, signature: Signature) -> Result<PledgeReminder, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(diesel::update(pledge_reminders::table.find(reminder_id))
+                .set((
+                    pledge_reminders::fulfilled_at.eq(Some(Utc::now().naive_utc())),
+                    pledge_reminders::signature.eq(Some(signature.to_string())),
+                ))
+                .get_result::<PledgeReminder>(conn)?)
+        })
+        .await
+    }
+}
@@ -0,0 +1,216 @@
+use actix_web::web;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::dsl::{avg, count, count_star, date, max, sql, sum};
+use diesel::prelude::*;
+use diesel::sql_types::{Double, Nullable};
+use solana_sdk::signature::Signature;
+use crate::DbPool;
+use crate::config::SolanaCluster;
+use crate::routes::blink_chain::models::{Beneficiary, NewBeneficiary, Project};
+use crate::routes::projects::models::{DailyTotal, Donation, DonationTotals, DonorTotal};
+use crate::schema::{donations, project_beneficiaries, projects};
+use crate::services::donations::{
+    fetch_transaction, record_confirmed_donation, AnonymousDonor, DonationError, SignatureKey,
+};
+use super::{run, RepoError};
+
+/// Projects, their beneficiaries and the donations ledger.
+pub struct ProjectRepo {
+    pool: DbPool,
+    signature_key: SignatureKey,
+}
+
+impl ProjectRepo {
+    pub fn new(pool: DbPool, signature_key: SignatureKey) -> Self {
+        Self { pool, signature_key }
+    }
+
+    pub async fn find(&self, project_id: i32) -> Result<Option<Project>, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(projects::table.find(project_id).first::<Project>(conn).optional()?)
+        })
+        .await
+    }
+
+    /// The project with its beneficiaries in payout order.
+    pub async fn find_with_beneficiaries(&self, project_id: i32) -> Result<Option<(Project, Vec<Beneficiary>)>, RepoError> {
+        run(&self.pool, move |conn| {
+            let Some(project) = projects::table.find(project_id).first::<Project>(conn).optional()? else {
+                return Ok(None);
+            };
+            let beneficiaries = project_beneficiaries::table
+                .filter(project_beneficiaries::project_id.eq(project_id))
+                .order(project_beneficiaries::id.asc())
+                .load::<Beneficiary>(conn)?;
+            Ok(Some((project, beneficiaries)))
+        })
+        .await
+    }
+
+    /// Replaces the beneficiaries of the project.
+    pub async fn replace_beneficiaries(
+        &self,
+        project_id: i32,
+        beneficiaries: Vec<NewBeneficiary>,
+    ) -> Result<Vec<Beneficiary>, RepoError> {
+        run(&self.pool, move |conn| {
+            conn.transaction(|conn| {
+                diesel::delete(project_beneficiaries::table.filter(project_beneficiaries::project_id.eq(project_id)))
+                    .execute(conn)?;
+                diesel::insert_into(project_beneficiaries::table)
+                    .values(&beneficiaries)
+                    .get_results::<Beneficiary>(conn)
+            })
+            .map_err(RepoError::from)
+        })
+        .await
+    }
+
+    pub async fn min_donation(&self, project_id: i32) -> Result<Option<f64>, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(projects::table
+                .find(project_id)
+                .select(projects::min_donation)
+                .first::<f64>(conn)
+                .optional()?)
+        })
+        .await
+    }
+
+    /// Donation totals and daily totals of the project, oldest day first, `None` if it
+    /// doesn't exist.
+    ///
+    /// Every wallet counts as one donor however often it gave, and so does every anonymous
+    /// donor nullifier. Anonymous donations recorded before nullifiers were kept have neither
+    /// and aren't counted as donors.
+    pub async fn stats(&self, project_id: i32) -> Result<Option<(DonationTotals, Vec<DailyTotal>)>, RepoError> {
+        run(&self.pool, move |conn| {
+            if !exists(conn, project_id)? {
+                return Ok(None);
+            }
+            let ledger = donations::table.filter(donations::project_id.eq(project_id));
+
+            let (total, donation_count, wallets, nullifiers, anonymous_donation_count, average, median) = ledger
+                .select((
+                    sum(donations::amount_lamports),
+                    count_star(),
+                    count(donations::donor_wallet).aggregate_distinct(),
+                    count(donations::donor_nullifier).aggregate_distinct(),
+                    count(donations::id).aggregate_filter(donations::verified_anonymous),
+                    avg(donations::amount_lamports),
+                    // interpolates between the two middle donations of an even count
+                    sql::<Nullable<Double>>("percentile_cont(0.5) WITHIN GROUP (ORDER BY amount_lamports)"),
+                ))
+                .first::<(Option<BigDecimal>, i64, i64, i64, i64, Option<BigDecimal>, Option<f64>)>(conn)?;
+            let totals = DonationTotals {
+                total_lamports: lamports(total),
+                donation_count,
+                donor_count: wallets + nullifiers,
+                anonymous_donation_count,
+                average_lamports: average.and_then(|average| average.to_f64()).unwrap_or_default(),
+                median_lamports: median.unwrap_or_default(),
+            };
+
+            // diesel only selects grouped columns, not grouped expressions, but the day is
+            // the same for every row of a group
+            let day = date(donations::confirmed_at);
+            let daily = ledger
+                .group_by(day)
+                .select((max(day).assume_not_null(), sum(donations::amount_lamports), count_star()))
+                .order_by(max(day))
+                .load::<(NaiveDate, Option<BigDecimal>, i64)>(conn)?
+                .into_iter()
+                .map(|(day, total, donation_count)| DailyTotal {
+                    day,
+                    total_lamports: lamports(total),
+                    donation_count,
+                })
+                .collect();
+            Ok(Some((totals, daily)))
+        })
+        .await
+    }
+
+    /// The `limit` wallets that gave the most, ties broken by wallet address, `None` if the
+    /// project doesn't exist. Anonymous donations have no wallet and aren't ranked.
+    pub async fn top_donors(&self, project_id: i32, limit: i64) -> Result<Option<Vec<DonorTotal>>, RepoError> {
+        run(&self.pool, move |conn| {
+            if !exists(conn, project_id)? {
+                return Ok(None);
+            }
+            let donors = donations::table
+                .filter(donations::project_id.eq(project_id))
+                .filter(donations::donor_wallet.is_not_null())
+                .group_by(donations::donor_wallet)
+                .select((
+                    donations::donor_wallet.assume_not_null(),
+                    sum(donations::amount_lamports),
+                    count_star(),
+                    max(donations::confirmed_at).assume_not_null(),
+                ))
+                .order_by((sum(donations::amount_lamports).desc(), donations::donor_wallet.asc()))
+                .limit(limit)
+                .load::<(String, Option<BigDecimal>, i64, NaiveDateTime)>(conn)?
+                .into_iter()
+                .map(|(donor_wallet, total, donation_count, last_donated_at)| DonorTotal {
+                    donor_wallet,
+                    total_lamports: lamports(total),
+                    donation_count,
+                    last_donated_at,
+                })
+                .collect();
+            Ok(Some(donors))
+        })
+        .await
+    }
+
+    /// Looks up `signature` on `cluster` and records it in the donations ledger, see
+    /// [`record_confirmed_donation`].
+    pub async fn record_donation(
+        &self,
+        cluster: &SolanaCluster,
+        project_id: i32,
+        signature: Signature,
+        anonymous: Option<AnonymousDonor>,
+    ) -> Result<Donation, DonationError> {
+        // a slow RPC node mustn't hold on to a pooled connection, so the transaction is
+        // fetched before one is checked out
+        let rpc_client = cluster.rpc_client();
+        let confirmed = web::block(move || fetch_transaction(&rpc_client, &signature)).await??;
+
+        let key = self.signature_key.clone();
+        run(&self.pool, move |conn| {
+            record_confirmed_donation(conn, &confirmed, &key, project_id, &signature, anonymous)
+        })
+        .await
+    }
+
+    /// The public donation `signature` recorded for `project_id`, if any. Anonymous
+    /// donations aren't found by their signature.
+    pub async fn find_donation(&self, project_id: i32, signature: Signature) -> Result<Option<Donation>, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(donations::table
+                .filter(donations::signature.eq(signature.to_string()))
+                .filter(donations::project_id.eq(project_id))
+                .first::<Donation>(conn)
+                .optional()?)
+        })
+        .await
+    }
+}
+
+/// A `SUM` over lamports. Postgres sums `BIGINT`s as `NUMERIC`, but a project can't
+/// raise more lamports than fit an `i64`, the whole SOL supply is far fewer.
+fn lamports(sum: Option<BigDecimal>) -> i64 {
+    sum.and_then(|sum| sum.to_i64()).unwrap_or_default()
+}
+
+fn exists(conn: &mut PgConnection, project_id: i32) -> Result<bool, RepoError> {
+    let found = projects::table
+        .find(project_id)
+        .select(projects::id)
+        .first::<i32>(conn)
+        .optional()?;
+    Ok(found.is_some())
+}
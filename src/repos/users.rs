@@ -0,0 +1,28 @@
+use diesel::prelude::*;
+use crate::DbPool;
+use crate::routes::users::models::{User, UserResponse};
+use crate::schema::users;
+use super::{run, RepoError};
+
+pub struct UserRepo {
+    pool: DbPool,
+}
+
+impl UserRepo {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list(&self) -> Result<Vec<UserResponse>, RepoError> {
+        run(&self.pool, |conn| Ok(users::table.load::<UserResponse>(conn)?)).await
+    }
+
+    pub async fn create(&self, user: User) -> Result<UserResponse, RepoError> {
+        run(&self.pool, move |conn| {
+            Ok(diesel::insert_into(users::table)
+                .values(&user)
+                .get_result::<UserResponse>(conn)?)
+        })
+        .await
+    }
+}
@@ -1,7 +1,23 @@
-use actix_web::{web, HttpResponse, Scope};
+use actix_web::{web, HttpRequest, HttpResponse, Scope};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::client_ip::ClientIp;
+use crate::error::AppError;
 use crate::services::auth::AuthService;
-use crate::zk::{real::RealZKVerifier, ZKVerifier};
+use crate::services::challenges::ChallengeStore;
+use crate::services::donation_commitments::{DonationCommitmentError, DonationCommitmentStore};
+use crate::services::email_commitments::EmailCommitmentStore;
+use crate::services::email_verification::{EmailVerificationError, EmailVerifications};
+use crate::services::notifier::CodeSender;
+use crate::services::wallet_signatures;
+use crate::zk::attributes::{split_email, EmailOpening};
+use crate::zk::circuits::CircuitId;
+use crate::zk::donations::DonationOpening;
+use crate::zk::error::ZkError;
+use crate::zk::field::{check_public_inputs, fr_from_hex, fr_to_hex, public_inputs};
+use crate::zk::snarkjs;
+use crate::zk::{ProofEncoding, ZKProverBackend, ZKVerifier};
 
 #[derive(Debug, Deserialize)]
 pub struct WalletAuthRequest {
@@ -10,22 +26,57 @@ pub struct WalletAuthRequest {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct ZKProofRequest {
-    pub proof: String,
+    pub proof: ProofEncoding,
+    /// Hex field elements, see `zk::field::PUBLIC_INPUT_NAMES`, or decimal strings as in a
+    /// snarkjs `public.json` when `proof` is a snarkjs proof. Checked against the values
+    /// the server computes from the claimed identity and challenge.
     pub public_inputs: Vec<String>,
+    /// Circuit the proof was created with, the current one when missing. Proofs for an older
+    /// circuit must name it, so a missing id can't select one without the login challenge.
+    #[serde(default = "CircuitId::current")]
+    pub circuit_id: CircuitId,
+}
+
+impl ZKProofRequest {
+    /// The proof as base64 and the public inputs as hex, whichever encoding they came in.
+    fn normalized(&self) -> Result<(String, Vec<String>), ZkError> {
+        match &self.proof {
+            ProofEncoding::Compressed(proof) => Ok((proof.clone(), self.public_inputs.clone())),
+            ProofEncoding::Snarkjs(_) => {
+                let inputs = snarkjs::parse_public_signals(&self.public_inputs)?;
+                Ok((self.proof.to_base64()?, inputs.iter().map(fr_to_hex).collect()))
+            }
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct WalletZKAuthRequest {
     pub wallet_address: String,
+    /// Challenge from `/auth/zk/challenge` the proof is bound to.
+    pub challenge: String,
     pub zk_proof: ZKProofRequest,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct EmailZKAuthRequest {
     pub email: String,
+    pub challenge: String,
     pub zk_proof: ZKProofRequest,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ChallengeRequest {
+    /// Wallet address or email the challenge will be used to log in as.
+    pub identity: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChallengeResponse {
+    pub challenge: String,
+    pub expires_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
@@ -33,147 +84,315 @@ pub struct AuthResponse {
 }
 
 #[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
+pub struct EmailCodeResponse {
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EmailCommitmentRequest {
+    /// Code sent to the email by `/auth/zk/email-commitment/code`.
+    pub code: String,
+}
+
+/// A commitment to the caller's verified email and its opening, which only the caller
+/// gets to see.
+#[derive(Debug, Serialize)]
+pub struct EmailCommitmentResponse {
+    pub commitment: String,
+    pub domain: String,
+    pub local_hash: String,
+    pub domain_hash: String,
+    pub salt: String,
+}
+
+/// Signed by a wallet to get commitments to its donations.
+pub const DONATION_COMMITMENT_ACTION: &str = "issue donation commitment";
+
+#[derive(Debug, Deserialize)]
+pub struct DonationCommitmentRequest {
+    /// Id of a donation recorded from `wallet`.
+    pub donation_id: Uuid,
+    pub wallet: String,
+    /// Challenge from `/auth/zk/challenge` issued for `wallet`.
+    pub challenge: String,
+    /// Base58 signature of `wallet` over
+    /// `wallet_signatures::message(DONATION_COMMITMENT_ACTION, challenge)`.
+    pub signature: String,
+}
+
+/// A commitment to one of the caller's donations and its opening, which only the caller
+/// gets to see.
+#[derive(Debug, Serialize)]
+pub struct DonationCommitmentResponse {
+    pub commitment: String,
+    pub project_id: i32,
+    pub amount_lamports: u64,
+    pub salt: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateProofRequest {
     pub wallet_address: String,
+    pub challenge: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreateEmailProofRequest {
     pub email: String,
+    pub challenge: String,
 }
 
 pub fn auth_routes() -> Scope {
     web::scope("/auth")
         .route("/wallet", web::post().to(wallet_auth))
+        .route("/zk/challenge", web::post().to(create_challenge))
         .route("/zk/wallet", web::post().to(wallet_zk_auth))
         .route("/zk/email", web::post().to(email_zk_auth))
         .route("/zk/create-proof", web::post().to(create_wallet_proof))
         .route("/zk/create-email-proof", web::post().to(create_email_proof))
+        .route("/zk/email-commitment/code", web::post().to(send_email_code))
+        .route("/zk/email-commitment", web::post().to(create_email_commitment))
+        .route("/zk/donation-commitment", web::post().to(create_donation_commitment))
 }
 
 async fn wallet_auth(
     req: web::Json<WalletAuthRequest>,
     auth_service: web::Data<AuthService>,
-) -> HttpResponse {
-    println!("Received wallet auth request: {:?}", req);
-    match auth_service.wallet_auth(&req.wallet_address).await {
-        Ok(wallet_identity) => HttpResponse::Ok().json(wallet_identity),
-        Err(e) => {
-            println!("Error in wallet auth: {:?}", e);
-            HttpResponse::InternalServerError().json(ErrorResponse {
-                error: "Failed to authenticate wallet".to_string()
-            })
-        }
-    }
+) -> Result<HttpResponse, AppError> {
+    let wallet_identity = auth_service.wallet_auth(&req.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(wallet_identity))
+}
+
+async fn create_challenge(
+    client: ClientIp,
+    req: web::Json<ChallengeRequest>,
+    challenges: web::Data<ChallengeStore>,
+) -> Result<HttpResponse, AppError> {
+    let (challenge, expires_at) = challenges.issue(&req.identity, &client.0)?;
+    Ok(HttpResponse::Ok().json(ChallengeResponse {
+        challenge: fr_to_hex(&challenge),
+        expires_at,
+    }))
 }
 
 async fn wallet_zk_auth(
     req: web::Json<WalletZKAuthRequest>,
     auth_service: web::Data<AuthService>,
-    zk_verifier: web::Data<RealZKVerifier>,
-) -> HttpResponse {
-    println!("Received wallet ZK auth request: {:?}", req);
+    zk_verifier: web::Data<dyn ZKVerifier>,
+    challenges: web::Data<ChallengeStore>,
+) -> Result<HttpResponse, AppError> {
+    let challenge = fr_from_hex(&req.challenge).ok_or_else(malformed_challenge)?;
+    let (proof, provided_inputs) = req.zk_proof.normalized()?;
+    let expected = public_inputs(&req.wallet_address, challenge);
+    check_public_inputs(&provided_inputs, &expected).map_err(ZkError::from)?;
+    if !challenges.is_pending(&challenge, &req.wallet_address) {
+        return Err(invalid_challenge());
+    }
 
     let verifier = zk_verifier.get_ref();
-    let verification_result = verifier.verify_wallet(&req.wallet_address, &req.zk_proof.proof);
-    println!("ZK verification result: {}", verification_result);
-
-    if verification_result {
-        match auth_service.create_auth_token(&req.wallet_address).await {
-            Ok((token, user_id)) => {
-                println!("Auth token created successfully");
-                HttpResponse::Ok().json(AuthResponse {
-                    token,
-                    user_id,
-                })
-            },
-            Err(e) => {
-                println!("Failed to create auth token: {:?}", e);
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: "Failed to create auth token".to_string()
-                })
-            }
-        }
-    } else {
-        println!("ZK verification failed");
-        HttpResponse::Unauthorized().json(ErrorResponse {
-            error: "ZK proof verification failed".to_string()
-        })
+    verifier
+        .check_circuit(&req.zk_proof.circuit_id)
+        .map_err(ZkError::from)
+        .and_then(|()| verifier.verify_wallet(&req.zk_proof.circuit_id, &req.wallet_address, challenge, &proof))?;
+
+    // consuming only after verification keeps a bad proof from burning the challenge
+    if !challenges.consume(&challenge, &req.wallet_address) {
+        return Err(invalid_challenge());
     }
+
+    let (token, user_id) = auth_service.create_auth_token(&req.wallet_address).await?;
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        token,
+        user_id,
+    }))
 }
 
 async fn email_zk_auth(
     req: web::Json<EmailZKAuthRequest>,
     auth_service: web::Data<AuthService>,
-    zk_verifier: web::Data<RealZKVerifier>,
-) -> HttpResponse {
-    println!("Received email ZK auth request: {:?}", req);
+    zk_verifier: web::Data<dyn ZKVerifier>,
+    challenges: web::Data<ChallengeStore>,
+) -> Result<HttpResponse, AppError> {
+    let challenge = fr_from_hex(&req.challenge).ok_or_else(malformed_challenge)?;
+    let (proof, provided_inputs) = req.zk_proof.normalized()?;
+    let expected = public_inputs(&req.email, challenge);
+    check_public_inputs(&provided_inputs, &expected).map_err(ZkError::from)?;
+    if !challenges.is_pending(&challenge, &req.email) {
+        return Err(invalid_challenge());
+    }
 
     let verifier = zk_verifier.get_ref();
-    let verification_result = verifier.verify_email(&req.email, &req.zk_proof.proof);
-    println!("ZK verification result: {}", verification_result);
-
-    if verification_result {
-        match auth_service.create_auth_token(&req.email).await {
-            Ok((token, user_id)) => {
-                println!("Auth token created successfully");
-                HttpResponse::Ok().json(AuthResponse {
-                    token,
-                    user_id,
-                })
-            },
-            Err(e) => {
-                println!("Failed to create auth token: {:?}", e);
-                HttpResponse::InternalServerError().json(ErrorResponse {
-                    error: "Failed to create auth token".to_string()
-                })
-            }
-        }
-    } else {
-        println!("ZK verification failed");
-        HttpResponse::Unauthorized().json(ErrorResponse {
-            error: "ZK proof verification failed".to_string()
-        })
+    verifier
+        .check_circuit(&req.zk_proof.circuit_id)
+        .map_err(ZkError::from)
+        .and_then(|()| verifier.verify_email(&req.zk_proof.circuit_id, &req.email, challenge, &proof))?;
+
+    // consuming only after verification keeps a bad proof from burning the challenge
+    if !challenges.consume(&challenge, &req.email) {
+        return Err(invalid_challenge());
     }
+
+    let (token, user_id) = auth_service.create_auth_token(&req.email).await?;
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        token,
+        user_id,
+    }))
 }
 
 async fn create_wallet_proof(
     req: web::Json<CreateProofRequest>,
-    zk_verifier: web::Data<RealZKVerifier>,
-) -> HttpResponse {
-    println!("Received create proof request: {:?}", req);
+    zk_prover: web::Data<dyn ZKProverBackend>,
+) -> Result<HttpResponse, AppError> {
+    let challenge = fr_from_hex(&req.challenge).ok_or_else(malformed_challenge)?;
+    let inputs = public_inputs(&req.wallet_address, challenge);
 
-    let verifier = zk_verifier.get_ref();
-    match verifier.create_wallet_proof(&req.wallet_address) {
-        Some(proof) => HttpResponse::Ok().json(ZKProofRequest {
-            proof,
-            public_inputs: vec![],
-        }),
-        None => HttpResponse::InternalServerError().json(ErrorResponse {
-            error: "Failed to create proof".to_string(),
-        }),
-    }
+    // proving is CPU-bound, keep it off the actix workers
+    let input = req.into_inner().wallet_address;
+    let (proof, circuit_id) = web::block(move || {
+        zk_prover.create_wallet_proof(&input, challenge).map(|proof| (proof, zk_prover.circuit_id()))
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(ZKProofRequest {
+        proof: ProofEncoding::Compressed(proof),
+        public_inputs: inputs.iter().map(fr_to_hex).collect(),
+        circuit_id,
+    }))
 }
 
 async fn create_email_proof(
     req: web::Json<CreateEmailProofRequest>,
-    zk_verifier: web::Data<RealZKVerifier>,
-) -> HttpResponse {
-    println!("Received create email proof request: {:?}", req);
+    zk_prover: web::Data<dyn ZKProverBackend>,
+) -> Result<HttpResponse, AppError> {
+    let challenge = fr_from_hex(&req.challenge).ok_or_else(malformed_challenge)?;
+    let inputs = public_inputs(&req.email, challenge);
 
-    let verifier = zk_verifier.get_ref();
-    match verifier.create_email_proof(&req.email) {
-        Some(proof) => HttpResponse::Ok().json(ZKProofRequest {
-            proof,
-            public_inputs: vec![],
-        }),
-        None => HttpResponse::InternalServerError().json(ErrorResponse {
-            error: "Failed to create proof".to_string(),
-        }),
+    // proving is CPU-bound, keep it off the actix workers
+    let input = req.into_inner().email;
+    let (proof, circuit_id) = web::block(move || {
+        zk_prover.create_email_proof(&input, challenge).map(|proof| (proof, zk_prover.circuit_id()))
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(ZKProofRequest {
+        proof: ProofEncoding::Compressed(proof),
+        public_inputs: inputs.iter().map(fr_to_hex).collect(),
+        circuit_id,
+    }))
+}
+
+async fn send_email_code(
+    req: HttpRequest,
+    auth_service: web::Data<AuthService>,
+    verifications: web::Data<EmailVerifications>,
+    sender: web::Data<dyn CodeSender>,
+) -> Result<HttpResponse, AppError> {
+    let email = email_subject(&req, &auth_service)?;
+    let (code, expires_at) = verifications.start(&email)?;
+
+    // SMTP is blocking, keep it off the actix workers
+    let sent = {
+        let email = email.clone();
+        web::block(move || sender.send_code(&email, &code)).await?
+    };
+    if let Err(e) = sent {
+        verifications.cancel(&email);
+        return Err(EmailVerificationError::from(e).into());
     }
-} 
\ No newline at end of file
+
+    Ok(HttpResponse::Accepted().json(EmailCodeResponse { expires_at }))
+}
+
+async fn create_email_commitment(
+    req: HttpRequest,
+    body: web::Json<EmailCommitmentRequest>,
+    auth_service: web::Data<AuthService>,
+    verifications: web::Data<EmailVerifications>,
+    commitments: web::Data<dyn EmailCommitmentStore>,
+) -> Result<HttpResponse, AppError> {
+    // anyone can log in as an email, only the code shows they read its inbox
+    let email = email_subject(&req, &auth_service)?;
+    verifications.confirm(&email, &body.code)?;
+
+    let (_, domain) = split_email(&email).ok_or_else(email_login_required)?;
+    let opening = EmailOpening::new(&email, &mut ark_std::rand::thread_rng()).ok_or_else(email_login_required)?;
+
+    let commitment = opening.commitment();
+    web::block(move || commitments.issue(&commitment)).await??;
+
+    Ok(HttpResponse::Created().json(EmailCommitmentResponse {
+        commitment: fr_to_hex(&commitment),
+        domain,
+        local_hash: fr_to_hex(&opening.local_hash),
+        domain_hash: fr_to_hex(&opening.domain_hash),
+        salt: fr_to_hex(&opening.salt),
+    }))
+}
+
+async fn create_donation_commitment(
+    body: web::Json<DonationCommitmentRequest>,
+    challenges: web::Data<ChallengeStore>,
+    commitments: web::Data<dyn DonationCommitmentStore>,
+) -> Result<HttpResponse, AppError> {
+    // only the wallet the donation was sent from gets a commitment to it, and a login
+    // doesn't show the caller holds its key, so the wallet signs for it
+    wallet_signatures::authorize(
+        &challenges,
+        &body.wallet,
+        DONATION_COMMITMENT_ACTION,
+        &body.challenge,
+        &body.signature,
+    )?;
+    let wallet = body.wallet.clone();
+
+    let donation_id = body.donation_id;
+    let issued = web::block(move || -> Result<Option<DonationOpening>, DonationCommitmentError> {
+        let Some(donation) = commitments.find_donation(donation_id, &wallet)? else {
+            return Ok(None);
+        };
+        let opening = DonationOpening::new(
+            donation.project_id,
+            donation.amount_lamports,
+            &mut ark_std::rand::thread_rng(),
+        );
+        commitments.issue(&opening.commitment())?;
+        Ok(Some(opening))
+    })
+    .await??;
+    let opening = issued.ok_or_else(|| AppError::not_found("No donation with this id was recorded from your wallet"))?;
+
+    Ok(HttpResponse::Created().json(DonationCommitmentResponse {
+        commitment: fr_to_hex(&opening.commitment()),
+        project_id: opening.project_id,
+        amount_lamports: opening.amount_lamports,
+        salt: fr_to_hex(&opening.salt),
+    }))
+}
+
+/// Subject of a valid bearer token, if the request carries one.
+pub(crate) fn bearer_subject(req: &HttpRequest, auth_service: &AuthService) -> Option<String> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .and_then(|token| auth_service.token_subject(token).ok())
+}
+
+/// Email address of a valid bearer token from an email login.
+fn email_subject(req: &HttpRequest, auth_service: &AuthService) -> Result<String, AppError> {
+    bearer_subject(req, auth_service)
+        .filter(|subject| split_email(subject).is_some())
+        .ok_or_else(email_login_required)
+}
+
+fn email_login_required() -> AppError {
+    AppError::unauthorized("A valid auth token from an email login is required")
+}
+
+fn malformed_challenge() -> AppError {
+    AppError::validation("Invalid challenge")
+}
+
+fn invalid_challenge() -> AppError {
+    AppError::unauthorized("Challenge expired, already used or issued for another identity")
+}
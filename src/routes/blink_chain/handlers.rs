@@ -1,47 +1,36 @@
-use actix_web::{get, post, options, web, HttpResponse, Responder, HttpRequest};
-use diesel::prelude::*;
-use solana_client::rpc_client::RpcClient;
+use std::time::Instant;
+use actix_web::{get, post, web, HttpResponse, HttpRequest};
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     message::Message,
     pubkey::Pubkey,
     system_instruction,
     transaction::Transaction,
-    signer::keypair::Keypair,
 };
-use crate::DbPool;
+use crate::config::SolanaCluster;
+use crate::error::AppError;
+use crate::metrics;
+use crate::repos::ProjectRepo;
 use super::models::*;
+use super::simulation::describe_simulation_error;
+use super::split::{split_lamports, TOTAL_BPS};
 
 #[get("")]
 pub async fn get_project(
-    pool: web::Data<DbPool>,
+    projects: web::Data<ProjectRepo>,
     query: web::Query<std::collections::HashMap<String, String>>,
     req: HttpRequest,
-) -> impl Responder {
-    use crate::schema::projects::dsl::*;
-
-    let project_id = match query.get("id").and_then(|val| val.parse::<i32>().ok()) {
-        Some(pid) => pid,
-        None => return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid project ID"
-        })),
-    };
+) -> Result<HttpResponse, AppError> {
+    let project_id = query
+        .get("id")
+        .and_then(|val| val.parse::<i32>().ok())
+        .ok_or_else(|| AppError::validation("Invalid project ID"))?;
 
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database connection error"
-        })),
-    };
-
-    let project = match projects
-        .filter(id.eq(project_id))
-        .first::<Project>(&mut conn) {
-        Ok(p) => p,
-        Err(_) => return HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Project not found"
-        })),
-    };
+    let project = projects
+        .find(project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
 
     let base_href = format!("{}/api/blink-chain?", req.connection_info().host());
     
@@ -65,98 +54,98 @@ pub async fn get_project(
         },
     };
 
-    HttpResponse::Ok().json(response)
+    Ok(HttpResponse::Ok().json(response))
 }
 
 #[post("")]
 pub async fn process_donation(
-    pool: web::Data<DbPool>,
+    projects: web::Data<ProjectRepo>,
+    cluster: web::Data<SolanaCluster>,
     donation: web::Json<DonationRequest>,
-) -> impl Responder {
-    use crate::schema::projects::dsl::*;
-
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database connection error"
-        })),
-    };
-
-    // Find project
-    let project = match projects
-        .filter(id.eq(donation.id))
-        .first::<Project>(&mut conn) {
-        Ok(p) => p,
-        Err(_) => return HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Project not found"
-        })),
-    };
+) -> Result<HttpResponse, AppError> {
+    // Find project and its beneficiaries
+    let (project, beneficiaries) = projects
+        .find_with_beneficiaries(donation.id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
 
     // Parse account
-    let account = match Pubkey::try_from(donation.account.as_str()) {
-        Ok(pk) => pk,
-        Err(_) => return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid account provided"
-        })),
-    };
-
-    // Parse project wallet
-    let project_wallet = match Pubkey::try_from(project.wallet.as_str()) {
-        Ok(pk) => pk,
-        Err(_) => return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid project wallet"
-        })),
-    };
+    let account = Pubkey::try_from(donation.account.as_str())
+        .map_err(|_| AppError::validation("Invalid account provided"))?;
+
+    // Pay the beneficiaries, falling back to the project wallet
+    let mut shares = Vec::with_capacity(beneficiaries.len().max(1));
+    if beneficiaries.is_empty() {
+        let pk = Pubkey::try_from(project.wallet.as_str())
+            .map_err(|_| AppError::validation("Invalid project wallet"))?;
+        shares.push((pk, TOTAL_BPS as i32));
+    } else {
+        for beneficiary in &beneficiaries {
+            let pk = Pubkey::try_from(beneficiary.wallet.as_str())
+                .map_err(|_| AppError::validation(format!("Invalid beneficiary wallet: {}", beneficiary.wallet)))?;
+            shares.push((pk, beneficiary.share_bps));
+        }
+    }
 
     // Validate amount
-    let donation_amount = match donation.amount.parse::<f64>() {
-        Ok(amount) => amount,
-        Err(_) => return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "Invalid donation amount"
-        })),
-    };
+    let donation_amount = donation.amount.parse::<f64>()
+        .map_err(|_| AppError::validation("Invalid donation amount"))?;
 
     if donation_amount < project.min_donation {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": format!("Donation amount must be at least {} SOL", project.min_donation)
-        }));
+        return Err(AppError::validation(format!(
+            "Donation amount must be at least {} SOL",
+            project.min_donation
+        )));
     }
 
-    // Setup Solana client
-    let rpc_client = RpcClient::new_with_commitment(
-        "https://api.devnet.solana.com".to_string(),
-        CommitmentConfig::confirmed(),
-    );
-
     let donation_lamports = (donation_amount * 1_000_000_000.0) as u64;
 
-    // Check balance
-    match rpc_client.get_balance(&account) {
-        Ok(balance) if balance < donation_lamports => {
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "Insufficient balance for donation"
-            }))
-        }
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to check balance"
-        })),
-        _ => {}
+    // Split donation between beneficiaries
+    let transfers = split_lamports(donation_lamports, &shares)
+        .map_err(|e| AppError::Internal(format!("Invalid beneficiary configuration: {}", e)))?;
+
+    // Create transfer instructions
+    let instructions: Vec<_> = transfers
+        .iter()
+        .map(|(to, lamports)| system_instruction::transfer(&account, to, *lamports))
+        .collect();
+
+    // The RPC client blocks, so build and simulate the transaction off the actix workers
+    let (transaction, simulation) = web::block(move || {
+        let rpc_client = cluster.rpc_client();
+
+        // Get recent blockhash
+        let started = Instant::now();
+        let recent_blockhash = rpc_client.get_latest_blockhash();
+        metrics::observe_rpc("getLatestBlockhash", started, recent_blockhash.is_ok());
+
+        // Create transaction
+        let mut message = Message::new(&instructions, Some(&account));
+        message.recent_blockhash = recent_blockhash?;
+        let transaction = Transaction::new_unsigned(message);
+
+        // Simulate before handing it to the wallet, the donor hasn't signed yet
+        let started = Instant::now();
+        let simulation = rpc_client.simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..Default::default()
+            },
+        );
+        metrics::observe_rpc("simulateTransaction", started, simulation.is_ok());
+        Ok::<_, AppError>((transaction, simulation?.value))
+    })
+    .await??;
+
+    if let Some(err) = simulation.err {
+        log::debug!("Donation simulation failed: {:?}, logs: {:?}", err, simulation.logs);
+        let recipients: Vec<Pubkey> = transfers.iter().map(|(to, _)| *to).collect();
+        return Err(AppError::validation(describe_simulation_error(&err, &account, &recipients)));
     }
 
-    // Create transfer instruction
-    let instruction = system_instruction::transfer(&account, &project_wallet, donation_lamports);
-
-    // Get recent blockhash
-    let recent_blockhash = match rpc_client.get_latest_blockhash() {
-        Ok(blockhash) => blockhash,
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to get blockhash"
-        })),
-    };
-
-    // Create transaction
-    let message = Message::new(&[instruction], Some(&account));
-    let transaction = Transaction::new::<[&Keypair; 0]>(&[], message, recent_blockhash);
+    metrics::donation_transaction_built(project.id);
 
     let response = ActionPostResponse {
         transaction_type: "transaction".to_string(),
@@ -164,14 +153,5 @@ pub async fn process_donation(
         message: format!("Donating {} SOL to {}", donation_amount, project.title),
     };
 
-    HttpResponse::Ok().json(response)
+    Ok(HttpResponse::Ok().json(response))
 }
-
-#[options("")]
-pub async fn options() -> impl Responder {
-    HttpResponse::Ok()
-        .append_header(("Access-Control-Allow-Origin", "*"))
-        .append_header(("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
-        .append_header(("Access-Control-Allow-Headers", "Content-Type"))
-        .finish()
-} 
\ No newline at end of file
@@ -1,12 +1,13 @@
 use actix_web::web;
 
 pub mod models;
+pub mod simulation;
+pub mod split;
 mod handlers;
 
-pub use handlers::{get_project, process_donation, options};
+pub use handlers::{get_project, process_donation};
 
 pub fn blink_chain_config(cfg: &mut web::ServiceConfig) {
     cfg.service(get_project)
-       .service(process_donation)
-       .service(options);
+       .service(process_donation);
 } 
\ No newline at end of file
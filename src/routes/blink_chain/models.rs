@@ -14,6 +14,25 @@ pub struct Project {
     pub wallet: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, Queryable)]
+#[diesel(table_name = crate::schema::project_beneficiaries)]
+pub struct Beneficiary {
+    pub id: i32,
+    pub project_id: i32,
+    pub wallet: String,
+    pub share_bps: i32,
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::project_beneficiaries)]
+pub struct NewBeneficiary {
+    pub project_id: i32,
+    pub wallet: String,
+    pub share_bps: i32,
+    pub label: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DonationRequest {
     pub id: i32,
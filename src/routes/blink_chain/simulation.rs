@@ -0,0 +1,54 @@
+use solana_sdk::{
+    instruction::InstructionError,
+    pubkey::Pubkey,
+    system_instruction::SystemError,
+    transaction::TransactionError,
+};
+
+/// Turns a failed donation simulation into a message the donor can act on.
+///
+/// `payer` is the donating account and `recipients[i]` the beneficiary credited by
+/// the i-th transfer instruction.
+pub fn describe_simulation_error(err: &TransactionError, payer: &Pubkey, recipients: &[Pubkey]) -> String {
+    match err {
+        TransactionError::AccountNotFound | TransactionError::InvalidAccountForFee => {
+            format!("Account {} has no SOL to pay for this donation", payer)
+        }
+        TransactionError::InsufficientFundsForFee => {
+            "Insufficient funds for fee".to_string()
+        }
+        TransactionError::InsufficientFundsForRent { account_index } => {
+            if *account_index == 0 {
+                "Donation would leave your account below the rent-exempt minimum, try a smaller amount".to_string()
+            } else {
+                "Donation is too small to make the recipient account rent-exempt".to_string()
+            }
+        }
+        TransactionError::BlockhashNotFound => {
+            "Transaction expired before it could be checked, please try again".to_string()
+        }
+        TransactionError::InstructionError(index, instruction_error) => {
+            let recipient = recipients
+                .get(*index as usize)
+                .map(|pk| pk.to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            match instruction_error {
+                InstructionError::Custom(code) if *code == SystemError::ResultWithNegativeLamports as u32 => {
+                    "Insufficient balance for donation".to_string()
+                }
+                InstructionError::InsufficientFunds => {
+                    "Insufficient balance for donation".to_string()
+                }
+                InstructionError::InvalidAccountData
+                | InstructionError::InvalidAccountOwner
+                | InstructionError::ExternalAccountLamportSpend
+                | InstructionError::ReadonlyLamportChange => {
+                    format!("Recipient account {} invalid", recipient)
+                }
+                other => format!("Transfer to {} would fail: {}", recipient, other),
+            }
+        }
+        other => format!("Donation transaction would fail: {}", other),
+    }
+}
@@ -0,0 +1,65 @@
+use solana_sdk::pubkey::Pubkey;
+
+/// Basis points making up a whole donation (100%).
+pub const TOTAL_BPS: u64 = 10_000;
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum SplitError {
+    #[error("Project has no beneficiaries configured")]
+    NoBeneficiaries,
+    #[error("Beneficiary share must be positive, got {0} bps")]
+    InvalidShare(i32),
+    #[error("Beneficiary shares must sum to {TOTAL_BPS} bps, got {0}")]
+    InvalidTotal(u64),
+}
+
+/// Checks that `shares` are positive basis points adding up to [`TOTAL_BPS`].
+pub fn check_shares(shares: impl IntoIterator<Item = i32>) -> Result<(), SplitError> {
+    let mut count = 0;
+    let mut total_bps = 0u64;
+    for bps in shares {
+        if bps <= 0 {
+            return Err(SplitError::InvalidShare(bps));
+        }
+        count += 1;
+        total_bps += bps as u64;
+    }
+    if count == 0 {
+        return Err(SplitError::NoBeneficiaries);
+    }
+    if total_bps != TOTAL_BPS {
+        return Err(SplitError::InvalidTotal(total_bps));
+    }
+    Ok(())
+}
+
+/// Splits `lamports` between beneficiaries according to their basis-point shares.
+///
+/// Each share is rounded down; the leftover dust goes to the beneficiary with the
+/// largest share, ties broken by position, so the same input always yields the
+/// same transfers. Beneficiaries whose portion rounds to zero are omitted.
+pub fn split_lamports(lamports: u64, shares: &[(Pubkey, i32)]) -> Result<Vec<(Pubkey, u64)>, SplitError> {
+    check_shares(shares.iter().map(|(_, bps)| *bps))?;
+
+    let mut parts: Vec<(Pubkey, u64)> = shares
+        .iter()
+        .map(|(wallet, bps)| {
+            let part = (lamports as u128 * *bps as u128 / TOTAL_BPS as u128) as u64;
+            (*wallet, part)
+        })
+        .collect();
+
+    let distributed: u64 = parts.iter().map(|(_, part)| part).sum();
+    let dust = lamports - distributed;
+
+    // first beneficiary holding the largest share receives the dust
+    let mut dust_index = 0;
+    for (i, (_, bps)) in shares.iter().enumerate() {
+        if *bps > shares[dust_index].1 {
+            dust_index = i;
+        }
+    }
+    parts[dust_index].1 += dust;
+
+    Ok(parts.into_iter().filter(|(_, part)| *part > 0).collect())
+}
@@ -1,4 +1,5 @@
-use actix_web::{get, HttpResponse, Responder};
+use actix_web::{get, web, HttpResponse, Responder};
+use crate::services::readiness::ReadinessChecks;
 
 #[get("/health")]
 pub async fn health_check() -> impl Responder {
@@ -6,4 +7,20 @@ pub async fn health_check() -> impl Responder {
         "status": "healthy",
         "timestamp": chrono::Utc::now().to_rfc3339()
     }))
-} 
\ No newline at end of file
+}
+
+/// Whether the server's dependencies are up, 503 while a critical one is down.
+#[get("/ready")]
+pub async fn ready_check(readiness: web::Data<ReadinessChecks>) -> impl Responder {
+    let report = readiness.run().await;
+    if report.is_ready() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
+
+pub fn health_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(health_check)
+       .service(ready_check);
+}
@@ -0,0 +1,10 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use crate::DbPool;
+use crate::metrics;
+
+#[get("/metrics")]
+pub async fn get_metrics(pool: web::Data<DbPool>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&pool))
+}
@@ -3,15 +3,30 @@ use actix_web::web;
 pub mod auth;
 pub mod blink_chain;
 pub mod health;
+pub mod metrics;
+pub mod pledges;
+pub mod projects;
 pub mod users;
+pub mod zk;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
     cfg
+        .configure(health::health_config)
+        .service(metrics::get_metrics)
         .service(auth::auth_routes())
+        .service(zk::zk_routes())
         .service(
             web::scope("/api/blink-chain")
                 .configure(blink_chain::blink_chain_config),
         )
+        .service(
+            web::scope("/api/pledges")
+                .configure(pledges::pledges_config),
+        )
+        .service(
+            web::scope("/api/projects")
+                .configure(projects::projects_config),
+        )
         .service(
             web::scope("/api/users")
                 .configure(users::users_config),
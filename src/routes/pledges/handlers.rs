@@ -0,0 +1,167 @@
+use std::str::FromStr;
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use solana_sdk::signature::Signature;
+use uuid::Uuid;
+use crate::config::SolanaCluster;
+use crate::error::AppError;
+use crate::repos::{IdentityRepo, PledgeRepo, ProjectRepo};
+use crate::routes::auth::bearer_subject;
+use crate::services::auth::AuthService;
+use crate::services::cache::ProjectCache;
+use crate::services::donations::DonationError;
+use crate::services::notifier::Notifiers;
+use crate::services::pledges::check_fulfilment;
+use super::models::*;
+
+/// The identity of the account whose token the request carries.
+async fn caller_identity(
+    http_req: &HttpRequest,
+    auth_service: &AuthService,
+    identities: &IdentityRepo,
+) -> Result<Uuid, AppError> {
+    let subject = bearer_subject(http_req, auth_service)
+        .ok_or_else(|| AppError::unauthorized("A valid auth token is required"))?;
+    identities
+        .find_by_subject(&subject)
+        .await?
+        .ok_or_else(|| AppError::unauthorized("No identity is linked to this account"))
+}
+
+#[post("")]
+pub async fn create_pledge(
+    http_req: HttpRequest,
+    auth_service: web::Data<AuthService>,
+    identities: web::Data<IdentityRepo>,
+    pledges: web::Data<PledgeRepo>,
+    projects: web::Data<ProjectRepo>,
+    notifiers: web::Data<Notifiers>,
+    req: web::Json<CreatePledgeRequest>,
+) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner();
+
+    let amount = req.amount
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| *amount > 0.0)
+        .ok_or_else(|| AppError::validation("Invalid pledge amount"))?;
+
+    if req.interval_days <= 0 {
+        return Err(AppError::validation("Pledge interval must be at least one day"));
+    }
+
+    notifiers
+        .check_target(&req.notify_channel, &req.notify_target)
+        .map_err(|e| AppError::validation(e.to_string()))?;
+
+    let identity_id = caller_identity(&http_req, &auth_service, &identities).await?;
+
+    let min_donation = projects
+        .min_donation(req.project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    if amount < min_donation {
+        return Err(AppError::validation(format!(
+            "Pledge amount must be at least {} SOL",
+            min_donation
+        )));
+    }
+
+    let new_pledge = NewPledge {
+        identity_id,
+        project_id: req.project_id,
+        amount,
+        interval_days: req.interval_days,
+        notify_channel: req.notify_channel,
+        notify_target: req.notify_target,
+        next_due_at: req.start_at.unwrap_or_else(|| Utc::now().naive_utc()),
+    };
+
+    let pledge = pledges.create(new_pledge).await?;
+    Ok(HttpResponse::Created().json(pledge))
+}
+
+/// Pledges of other identities are answered as not found, so their ids can't be probed.
+#[get("/{id}")]
+pub async fn get_pledge(
+    http_req: HttpRequest,
+    auth_service: web::Data<AuthService>,
+    identities: web::Data<IdentityRepo>,
+    pledges: web::Data<PledgeRepo>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let identity_id = caller_identity(&http_req, &auth_service, &identities).await?;
+    let (pledge, reminders) = pledges
+        .find_with_reminders(path.into_inner())
+        .await?
+        .filter(|(pledge, _)| pledge.identity_id == identity_id)
+        .ok_or_else(|| AppError::not_found("Pledge not found"))?;
+
+    Ok(HttpResponse::Ok().json(PledgeResponse { pledge, reminders }))
+}
+
+#[delete("/{id}")]
+pub async fn cancel_pledge(
+    http_req: HttpRequest,
+    auth_service: web::Data<AuthService>,
+    identities: web::Data<IdentityRepo>,
+    pledges: web::Data<PledgeRepo>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let identity_id = caller_identity(&http_req, &auth_service, &identities).await?;
+    let pledge = pledges
+        .cancel(path.into_inner(), identity_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Pledge not found"))?;
+
+    Ok(HttpResponse::Ok().json(pledge))
+}
+
+#[post("/reminders/{id}/confirm")]
+pub async fn confirm_reminder(
+    identities: web::Data<IdentityRepo>,
+    pledges: web::Data<PledgeRepo>,
+    projects: web::Data<ProjectRepo>,
+    cache: web::Data<ProjectCache<serde_json::Value>>,
+    path: web::Path<Uuid>,
+    cluster: web::Data<SolanaCluster>,
+    req: web::Json<ConfirmReminderRequest>,
+) -> Result<HttpResponse, AppError> {
+    let reminder_id = path.into_inner();
+
+    let signature = Signature::from_str(&req.signature)
+        .map_err(|_| AppError::validation("Invalid transaction signature"))?;
+
+    let (reminder, pledge) = pledges
+        .find_reminder(reminder_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Pledge reminder not found"))?;
+
+    if reminder.fulfilled_at.is_some() {
+        return Err(AppError::conflict("Pledge reminder already fulfilled"));
+    }
+
+    // The transaction must be a confirmed donation to the pledged project
+    let donation = match projects.record_donation(&cluster, pledge.project_id, signature, None).await {
+        Ok(donation) => {
+            cache.invalidate(pledge.project_id);
+            donation
+        }
+        Err(DonationError::AlreadyRecorded) => projects
+            .find_donation(pledge.project_id, signature)
+            .await?
+            .ok_or_else(|| AppError::validation("Transaction was recorded for a different project"))?,
+        Err(e) => return Err(e.into()),
+    };
+
+    // the donation must come from one of the pledging identity's wallets
+    let donor_identity = match &donation.donor_wallet {
+        Some(wallet) => identities.find_by_subject(wallet).await?,
+        None => None,
+    };
+    check_fulfilment(&pledge, &donation, donor_identity)?;
+
+    let reminder = pledges.fulfil_reminder(reminder_id, signature).await?;
+    Ok(HttpResponse::Ok().json(reminder))
+}
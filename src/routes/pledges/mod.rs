@@ -0,0 +1,13 @@
+use actix_web::web;
+
+pub mod models;
+mod handlers;
+
+pub use handlers::{create_pledge, get_pledge, cancel_pledge, confirm_reminder};
+
+pub fn pledges_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(create_pledge)
+       .service(confirm_reminder)
+       .service(get_pledge)
+       .service(cancel_pledge);
+}
\ No newline at end of file
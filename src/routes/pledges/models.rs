@@ -0,0 +1,76 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = crate::schema::pledges)]
+pub struct Pledge {
+    pub id: Uuid,
+    pub identity_id: Uuid,
+    pub project_id: i32,
+    pub amount: f64,
+    pub interval_days: i32,
+    pub notify_channel: String,
+    pub notify_target: String,
+    pub next_due_at: NaiveDateTime,
+    pub active: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::pledges)]
+pub struct NewPledge {
+    pub identity_id: Uuid,
+    pub project_id: i32,
+    pub amount: f64,
+    pub interval_days: i32,
+    pub notify_channel: String,
+    pub notify_target: String,
+    pub next_due_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = crate::schema::pledge_reminders)]
+pub struct PledgeReminder {
+    pub id: Uuid,
+    pub pledge_id: Uuid,
+    pub due_at: NaiveDateTime,
+    pub action_url: String,
+    pub delivered_at: Option<NaiveDateTime>,
+    pub delivery_error: Option<String>,
+    pub fulfilled_at: Option<NaiveDateTime>,
+    pub signature: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::pledge_reminders)]
+pub struct NewPledgeReminder {
+    pub id: Uuid,
+    pub pledge_id: Uuid,
+    pub due_at: NaiveDateTime,
+    pub action_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePledgeRequest {
+    pub project_id: i32,
+    pub amount: String,
+    pub interval_days: i32,
+    pub notify_channel: String,
+    pub notify_target: String,
+    pub start_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ConfirmReminderRequest {
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PledgeResponse {
+    #[serde(flatten)]
+    pub pledge: Pledge,
+    pub reminders: Vec<PledgeReminder>,
+}
@@ -0,0 +1,219 @@
+use std::str::FromStr;
+use std::collections::HashSet;
+use actix_web::{get, post, put, web, HttpResponse};
+use solana_sdk::{
+    native_token::{lamports_to_sol, LAMPORTS_PER_SOL},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use crate::config::SolanaCluster;
+use crate::error::AppError;
+use crate::repos::ProjectRepo;
+use crate::routes::blink_chain::models::NewBeneficiary;
+use crate::routes::blink_chain::split::check_shares;
+use crate::services::cache::ProjectCache;
+use crate::services::challenges::ChallengeStore;
+use crate::services::donations::AnonymousDonor;
+use crate::services::membership::MembershipGroup;
+use crate::services::wallet_signatures;
+use crate::zk::field::parse_fr;
+use crate::zk::membership::{scope_to_field, signal_to_field, MembershipInputs};
+use crate::zk::ZKVerifier;
+use super::models::*;
+
+const DEFAULT_DONORS_LIMIT: i64 = 10;
+const MAX_DONORS_LIMIT: i64 = 100;
+
+/// Action the project wallet signs to replace the beneficiaries, see [`wallet_signatures`].
+pub const SET_BENEFICIARIES_ACTION: &str = "set beneficiaries";
+
+/// Action the donor wallet signs to record its donation anonymously, see [`wallet_signatures`].
+pub const ANONYMOUS_DONATION_ACTION: &str = "record anonymous donation";
+
+#[post("/{id}/donations")]
+#[allow(clippy::too_many_arguments)]
+pub async fn record_donation(
+    projects: web::Data<ProjectRepo>,
+    cache: web::Data<ProjectCache<serde_json::Value>>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+    group: web::Data<MembershipGroup>,
+    challenges: web::Data<ChallengeStore>,
+    path: web::Path<i32>,
+    cluster: web::Data<SolanaCluster>,
+    req: web::Json<RecordDonationRequest>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let req = req.into_inner();
+
+    let signature = Signature::from_str(&req.signature)
+        .map_err(|_| AppError::validation("Invalid transaction signature"))?;
+
+    // a membership proof bound to this transaction lets the donor be counted without linking their wallet
+    let anonymous = match req.anonymous {
+        Some(anonymous) => {
+            let wallet = Pubkey::from_str(&anonymous.donor_wallet)
+                .map_err(|_| AppError::validation("Invalid donor_wallet"))?;
+            let root = parse_fr(&anonymous.root)
+                .map_err(|e| AppError::validation(format!("Invalid root: {}", e)))?;
+            let nullifier_hash = parse_fr(&anonymous.nullifier_hash)
+                .map_err(|e| AppError::validation(format!("Invalid nullifier_hash: {}", e)))?;
+            if !group.is_known_root(&root) {
+                return Err(AppError::validation("Unknown or outdated root, fetch a new Merkle path"));
+            }
+            let inputs = MembershipInputs {
+                root,
+                nullifier_hash,
+                external_nullifier: scope_to_field(&donor_scope(project_id)),
+                signal_hash: signal_to_field(&req.signature),
+            };
+            let proof = anonymous.proof;
+            web::block(move || zk_verifier.verify_membership(&proof, &inputs)).await??;
+
+            // the proof only shows the submitter is a member; the wallet signature shows the
+            // donation is theirs, and the transaction is checked to come from that wallet
+            wallet_signatures::authorize(
+                &challenges,
+                &anonymous.donor_wallet,
+                ANONYMOUS_DONATION_ACTION,
+                &anonymous.challenge,
+                &anonymous.wallet_signature,
+            )?;
+            Some(AnonymousDonor { nullifier_hash, wallet })
+        }
+        None => None,
+    };
+
+    let donation = projects.record_donation(&cluster, project_id, signature, anonymous).await?;
+    cache.invalidate(project_id);
+    Ok(HttpResponse::Created().json(donation))
+}
+
+#[put("/{id}/beneficiaries")]
+pub async fn set_beneficiaries(
+    projects: web::Data<ProjectRepo>,
+    challenges: web::Data<ChallengeStore>,
+    path: web::Path<i32>,
+    req: web::Json<SetBeneficiariesRequest>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let req = req.into_inner();
+
+    let mut wallets = HashSet::new();
+    for beneficiary in &req.beneficiaries {
+        if Pubkey::from_str(&beneficiary.wallet).is_err() {
+            return Err(AppError::validation(format!("Invalid beneficiary wallet: {}", beneficiary.wallet)));
+        }
+        if !wallets.insert(beneficiary.wallet.as_str()) {
+            return Err(AppError::validation(format!("Duplicate beneficiary wallet: {}", beneficiary.wallet)));
+        }
+    }
+    check_shares(req.beneficiaries.iter().map(|b| b.share_bps)).map_err(|e| AppError::validation(e.to_string()))?;
+
+    // only the project wallet may redirect where donations go
+    let project = projects
+        .find(project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+    wallet_signatures::authorize(
+        &challenges,
+        &project.wallet,
+        SET_BENEFICIARIES_ACTION,
+        &req.challenge,
+        &req.signature,
+    )?;
+
+    let beneficiaries = req
+        .beneficiaries
+        .into_iter()
+        .map(|b| NewBeneficiary {
+            project_id,
+            wallet: b.wallet,
+            share_bps: b.share_bps,
+            label: b.label,
+        })
+        .collect();
+    let beneficiaries = projects.replace_beneficiaries(project_id, beneficiaries).await?;
+    Ok(HttpResponse::Ok().json(beneficiaries))
+}
+
+#[get("/{id}/stats")]
+pub async fn get_project_stats(
+    projects: web::Data<ProjectRepo>,
+    cache: web::Data<ProjectCache<serde_json::Value>>,
+    path: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+
+    if let Some(stats) = cache.get(project_id, "stats") {
+        return Ok(HttpResponse::Ok().json(stats));
+    }
+
+    let (totals, daily) = projects
+        .stats(project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    let stats = ProjectStats {
+        project_id,
+        total_raised: lamports_to_sol(totals.total_lamports as u64),
+        total_raised_lamports: totals.total_lamports,
+        donation_count: totals.donation_count,
+        donor_count: totals.donor_count,
+        anonymous_donation_count: totals.anonymous_donation_count,
+        average_donation: totals.average_lamports / LAMPORTS_PER_SOL as f64,
+        median_donation: totals.median_lamports / LAMPORTS_PER_SOL as f64,
+        daily: daily.into_iter().map(|d| DailyStats {
+            day: d.day,
+            total: lamports_to_sol(d.total_lamports as u64),
+            donation_count: d.donation_count,
+        }).collect(),
+    };
+
+    let stats = serde_json::to_value(stats)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize project stats: {}", e)))?;
+    cache.insert(project_id, "stats", stats.clone());
+    Ok(HttpResponse::Ok().json(stats))
+}
+
+#[get("/{id}/donors")]
+pub async fn get_project_donors(
+    projects: web::Data<ProjectRepo>,
+    cache: web::Data<ProjectCache<serde_json::Value>>,
+    path: web::Path<i32>,
+    query: web::Query<DonorsQuery>,
+) -> Result<HttpResponse, AppError> {
+    let project_id = path.into_inner();
+    let limit = query.limit.unwrap_or(DEFAULT_DONORS_LIMIT).clamp(1, MAX_DONORS_LIMIT);
+    let anonymize = query.anonymize.unwrap_or(false);
+
+    let cache_key = format!("donors:{}:{}", limit, anonymize);
+    if let Some(donors) = cache.get(project_id, &cache_key) {
+        return Ok(HttpResponse::Ok().json(donors));
+    }
+
+    let top = projects
+        .top_donors(project_id, limit)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project not found"))?;
+
+    let donors: Vec<DonorStats> = top.into_iter().enumerate().map(|(i, d)| DonorStats {
+        rank: i + 1,
+        donor: if anonymize { anonymize_wallet(&d.donor_wallet) } else { d.donor_wallet },
+        total: lamports_to_sol(d.total_lamports as u64),
+        donation_count: d.donation_count,
+        last_donated_at: d.last_donated_at,
+    }).collect();
+
+    let donors = serde_json::to_value(donors)
+        .map_err(|e| AppError::Internal(format!("Failed to serialize project donors: {}", e)))?;
+    cache.insert(project_id, &cache_key, donors.clone());
+    Ok(HttpResponse::Ok().json(donors))
+}
+
+/// Keeps only the first and last four characters of a wallet address.
+fn anonymize_wallet(wallet: &str) -> String {
+    if wallet.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("{}…{}", &wallet[..4], &wallet[wallet.len() - 4..])
+}
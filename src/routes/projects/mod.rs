@@ -0,0 +1,13 @@
+use actix_web::web;
+
+pub mod models;
+mod handlers;
+
+pub use handlers::{record_donation, set_beneficiaries, get_project_stats, get_project_donors, SET_BENEFICIARIES_ACTION, ANONYMOUS_DONATION_ACTION};
+
+pub fn projects_config(cfg: &mut web::ServiceConfig) {
+    cfg.service(record_donation)
+       .service(set_beneficiaries)
+       .service(get_project_stats)
+       .service(get_project_donors);
+}
\ No newline at end of file
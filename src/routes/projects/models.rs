@@ -0,0 +1,143 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Queryable)]
+#[diesel(table_name = crate::schema::donations)]
+pub struct Donation {
+    pub id: Uuid,
+    pub project_id: i32,
+    pub donor_wallet: Option<String>,
+    pub amount_lamports: i64,
+    pub signature: Option<String>,
+    pub slot: Option<i64>,
+    pub confirmed_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+    pub signature_hash: String,
+    pub verified_anonymous: bool,
+    pub donor_nullifier: Option<String>,
+}
+
+#[derive(Debug, Insertable)]
+#[diesel(table_name = crate::schema::donations)]
+pub struct NewDonation {
+    pub project_id: i32,
+    pub donor_wallet: Option<String>,
+    pub amount_lamports: i64,
+    pub signature: Option<String>,
+    pub slot: Option<i64>,
+    pub confirmed_at: NaiveDateTime,
+    pub signature_hash: String,
+    pub verified_anonymous: bool,
+    pub donor_nullifier: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RecordDonationRequest {
+    pub signature: String,
+    /// When present the donation is recorded without the donor's wallet.
+    pub anonymous: Option<AnonymousDonorProof>,
+}
+
+/// Membership proof for the scope [`donor_scope`] with the transaction signature as signal,
+/// showing the donor is a member of the group without revealing which one, and a signature
+/// of the wallet the donation was sent from, so nobody else can claim the transaction.
+#[derive(Debug, Deserialize)]
+pub struct AnonymousDonorProof {
+    pub proof: String,
+    /// Hex root the proof was made against, one of the group's recent roots.
+    pub root: String,
+    pub nullifier_hash: String,
+    /// Wallet the donation was sent from. It is checked but not stored.
+    pub donor_wallet: String,
+    /// Challenge from `/auth/zk/challenge` issued for the donor wallet.
+    pub challenge: String,
+    /// Base58 signature of the donor wallet over
+    /// `wallet_signatures::message(ANONYMOUS_DONATION_ACTION, challenge)`.
+    pub wallet_signature: String,
+}
+
+/// Membership scope of anonymous donations to `project_id`. An identity's nullifier hash is
+/// the same for all its donations to a project, so the donor is counted once.
+pub fn donor_scope(project_id: i32) -> String {
+    format!("donor:{}", project_id)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BeneficiaryInput {
+    pub wallet: String,
+    pub share_bps: i32,
+    pub label: Option<String>,
+}
+
+/// Replaces the beneficiaries of a project, signed by the project wallet.
+#[derive(Debug, Deserialize)]
+pub struct SetBeneficiariesRequest {
+    /// Challenge from `/auth/zk/challenge` issued for the project wallet.
+    pub challenge: String,
+    /// Base58 signature of the project wallet over
+    /// `wallet_signatures::message(SET_BENEFICIARIES_ACTION, challenge)`.
+    pub signature: String,
+    pub beneficiaries: Vec<BeneficiaryInput>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DonorsQuery {
+    pub limit: Option<i64>,
+    pub anonymize: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DonationTotals {
+    pub total_lamports: i64,
+    pub donation_count: i64,
+    pub donor_count: i64,
+    pub anonymous_donation_count: i64,
+    pub average_lamports: f64,
+    pub median_lamports: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyTotal {
+    pub day: NaiveDate,
+    pub total_lamports: i64,
+    pub donation_count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DonorTotal {
+    pub donor_wallet: String,
+    pub total_lamports: i64,
+    pub donation_count: i64,
+    pub last_donated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyStats {
+    pub day: NaiveDate,
+    pub total: f64,
+    pub donation_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProjectStats {
+    pub project_id: i32,
+    pub total_raised: f64,
+    pub total_raised_lamports: i64,
+    pub donation_count: i64,
+    pub donor_count: i64,
+    pub anonymous_donation_count: i64,
+    pub average_donation: f64,
+    pub median_donation: f64,
+    pub daily: Vec<DailyStats>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DonorStats {
+    pub rank: usize,
+    pub donor: String,
+    pub total: f64,
+    pub donation_count: i64,
+    pub last_donated_at: NaiveDateTime,
+}
@@ -1,48 +1,19 @@
-use actix_web::{get, post, web, HttpResponse, Responder};
-use diesel::prelude::*;
-use crate::DbPool;
-use super::models::{User, UserResponse};
+use actix_web::{get, post, web, HttpResponse};
+use crate::error::AppError;
+use crate::repos::UserRepo;
+use super::models::User;
 
 #[get("/users")]
-pub async fn get_users(pool: web::Data<DbPool>) -> impl Responder {
-    use crate::schema::users::dsl::*;
-
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database connection error"
-        })),
-    };
-
-    match users.load::<UserResponse>(&mut conn) {
-        Ok(results) => HttpResponse::Ok().json(results),
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to fetch users"
-        })),
-    }
+pub async fn get_users(users: web::Data<UserRepo>) -> Result<HttpResponse, AppError> {
+    let results = users.list().await?;
+    Ok(HttpResponse::Ok().json(results))
 }
 
 #[post("/users")]
 pub async fn create_user(
-    pool: web::Data<DbPool>,
+    users: web::Data<UserRepo>,
     user: web::Json<User>
-) -> impl Responder {
-    use crate::schema::users::dsl::*;
-
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => return HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database connection error"
-        })),
-    };
-
-    match diesel::insert_into(users)
-        .values(&user.into_inner())
-        .get_result::<UserResponse>(&mut conn)
-    {
-        Ok(result) => HttpResponse::Created().json(result),
-        Err(_) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Failed to create user"
-        })),
-    }
-} 
\ No newline at end of file
+) -> Result<HttpResponse, AppError> {
+    let result = users.create(user.into_inner()).await?;
+    Ok(HttpResponse::Created().json(result))
+}
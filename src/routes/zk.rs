@@ -0,0 +1,401 @@
+use actix_web::{web, HttpRequest, HttpResponse, Scope};
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use crate::error::AppError;
+use crate::repos::IdentityRepo;
+use crate::routes::auth::bearer_subject;
+use crate::services::auth::AuthService;
+use crate::services::challenges::ChallengeStore;
+use crate::services::donation_commitments::DonationCommitmentStore;
+use crate::services::email_commitments::EmailCommitmentStore;
+use crate::services::email_verification::EmailVerifications;
+use crate::services::membership::{MembershipGroup, NullifierStore};
+use crate::services::wallet_signatures;
+use crate::zk::attributes::split_email;
+use crate::zk::field::{fr_to_hex, parse_fr, FieldError};
+use crate::zk::circuits::CircuitId;
+use crate::zk::envelope::{ProofEnvelope, ProofSystem};
+use crate::zk::membership::{scope_to_field, signal_to_field, MembershipInputs};
+use crate::zk::solana::{fr_to_bytes, SolanaProof, SolanaProofHex, SolanaVerifyingKey, SolanaVerifyingKeyHex};
+use crate::zk::error::ZkError;
+use crate::zk::{BatchProof, ZKVerifier};
+
+/// Upper bound on proofs per request, so one call can't tie up a blocking thread for long.
+pub const MAX_BATCH_SIZE: usize = 256;
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyBatchRequest {
+    pub proofs: Vec<BatchProof>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchProofResult {
+    pub index: usize,
+    pub valid: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyBatchResponse {
+    pub results: Vec<BatchProofResult>,
+    pub valid_count: usize,
+    pub invalid_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MembershipRootResponse {
+    pub root: String,
+    pub depth: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MembershipPathResponse {
+    pub commitment: String,
+    pub leaf_index: usize,
+    pub root: String,
+    /// Sibling hashes from the leaf up to the root.
+    pub siblings: Vec<String>,
+    /// 1 where the node on the way up is the right child, 0 where it is the left one.
+    pub path_indices: Vec<u8>,
+}
+
+/// Signed by a wallet to register its membership commitment.
+pub const REGISTER_COMMITMENT_ACTION: &str = "register membership commitment";
+
+/// A commitment for the identity of the bearer token. The token alone doesn't show the
+/// caller owns that identity, so a wallet signs a challenge and an email address proves
+/// its inbox with a code.
+#[derive(Debug, Deserialize)]
+pub struct RegisterCommitmentRequest {
+    /// Hex commitment of the identity secrets, kept on the member's device.
+    pub commitment: String,
+    /// For a wallet: challenge from `/auth/zk/challenge` issued for the wallet.
+    pub challenge: Option<String>,
+    /// For a wallet: its base58 signature over
+    /// `wallet_signatures::message(REGISTER_COMMITMENT_ACTION, challenge)`.
+    pub signature: Option<String>,
+    /// For an email address: the code sent by `/auth/zk/email-commitment/code`.
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegisterCommitmentResponse {
+    pub leaf_index: usize,
+    pub root: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyMembershipRequest {
+    pub proof: String,
+    /// Hex root the proof was made against, one of the group's recent roots.
+    pub root: String,
+    pub nullifier_hash: String,
+    /// What the proof is used for, e.g. `poll:42`. Each identity can prove once per scope.
+    pub scope: String,
+    /// Message the proof vouches for, e.g. the vote cast.
+    pub signal: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyMembershipResponse {
+    pub valid: bool,
+    pub nullifier_hash: String,
+    pub external_nullifier: String,
+    pub signal_hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailDomainRequest {
+    /// Commitment issued by `/auth/zk/email-commitment`.
+    pub commitment: String,
+    pub domain: String,
+    /// Challenge from `/auth/zk/challenge`, requested with the commitment as identity.
+    pub challenge: String,
+    pub proof: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyEmailDomainResponse {
+    pub valid: bool,
+    pub domain: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyDonationThresholdRequest {
+    /// Commitment issued by `/auth/zk/donation-commitment`.
+    pub commitment: String,
+    pub project_id: i32,
+    /// Smallest donation the proof shows, in lamports.
+    pub threshold_lamports: u64,
+    /// Challenge from `/auth/zk/challenge`, requested with the commitment as identity.
+    pub challenge: String,
+    pub proof: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VerifyDonationThresholdResponse {
+    pub valid: bool,
+    pub project_id: i32,
+    pub threshold_lamports: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SolanaProofRequest {
+    /// Base64 proof as created by the prover backend.
+    pub proof: String,
+    /// Hex public inputs the proof is for.
+    pub public_inputs: Vec<String>,
+}
+
+pub fn zk_routes() -> Scope {
+    web::scope("/zk")
+        .route("/verify-batch", web::post().to(verify_batch))
+        .route("/membership/root", web::get().to(membership_root))
+        .route("/membership/path/{commitment}", web::get().to(membership_path))
+        .route("/membership/commitments", web::post().to(register_commitment))
+        .route("/membership/verify", web::post().to(verify_membership))
+        .route("/email-domain/verify", web::post().to(verify_email_domain))
+        .route("/donation-threshold/verify", web::post().to(verify_donation_threshold))
+        .route("/solana/verifying-key/{circuit_id}", web::get().to(solana_verifying_key))
+        .route("/solana/proof", web::post().to(solana_proof))
+}
+
+async fn verify_batch(
+    req: web::Json<VerifyBatchRequest>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+) -> Result<HttpResponse, AppError> {
+    let proofs = req.into_inner().proofs;
+    log::debug!("Received batch verification request for {} proofs", proofs.len());
+
+    if proofs.is_empty() || proofs.len() > MAX_BATCH_SIZE {
+        return Err(AppError::validation(format!(
+            "A batch must contain between 1 and {} proofs",
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    let results = web::block(move || zk_verifier.verify_batch(&proofs)).await?;
+
+    let valid_count = results.iter().filter(|valid| **valid).count();
+    Ok(HttpResponse::Ok().json(VerifyBatchResponse {
+        invalid_count: results.len() - valid_count,
+        valid_count,
+        results: results
+            .into_iter()
+            .enumerate()
+            .map(|(index, valid)| BatchProofResult { index, valid })
+            .collect(),
+    }))
+}
+
+async fn membership_root(group: web::Data<MembershipGroup>) -> HttpResponse {
+    HttpResponse::Ok().json(MembershipRootResponse {
+        root: fr_to_hex(&group.root()),
+        depth: group.depth(),
+        size: group.size(),
+    })
+}
+
+async fn membership_path(
+    commitment: web::Path<String>,
+    group: web::Data<MembershipGroup>,
+) -> Result<HttpResponse, AppError> {
+    let commitment = parse_fr(&commitment).map_err(|e| invalid_field("commitment", e))?;
+
+    let (leaf_index, path, root) = group
+        .path(&commitment)
+        .ok_or_else(|| AppError::not_found("Commitment is not in the group"))?;
+    Ok(HttpResponse::Ok().json(MembershipPathResponse {
+        commitment: fr_to_hex(&commitment),
+        leaf_index,
+        root: fr_to_hex(&root),
+        siblings: path.siblings.iter().map(fr_to_hex).collect(),
+        path_indices: path.is_right.iter().map(|is_right| *is_right as u8).collect(),
+    }))
+}
+
+async fn register_commitment(
+    req: HttpRequest,
+    body: web::Json<RegisterCommitmentRequest>,
+    auth_service: web::Data<AuthService>,
+    challenges: web::Data<ChallengeStore>,
+    verifications: web::Data<EmailVerifications>,
+    group: web::Data<MembershipGroup>,
+    identities: web::Data<IdentityRepo>,
+) -> Result<HttpResponse, AppError> {
+    let subject =
+        bearer_subject(&req, &auth_service).ok_or_else(|| AppError::unauthorized("A valid auth token is required"))?;
+    let commitment = parse_fr(&body.commitment).map_err(|e| invalid_field("commitment", e))?;
+
+    // anyone can get a login token for an identity, only its wallet or inbox shows it's theirs
+    if split_email(&subject).is_some() {
+        let code = body.code.as_deref().ok_or_else(|| AppError::validation("Missing code"))?;
+        verifications.confirm(&subject, code)?;
+    } else {
+        let (Some(challenge), Some(signature)) = (&body.challenge, &body.signature) else {
+            return Err(AppError::validation("Missing challenge or signature"));
+        };
+        wallet_signatures::authorize(&challenges, &subject, REGISTER_COMMITMENT_ACTION, challenge, signature)?;
+    }
+
+    let (leaf_index, root) = identities.register_commitment(group.into_inner(), &subject, commitment).await?;
+    Ok(HttpResponse::Created().json(RegisterCommitmentResponse {
+        leaf_index,
+        root: fr_to_hex(&root),
+    }))
+}
+
+async fn verify_membership(
+    req: web::Json<VerifyMembershipRequest>,
+    group: web::Data<MembershipGroup>,
+    nullifiers: web::Data<dyn NullifierStore>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner();
+    let root = parse_fr(&req.root).map_err(|e| invalid_field("root", e))?;
+    let nullifier_hash = parse_fr(&req.nullifier_hash).map_err(|e| invalid_field("nullifier_hash", e))?;
+    if !group.is_known_root(&root) {
+        return Err(AppError::validation("Unknown or outdated root, fetch a new Merkle path"));
+    }
+
+    let inputs = MembershipInputs {
+        root,
+        nullifier_hash,
+        external_nullifier: scope_to_field(&req.scope),
+        signal_hash: signal_to_field(&req.signal),
+    };
+    let proof = req.proof;
+    web::block(move || -> Result<(), AppError> {
+        zk_verifier.verify_membership(&proof, &inputs)?;
+        // recorded only once the proof checks out, so invalid proofs can't burn a nullifier
+        nullifiers.record(&inputs)?;
+        Ok(())
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(VerifyMembershipResponse {
+        valid: true,
+        nullifier_hash: fr_to_hex(&inputs.nullifier_hash),
+        external_nullifier: fr_to_hex(&inputs.external_nullifier),
+        signal_hash: fr_to_hex(&inputs.signal_hash),
+    }))
+}
+
+async fn verify_email_domain(
+    req: web::Json<VerifyEmailDomainRequest>,
+    commitments: web::Data<dyn EmailCommitmentStore>,
+    challenges: web::Data<ChallengeStore>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner();
+    let commitment = parse_fr(&req.commitment).map_err(|e| invalid_field("commitment", e))?;
+    let challenge = parse_fr(&req.challenge).map_err(|e| invalid_field("challenge", e))?;
+    // challenges for attribute proofs are issued to the commitment
+    let holder = fr_to_hex(&commitment);
+    if !challenges.is_pending(&challenge, &holder) {
+        return Err(unknown_challenge());
+    }
+
+    let domain = req.domain.to_ascii_lowercase();
+    let proof_domain = domain.clone();
+    web::block(move || -> Result<(), AppError> {
+        if !commitments.is_issued(&commitment)? {
+            return Err(not_issued());
+        }
+        Ok(zk_verifier.verify_email_domain(commitment, &proof_domain, challenge, &req.proof)?)
+    })
+    .await??;
+
+    if !challenges.consume(&challenge, &holder) {
+        return Err(used_challenge());
+    }
+    Ok(HttpResponse::Ok().json(VerifyEmailDomainResponse { valid: true, domain }))
+}
+
+async fn verify_donation_threshold(
+    req: web::Json<VerifyDonationThresholdRequest>,
+    commitments: web::Data<dyn DonationCommitmentStore>,
+    challenges: web::Data<ChallengeStore>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner();
+    let commitment = parse_fr(&req.commitment).map_err(|e| invalid_field("commitment", e))?;
+    let challenge = parse_fr(&req.challenge).map_err(|e| invalid_field("challenge", e))?;
+    // challenges for attribute proofs are issued to the commitment
+    let holder = fr_to_hex(&commitment);
+    if !challenges.is_pending(&challenge, &holder) {
+        return Err(unknown_challenge());
+    }
+
+    let (project_id, threshold) = (req.project_id, req.threshold_lamports);
+    web::block(move || -> Result<(), AppError> {
+        if !commitments.is_issued(&commitment)? {
+            return Err(not_issued());
+        }
+        Ok(zk_verifier.verify_donation_threshold(commitment, project_id, threshold, challenge, &req.proof)?)
+    })
+    .await??;
+
+    if !challenges.consume(&challenge, &holder) {
+        return Err(used_challenge());
+    }
+    Ok(HttpResponse::Ok().json(VerifyDonationThresholdResponse {
+        valid: true,
+        project_id,
+        threshold_lamports: threshold,
+    }))
+}
+
+/// Verifying key of a circuit in the layout groth16-solana expects, hex encoded.
+async fn solana_verifying_key(
+    circuit_id: web::Path<String>,
+    zk_verifier: web::Data<dyn ZKVerifier>,
+) -> Result<HttpResponse, AppError> {
+    let circuit = circuit_id.parse::<CircuitId>()?;
+    let vk = zk_verifier
+        .verifying_key(&circuit)
+        .ok_or_else(|| AppError::not_found(format!("No verifying key loaded for {}", circuit)))?;
+    Ok(HttpResponse::Ok().json(SolanaVerifyingKeyHex::from(&SolanaVerifyingKey::from_vk(&vk))))
+}
+
+/// Converts a proof to the layout groth16-solana expects, so clients can submit it on chain.
+async fn solana_proof(req: web::Json<SolanaProofRequest>) -> Result<HttpResponse, AppError> {
+    let req = req.into_inner();
+    let bytes = BASE64.decode(&req.proof).map_err(|_| ZkError::MalformedBase64)?;
+    // only Groth16 proofs can be checked on chain
+    let bytes = ProofEnvelope::open(&bytes, ProofSystem::Groth16)?;
+    let proof = SolanaProof::from_compressed(&bytes).map_err(|e| AppError::validation(e.to_string()))?;
+    let public_inputs = req
+        .public_inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            parse_fr(input)
+                .map(|input| hex::encode(fr_to_bytes(&input)))
+                .map_err(|e| invalid_field(&format!("public_inputs[{}]", index), e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(SolanaProofHex {
+        proof_a: hex::encode(proof.a),
+        proof_b: hex::encode(proof.b),
+        proof_c: hex::encode(proof.c),
+        public_inputs,
+    }))
+}
+
+fn invalid_field(name: &str, error: FieldError) -> AppError {
+    AppError::validation(format!("{} is invalid: {}", name, error))
+}
+
+fn unknown_challenge() -> AppError {
+    AppError::unauthorized("Challenge is unknown, expired or was issued for another identity")
+}
+
+fn used_challenge() -> AppError {
+    AppError::unauthorized("Challenge was already used")
+}
+
+fn not_issued() -> AppError {
+    AppError::forbidden("Commitment was not issued by this server")
+}
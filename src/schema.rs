@@ -1,5 +1,35 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    donation_commitments (commitment) {
+        commitment -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    donations (id) {
+        id -> Uuid,
+        project_id -> Int4,
+        donor_wallet -> Nullable<Varchar>,
+        amount_lamports -> Int8,
+        signature -> Nullable<Varchar>,
+        slot -> Nullable<Int8>,
+        confirmed_at -> Timestamp,
+        created_at -> Timestamp,
+        signature_hash -> Varchar,
+        verified_anonymous -> Bool,
+        donor_nullifier -> Nullable<Varchar>,
+    }
+}
+
+diesel::table! {
+    email_commitments (commitment) {
+        commitment -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     email_identities (id) {
         id -> Uuid,
@@ -16,6 +46,56 @@ diesel::table! {
         id -> Uuid,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        commitment -> Nullable<Varchar>,
+        commitment_index -> Nullable<Int8>,
+    }
+}
+
+diesel::table! {
+    membership_nullifiers (external_nullifier, nullifier_hash) {
+        external_nullifier -> Varchar,
+        nullifier_hash -> Varchar,
+        signal_hash -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    pledge_reminders (id) {
+        id -> Uuid,
+        pledge_id -> Uuid,
+        due_at -> Timestamp,
+        action_url -> Varchar,
+        delivered_at -> Nullable<Timestamp>,
+        delivery_error -> Nullable<Text>,
+        fulfilled_at -> Nullable<Timestamp>,
+        signature -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    pledges (id) {
+        id -> Uuid,
+        identity_id -> Uuid,
+        project_id -> Int4,
+        amount -> Float8,
+        interval_days -> Int4,
+        notify_channel -> Varchar,
+        notify_target -> Varchar,
+        next_due_at -> Timestamp,
+        active -> Bool,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    project_beneficiaries (id) {
+        id -> Int4,
+        project_id -> Int4,
+        wallet -> Varchar,
+        share_bps -> Int4,
+        label -> Nullable<Varchar>,
     }
 }
 
@@ -50,12 +130,24 @@ diesel::table! {
     }
 }
 
+diesel::joinable!(donations -> projects (project_id));
 diesel::joinable!(email_identities -> identities (identity_id));
+diesel::joinable!(pledge_reminders -> pledges (pledge_id));
+diesel::joinable!(pledges -> identities (identity_id));
+diesel::joinable!(pledges -> projects (project_id));
+diesel::joinable!(project_beneficiaries -> projects (project_id));
 diesel::joinable!(wallet_identities -> identities (identity_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    donation_commitments,
+    donations,
+    email_commitments,
     email_identities,
     identities,
+    membership_nullifiers,
+    pledge_reminders,
+    pledges,
+    project_beneficiaries,
     projects,
     users,
     wallet_identities,
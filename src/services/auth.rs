@@ -1,7 +1,6 @@
-use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::PgConnection;
-use jsonwebtoken::{encode, EncodingKey, Header};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use crate::models::{WalletIdentity, EmailIdentity};
@@ -16,6 +15,8 @@ struct Claims {
 }
 
 pub struct AuthService {
+    // not read until the identity stubs below are implemented
+    #[allow(dead_code)]
     pool: Pool,
     jwt_secret: String,
 }
@@ -37,17 +38,17 @@ impl AuthService {
         })
     }
 
-    pub fn verify_signature(&self, wallet_address: &str, signature: &str, nonce: &str) -> bool {
+    pub fn verify_signature(&self, _wallet_address: &str, _signature: &str, _nonce: &str) -> bool {
         // Implement signature verification logic
         true
     }
 
-    pub async fn update_nonce(&self, wallet_address: &str) -> Result<String, diesel::result::Error> {
+    pub async fn update_nonce(&self, _wallet_address: &str) -> Result<String, diesel::result::Error> {
         // Implement nonce update logic
         Ok("new_nonce".to_string())
     }
 
-    pub async fn link_wallet_to_email(&self, email: &str, wallet_address: &str) -> Result<WalletIdentity, diesel::result::Error> {
+    pub async fn link_wallet_to_email(&self, _email: &str, wallet_address: &str) -> Result<WalletIdentity, diesel::result::Error> {
         // Implement wallet linking logic
         Ok(WalletIdentity {
             id: Uuid::new_v4(),
@@ -56,12 +57,12 @@ impl AuthService {
         })
     }
 
-    pub async fn link_email_to_wallet(&self, wallet_address: &str, email: &str, password_hash: &str) -> Result<(), diesel::result::Error> {
+    pub async fn link_email_to_wallet(&self, _wallet_address: &str, _email: &str, _password_hash: &str) -> Result<(), diesel::result::Error> {
         // Implement email linking logic
         Ok(())
     }
 
-    pub async fn get_user_identities(&self, user_id: Uuid) -> Result<(Option<EmailIdentity>, Option<WalletIdentity>), diesel::result::Error> {
+    pub async fn get_user_identities(&self, _user_id: Uuid) -> Result<(Option<EmailIdentity>, Option<WalletIdentity>), diesel::result::Error> {
         // Implement get identities logic
         Ok((None, None))
     }
@@ -82,4 +83,19 @@ impl AuthService {
 
         Ok((token, user_id))
     }
+
+    /// Checks that `token` was issued by this service and hasn't expired.
+    pub fn validate_token(&self, token: &str) -> Result<(), jsonwebtoken::errors::Error> {
+        self.token_subject(token).map(|_| ())
+    }
+
+    /// The wallet address or email a valid `token` was issued for.
+    pub fn token_subject(&self, token: &str) -> Result<String, jsonwebtoken::errors::Error> {
+        let data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
+            &Validation::default(),
+        )?;
+        Ok(data.claims.sub)
+    }
 } 
\ No newline at end of file
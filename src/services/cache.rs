@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// In-memory cache of per-project values that expire after a fixed TTL.
+pub struct ProjectCache<V> {
+    ttl: Duration,
+    entries: Mutex<HashMap<(i32, String), (Instant, V)>>,
+}
+
+impl<V: Clone> ProjectCache<V> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, project_id: i32, key: &str) -> Option<V> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&(project_id, key.to_string())) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, project_id: i32, key: &str, value: V) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+        entries.insert((project_id, key.to_string()), (Instant::now(), value));
+    }
+
+    /// Drops every cached value for `project_id`, e.g. after a new donation is recorded.
+    pub fn invalidate(&self, project_id: i32) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|(id, _), _| *id != project_id);
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use ark_bn254::Fr;
+use ark_std::UniformRand;
+use chrono::{DateTime, Utc};
+
+/// Most challenges outstanding at once, across all clients.
+pub const MAX_CHALLENGES: usize = 100_000;
+
+/// Most challenges outstanding at once for one client, which limits how many a client
+/// can be issued per challenge lifetime without using them.
+pub const MAX_CHALLENGES_PER_CLIENT: usize = 20;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ChallengeError {
+    #[error("Too many challenges outstanding, try again later")]
+    Full,
+    #[error("Too many challenges requested, use or let the outstanding ones expire first")]
+    RateLimited,
+}
+
+struct Entry {
+    identity: String,
+    client: String,
+    issued_at: Instant,
+}
+
+/// Outstanding challenges, indexed so that issuing one never scans the others: by issue
+/// time to drop expired ones from the front, and by client to count against its limit.
+#[derive(Default)]
+struct Entries {
+    by_challenge: HashMap<Fr, Entry>,
+    by_issue: BTreeSet<(Instant, Fr)>,
+    per_client: HashMap<String, usize>,
+}
+
+impl Entries {
+    fn insert(&mut self, challenge: Fr, entry: Entry) {
+        self.remove(&challenge);
+        self.by_issue.insert((entry.issued_at, challenge));
+        *self.per_client.entry(entry.client.clone()).or_default() += 1;
+        self.by_challenge.insert(challenge, entry);
+    }
+
+    fn remove(&mut self, challenge: &Fr) -> Option<Entry> {
+        let entry = self.by_challenge.remove(challenge)?;
+        self.unindex(*challenge, &entry);
+        Some(entry)
+    }
+
+    /// Drops the challenges issued `ttl` or longer ago, oldest first.
+    fn remove_expired(&mut self, ttl: Duration) {
+        while let Some(&(issued_at, challenge)) = self.by_issue.first() {
+            if issued_at.elapsed() < ttl {
+                break;
+            }
+            self.remove(&challenge);
+        }
+    }
+
+    fn unindex(&mut self, challenge: Fr, entry: &Entry) {
+        self.by_issue.remove(&(entry.issued_at, challenge));
+        if let Some(count) = self.per_client.get_mut(&entry.client) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_client.remove(&entry.client);
+            }
+        }
+    }
+}
+
+/// One-time challenges that ZK login proofs are bound to, each issued for one identity.
+/// Challenges live in memory, so a restart invalidates the ones still outstanding. The
+/// store is bounded both overall and per client, so unauthenticated callers can't grow it.
+pub struct ChallengeStore {
+    ttl: Duration,
+    capacity: usize,
+    per_client: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ChallengeStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_limits(ttl, MAX_CHALLENGES, MAX_CHALLENGES_PER_CLIENT)
+    }
+
+    pub fn with_limits(ttl: Duration, capacity: usize, per_client: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            per_client,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Issues a fresh challenge for `identity` to `client`, returning it with its expiry.
+    /// Expired challenges are dropped first, so only outstanding ones count to the limits.
+    pub fn issue(&self, identity: &str, client: &str) -> Result<(Fr, DateTime<Utc>), ChallengeError> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove_expired(self.ttl);
+
+        if entries.per_client.get(client).copied().unwrap_or(0) >= self.per_client {
+            return Err(ChallengeError::RateLimited);
+        }
+        if entries.by_challenge.len() >= self.capacity {
+            return Err(ChallengeError::Full);
+        }
+
+        let challenge = Fr::rand(&mut ark_std::rand::thread_rng());
+        let entry = Entry {
+            identity: identity.to_string(),
+            client: client.to_string(),
+            issued_at: Instant::now(),
+        };
+        entries.insert(challenge, entry);
+
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or(chrono::Duration::MAX);
+        Ok((challenge, Utc::now() + ttl))
+    }
+
+    /// Whether `challenge` was issued for `identity` and is neither used nor expired.
+    pub fn is_pending(&self, challenge: &Fr, identity: &str) -> bool {
+        let entries = self.entries.lock().unwrap();
+        matches!(
+            entries.by_challenge.get(challenge),
+            Some(entry) if entry.identity == identity && entry.issued_at.elapsed() < self.ttl
+        )
+    }
+
+    /// Marks `challenge` as used. Returns false if it wasn't pending for `identity`, e.g.
+    /// because a concurrent request with the same proof consumed it first.
+    pub fn consume(&self, challenge: &Fr, identity: &str) -> bool {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.by_challenge.get(challenge) {
+            Some(entry) if entry.identity == identity => {
+                let pending = entry.issued_at.elapsed() < self.ttl;
+                entries.remove(challenge);
+                pending
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use ark_bn254::Fr;
+use diesel::prelude::*;
+use uuid::Uuid;
+use crate::DbPool;
+use crate::schema::{donation_commitments, donations};
+use crate::zk::field::fr_to_hex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DonationCommitmentError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+}
+
+/// The part of a recorded donation its commitment is made to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommittedDonation {
+    pub project_id: i32,
+    pub amount_lamports: u64,
+}
+
+/// Commitments issued to donors for their recorded donations, see `zk::donations`.
+/// Threshold proofs are only accepted for commitments found here, since anyone can
+/// commit to an amount they never gave.
+pub trait DonationCommitmentStore: Send + Sync {
+    /// The donation `donation_id` if it was made from `wallet`. Anonymous donations don't
+    /// keep their wallet, so they are never found.
+    fn find_donation(&self, donation_id: Uuid, wallet: &str) -> Result<Option<CommittedDonation>, DonationCommitmentError>;
+    fn issue(&self, commitment: &Fr) -> Result<(), DonationCommitmentError>;
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, DonationCommitmentError>;
+}
+
+pub struct PgDonationCommitmentStore {
+    pool: DbPool,
+}
+
+impl PgDonationCommitmentStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl DonationCommitmentStore for PgDonationCommitmentStore {
+    fn find_donation(&self, donation_id: Uuid, wallet: &str) -> Result<Option<CommittedDonation>, DonationCommitmentError> {
+        let mut conn = self.pool.get()?;
+        let found = donations::table
+            .find(donation_id)
+            .filter(donations::donor_wallet.eq(wallet))
+            .select((donations::project_id, donations::amount_lamports))
+            .first::<(i32, i64)>(&mut conn)
+            .optional()?;
+        Ok(found.map(|(project_id, amount_lamports)| CommittedDonation {
+            project_id,
+            amount_lamports: amount_lamports as u64,
+        }))
+    }
+
+    fn issue(&self, commitment: &Fr) -> Result<(), DonationCommitmentError> {
+        let mut conn = self.pool.get()?;
+        diesel::insert_into(donation_commitments::table)
+            .values(donation_commitments::commitment.eq(fr_to_hex(commitment)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, DonationCommitmentError> {
+        let mut conn = self.pool.get()?;
+        let found = donation_commitments::table
+            .find(fr_to_hex(commitment))
+            .select(donation_commitments::commitment)
+            .first::<String>(&mut conn)
+            .optional()?;
+        Ok(found.is_some())
+    }
+}
+
+/// Keeps donations and commitments in memory; for tests and local development only.
+#[derive(Default)]
+pub struct MemoryDonationCommitmentStore {
+    donations: Mutex<HashMap<Uuid, (String, CommittedDonation)>>,
+    issued: Mutex<HashSet<Fr>>,
+}
+
+impl MemoryDonationCommitmentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a donation from `wallet`, as the donations ledger would.
+    pub fn add_donation(&self, donation_id: Uuid, wallet: &str, donation: CommittedDonation) {
+        self.donations.lock().unwrap().insert(donation_id, (wallet.to_string(), donation));
+    }
+}
+
+impl DonationCommitmentStore for MemoryDonationCommitmentStore {
+    fn find_donation(&self, donation_id: Uuid, wallet: &str) -> Result<Option<CommittedDonation>, DonationCommitmentError> {
+        let donations = self.donations.lock().unwrap();
+        Ok(donations
+            .get(&donation_id)
+            .filter(|(donor, _)| donor == wallet)
+            .map(|(_, donation)| *donation))
+    }
+
+    fn issue(&self, commitment: &Fr) -> Result<(), DonationCommitmentError> {
+        self.issued.lock().unwrap().insert(*commitment);
+        Ok(())
+    }
+
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, DonationCommitmentError> {
+        Ok(self.issued.lock().unwrap().contains(commitment))
+    }
+}
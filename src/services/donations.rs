@@ -0,0 +1,242 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use chrono::{DateTime, NaiveTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use ark_bn254::Fr;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use serde_json::json;
+use solana_client::{
+    client_error::ClientError,
+    rpc_client::RpcClient,
+    rpc_config::RpcTransactionConfig,
+    rpc_request::RpcRequest,
+};
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::Signature,
+    system_instruction::SystemInstruction,
+    system_program,
+};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+use crate::metrics;
+use crate::zk::field::fr_to_hex;
+use crate::routes::projects::models::{Donation, NewDonation};
+use crate::schema::{donations, project_beneficiaries, projects};
+
+#[derive(Debug, thiserror::Error)]
+pub enum DonationError {
+    #[error("Project not found")]
+    ProjectNotFound,
+    #[error("Transaction not found or not confirmed yet")]
+    NotConfirmed,
+    #[error("Donation transaction failed: {0}")]
+    Failed(String),
+    #[error("Transaction does not transfer SOL to this project")]
+    NotADonation,
+    #[error("Donation already recorded")]
+    AlreadyRecorded,
+    #[error("Transaction was not sent from the signing wallet")]
+    NotTheDonor,
+    #[error("Solana RPC error: {0}")]
+    Rpc(Box<ClientError>),
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+    #[error("Blocking task failed: {0}")]
+    Blocking(#[from] actix_web::error::BlockingError),
+}
+
+/// A system transfer found in a confirmed transaction.
+struct Transfer {
+    from: Pubkey,
+    to: Pubkey,
+    lamports: u64,
+}
+
+/// Who records a donation anonymously: the nullifier hash of their membership proof and
+/// the wallet they signed a challenge with, which must be the one the donation came from.
+pub struct AnonymousDonor {
+    pub nullifier_hash: Fr,
+    pub wallet: Pubkey,
+}
+
+/// Key of the hash donations are deduplicated by, see [`SignatureKey::hash`].
+#[derive(Clone)]
+pub struct SignatureKey(Vec<u8>);
+
+impl SignatureKey {
+    pub fn new(secret: &str) -> Self {
+        Self(secret.as_bytes().to_vec())
+    }
+
+    /// Hex HMAC-SHA256 of the base58 signature. Signatures are public on chain, so a plain
+    /// hash of an anonymous donation's signature would lead straight back to the donor.
+    pub fn hash(&self, signature: &Signature) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.0).expect("HMAC takes keys of any length");
+        mac.update(signature.to_string().as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+/// Records the confirmed transaction `signature`, as returned by [`fetch_transaction`],
+/// in the donations ledger.
+///
+/// Only system transfers to the project's beneficiaries (or its wallet when it has
+/// none) count towards the amount; the donor is the source of those transfers.
+///
+/// With an `anonymous` donor the donation is recorded without the donor's wallet,
+/// signature or slot, confirmed on a day rather than at a time, and deduplicated by the
+/// keyed hash of the signature only. Callers must have checked the membership proof the
+/// nullifier hash comes from, for the transaction signature as signal, and the signature
+/// of the donor's wallet; transactions sent from another wallet are
+/// [`DonationError::NotTheDonor`].
+pub fn record_confirmed_donation(
+    conn: &mut PgConnection,
+    confirmed: &EncodedConfirmedTransactionWithStatusMeta,
+    key: &SignatureKey,
+    project_id: i32,
+    signature: &Signature,
+    anonymous: Option<AnonymousDonor>,
+) -> Result<Donation, DonationError> {
+    let recipients = project_recipients(conn, project_id)?;
+
+    if let Some(err) = confirmed.transaction.meta.as_ref().and_then(|meta| meta.err.clone()) {
+        return Err(DonationError::Failed(err.to_string()));
+    }
+
+    let transaction = confirmed.transaction.transaction.decode()
+        .ok_or(DonationError::NotADonation)?;
+
+    let transfers: Vec<Transfer> = system_transfers(&transaction)
+        .into_iter()
+        .filter(|t| recipients.contains(&t.to))
+        .collect();
+    let donor = transfers.first().ok_or(DonationError::NotADonation)?.from;
+    let amount_lamports: u64 = transfers.iter()
+        .filter(|t| t.from == donor)
+        .map(|t| t.lamports)
+        .sum();
+
+    let confirmed_at = confirmed.block_time
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.naive_utc())
+        .unwrap_or_else(|| Utc::now().naive_utc());
+
+    let new_donation = match anonymous {
+        None => NewDonation {
+            project_id,
+            donor_wallet: Some(donor.to_string()),
+            amount_lamports: amount_lamports as i64,
+            signature: Some(signature.to_string()),
+            slot: Some(confirmed.slot as i64),
+            confirmed_at,
+            signature_hash: key.hash(signature),
+            verified_anonymous: false,
+            donor_nullifier: None,
+        },
+        Some(AnonymousDonor { wallet, .. }) if wallet != donor => {
+            return Err(DonationError::NotTheDonor);
+        }
+        Some(AnonymousDonor { nullifier_hash, .. }) => {
+            // donations recorded publicly before signatures were keyed are only found by signature
+            let recorded = donations::table
+                .filter(donations::signature.eq(signature.to_string()))
+                .select(donations::id)
+                .first::<uuid::Uuid>(conn)
+                .optional()?;
+            if recorded.is_some() {
+                return Err(DonationError::AlreadyRecorded);
+            }
+            NewDonation {
+                project_id,
+                donor_wallet: None,
+                amount_lamports: amount_lamports as i64,
+                signature: None,
+                slot: None,
+                confirmed_at: confirmed_at.date().and_time(NaiveTime::MIN),
+                signature_hash: key.hash(signature),
+                verified_anonymous: true,
+                donor_nullifier: Some(fr_to_hex(&nullifier_hash)),
+            }
+        }
+    };
+
+    diesel::insert_into(donations::table)
+        .values(&new_donation)
+        .get_result::<Donation>(conn)
+        .map_err(|e| match e {
+            diesel::result::Error::DatabaseError(
+                diesel::result::DatabaseErrorKind::UniqueViolation, _,
+            ) => DonationError::AlreadyRecorded,
+            e => DonationError::Db(e),
+        })
+}
+
+/// The confirmed transaction `signature`, or [`DonationError::NotConfirmed`] when the node
+/// answers `null`, as it does for signatures it hasn't seen or that aren't confirmed yet.
+///
+/// `RpcClient::get_transaction_with_config` can't tell a `null` result from a malformed one,
+/// so the request is sent with an optional result instead.
+pub fn fetch_transaction(
+    rpc_client: &RpcClient,
+    signature: &Signature,
+) -> Result<EncodedConfirmedTransactionWithStatusMeta, DonationError> {
+    let config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        max_supported_transaction_version: Some(0),
+    };
+    let started = Instant::now();
+    let confirmed = rpc_client.send::<Option<EncodedConfirmedTransactionWithStatusMeta>>(
+        RpcRequest::GetTransaction,
+        json!([signature.to_string(), config]),
+    );
+    metrics::observe_rpc("getTransaction", started, confirmed.is_ok());
+
+    confirmed
+        .map_err(|e| DonationError::Rpc(Box::new(e)))?
+        .ok_or(DonationError::NotConfirmed)
+}
+
+/// Wallets that receive donations for `project_id`.
+fn project_recipients(conn: &mut PgConnection, project_id: i32) -> Result<HashSet<Pubkey>, DonationError> {
+    let project_wallet = projects::table
+        .find(project_id)
+        .select(projects::wallet)
+        .first::<String>(conn)
+        .optional()?
+        .ok_or(DonationError::ProjectNotFound)?;
+
+    let mut wallets = project_beneficiaries::table
+        .filter(project_beneficiaries::project_id.eq(project_id))
+        .select(project_beneficiaries::wallet)
+        .load::<String>(conn)?;
+    if wallets.is_empty() {
+        wallets.push(project_wallet);
+    }
+
+    Ok(wallets.iter().filter_map(|w| w.parse().ok()).collect())
+}
+
+fn system_transfers(transaction: &solana_sdk::transaction::VersionedTransaction) -> Vec<Transfer> {
+    let keys = transaction.message.static_account_keys();
+    transaction.message.instructions()
+        .iter()
+        .filter(|ix| keys.get(ix.program_id_index as usize) == Some(&system_program::id()))
+        .filter_map(|ix| {
+            let lamports = match bincode::deserialize::<SystemInstruction>(&ix.data).ok()? {
+                SystemInstruction::Transfer { lamports } => lamports,
+                _ => return None,
+            };
+            Some(Transfer {
+                from: *keys.get(*ix.accounts.first()? as usize)?,
+                to: *keys.get(*ix.accounts.get(1)? as usize)?,
+                lamports,
+            })
+        })
+        .collect()
+}
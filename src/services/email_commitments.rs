@@ -0,0 +1,76 @@
+use std::collections::HashSet;
+use std::sync::Mutex;
+use ark_bn254::Fr;
+use diesel::prelude::*;
+use crate::DbPool;
+use crate::schema::email_commitments;
+use crate::zk::field::fr_to_hex;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailCommitmentError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+}
+
+/// Commitments issued to verified emails, see `zk::attributes`. Attribute proofs are only
+/// accepted for commitments found here, since anyone can commit to an address they
+/// don't own.
+pub trait EmailCommitmentStore: Send + Sync {
+    fn issue(&self, commitment: &Fr) -> Result<(), EmailCommitmentError>;
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, EmailCommitmentError>;
+}
+
+pub struct PgEmailCommitmentStore {
+    pool: DbPool,
+}
+
+impl PgEmailCommitmentStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl EmailCommitmentStore for PgEmailCommitmentStore {
+    fn issue(&self, commitment: &Fr) -> Result<(), EmailCommitmentError> {
+        let mut conn = self.pool.get()?;
+        diesel::insert_into(email_commitments::table)
+            .values(email_commitments::commitment.eq(fr_to_hex(commitment)))
+            .execute(&mut conn)?;
+        Ok(())
+    }
+
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, EmailCommitmentError> {
+        let mut conn = self.pool.get()?;
+        let found = email_commitments::table
+            .find(fr_to_hex(commitment))
+            .select(email_commitments::commitment)
+            .first::<String>(&mut conn)
+            .optional()?;
+        Ok(found.is_some())
+    }
+}
+
+/// Keeps commitments in memory; for tests and local development only.
+#[derive(Default)]
+pub struct MemoryEmailCommitmentStore {
+    issued: Mutex<HashSet<Fr>>,
+}
+
+impl MemoryEmailCommitmentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl EmailCommitmentStore for MemoryEmailCommitmentStore {
+    fn issue(&self, commitment: &Fr) -> Result<(), EmailCommitmentError> {
+        self.issued.lock().unwrap().insert(*commitment);
+        Ok(())
+    }
+
+    fn is_issued(&self, commitment: &Fr) -> Result<bool, EmailCommitmentError> {
+        Ok(self.issued.lock().unwrap().contains(commitment))
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use ark_std::rand::Rng;
+use chrono::{DateTime, Utc};
+use crate::services::notifier::NotifyError;
+
+/// How long an emailed code can be used.
+pub const CODE_TTL: Duration = Duration::from_secs(600);
+
+/// How long to wait before another code can be sent to the same address.
+pub const RESEND_AFTER: Duration = Duration::from_secs(60);
+
+/// Wrong guesses allowed before a code is discarded.
+pub const MAX_ATTEMPTS: u8 = 5;
+
+/// Most codes outstanding at once.
+pub const MAX_PENDING: usize = 100_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailVerificationError {
+    #[error("A code was sent to this address recently, try again later")]
+    TooSoon,
+    #[error("Too many verifications in progress, try again later")]
+    Full,
+    #[error("Verification code is wrong, expired or was never sent")]
+    InvalidCode,
+    #[error("Failed to send the verification email: {0}")]
+    Send(#[from] NotifyError),
+}
+
+struct Pending {
+    code: String,
+    sent_at: Instant,
+    attempts: u8,
+}
+
+/// Codes emailed to an address to prove its owner reads it. Codes live in memory, so a
+/// restart invalidates the ones still outstanding.
+pub struct EmailVerifications {
+    ttl: Duration,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+impl EmailVerifications {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Creates a six digit code for `email`, replacing any earlier one, and returns it with
+    /// its expiry. Refused while the previous code for `email` is younger than
+    /// [`RESEND_AFTER`], so the endpoint can't be used to flood an inbox.
+    pub fn start(&self, email: &str) -> Result<(String, DateTime<Utc>), EmailVerificationError> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.retain(|_, entry| entry.sent_at.elapsed() < self.ttl);
+        match pending.get(email) {
+            Some(entry) if entry.sent_at.elapsed() < RESEND_AFTER => return Err(EmailVerificationError::TooSoon),
+            Some(_) => {}
+            None if pending.len() >= MAX_PENDING => return Err(EmailVerificationError::Full),
+            None => {}
+        }
+
+        let code = format!("{:06}", ark_std::rand::thread_rng().gen_range(0..1_000_000));
+        pending.insert(email.to_string(), Pending { code: code.clone(), sent_at: Instant::now(), attempts: 0 });
+
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or(chrono::Duration::MAX);
+        Ok((code, Utc::now() + ttl))
+    }
+
+    /// Forgets the code for `email`, e.g. because it couldn't be delivered.
+    pub fn cancel(&self, email: &str) {
+        self.pending.lock().unwrap().remove(email);
+    }
+
+    /// Checks `code` against the one sent to `email` and consumes it. A code is discarded
+    /// after [`MAX_ATTEMPTS`] wrong guesses.
+    pub fn confirm(&self, email: &str, code: &str) -> Result<(), EmailVerificationError> {
+        let mut pending = self.pending.lock().unwrap();
+        let Some(entry) = pending.get_mut(email) else {
+            return Err(EmailVerificationError::InvalidCode);
+        };
+        if entry.sent_at.elapsed() >= self.ttl {
+            pending.remove(email);
+            return Err(EmailVerificationError::InvalidCode);
+        }
+        if entry.code != code {
+            entry.attempts += 1;
+            if entry.attempts >= MAX_ATTEMPTS {
+                pending.remove(email);
+            }
+            return Err(EmailVerificationError::InvalidCode);
+        }
+        pending.remove(email);
+        Ok(())
+    }
+}
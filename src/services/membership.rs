@@ -0,0 +1,249 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Mutex, RwLock};
+use ark_bn254::Fr;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::PgConnection;
+use crate::DbPool;
+use crate::repos::identities::find_by_subject;
+use crate::schema::{identities, membership_nullifiers};
+use crate::zk::field::{fr_to_hex, parse_fr};
+use crate::zk::membership::MembershipInputs;
+use crate::zk::merkle::{MerklePath, MerkleTree};
+
+/// How many of the latest roots proofs may refer to, so a proof made just before another
+/// identity joined still verifies.
+pub const ROOT_HISTORY: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MembershipError {
+    #[error("No registered identity found for this token")]
+    UnknownIdentity,
+    #[error("This identity already registered a commitment")]
+    AlreadyRegistered,
+    #[error("Commitment is already registered")]
+    DuplicateCommitment,
+    #[error("The group is full")]
+    GroupFull,
+    #[error("Nullifier already used for this scope")]
+    NullifierUsed,
+    #[error("Stored commitment #{0} is not a field element")]
+    CorruptCommitment(i64),
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+    #[error("Blocking task failed: {0}")]
+    Blocking(#[from] actix_web::error::BlockingError),
+}
+
+struct GroupState {
+    tree: MerkleTree,
+    leaf_index: HashMap<Fr, usize>,
+    roots: VecDeque<Fr>,
+}
+
+impl GroupState {
+    fn push(&mut self, commitment: Fr) -> bool {
+        let Some(root) = self.tree.push(commitment) else {
+            return false;
+        };
+        self.leaf_index.insert(commitment, self.tree.len() - 1);
+        if self.roots.len() == ROOT_HISTORY {
+            self.roots.pop_front();
+        }
+        self.roots.push_back(root);
+        true
+    }
+}
+
+/// The Merkle tree over the commitments registered on `identities`, in registration order.
+pub struct MembershipGroup {
+    state: RwLock<GroupState>,
+}
+
+impl MembershipGroup {
+    pub fn new(depth: usize) -> Self {
+        let tree = MerkleTree::new(depth);
+        let roots = VecDeque::from([tree.root()]);
+        Self {
+            state: RwLock::new(GroupState {
+                tree,
+                leaf_index: HashMap::new(),
+                roots,
+            }),
+        }
+    }
+
+    pub fn from_commitments(depth: usize, commitments: impl IntoIterator<Item = Fr>) -> Self {
+        let group = Self::new(depth);
+        {
+            let mut state = group.state.write().unwrap();
+            for commitment in commitments {
+                if !state.push(commitment) {
+                    break;
+                }
+            }
+        }
+        group
+    }
+
+    /// Builds the group from every commitment registered so far.
+    pub fn load(conn: &mut PgConnection, depth: usize) -> Result<Self, MembershipError> {
+        let group = Self::new(depth);
+        group.sync(conn)?;
+        Ok(group)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.state.read().unwrap().tree.depth()
+    }
+
+    pub fn size(&self) -> usize {
+        self.state.read().unwrap().tree.len()
+    }
+
+    pub fn root(&self) -> Fr {
+        self.state.read().unwrap().tree.root()
+    }
+
+    /// Whether `root` is one of the last [`ROOT_HISTORY`] roots of the group.
+    pub fn is_known_root(&self, root: &Fr) -> bool {
+        self.state.read().unwrap().roots.contains(root)
+    }
+
+    /// Leaf index and Merkle path of `commitment`, with the root the path leads to.
+    pub fn path(&self, commitment: &Fr) -> Option<(usize, MerklePath, Fr)> {
+        let state = self.state.read().unwrap();
+        let index = *state.leaf_index.get(commitment)?;
+        let path = state.tree.path(index)?;
+        Some((index, path, state.tree.root()))
+    }
+
+    /// Appends commitments registered since the last sync, e.g. by another instance.
+    pub fn sync(&self, conn: &mut PgConnection) -> Result<(), MembershipError> {
+        let mut state = self.state.write().unwrap();
+        let rows: Vec<(Option<String>, Option<i64>)> = identities::table
+            .filter(identities::commitment_index.ge(state.tree.len() as i64))
+            .order(identities::commitment_index.asc())
+            .select((identities::commitment, identities::commitment_index))
+            .load(conn)?;
+
+        for row in rows {
+            let (Some(commitment), Some(index)) = row else {
+                continue;
+            };
+            let commitment = parse_fr(&commitment).map_err(|_| MembershipError::CorruptCommitment(index))?;
+            if !state.push(commitment) {
+                return Err(MembershipError::GroupFull);
+            }
+        }
+        Ok(())
+    }
+
+    /// Registers `commitment` for the identity owning the wallet or email `subject` and
+    /// adds it to the group. Every identity registers once, so it gets one nullifier
+    /// hash per scope.
+    pub fn register(&self, conn: &mut PgConnection, subject: &str, commitment: &Fr) -> Result<usize, MembershipError> {
+        let capacity = self.state.read().unwrap().tree.capacity() as i64;
+        let index = conn.transaction(|conn| {
+            let identity_id = find_by_subject(conn, subject)?.ok_or(MembershipError::UnknownIdentity)?;
+
+            // indices must be handed out without gaps, in commit order
+            diesel::sql_query("LOCK TABLE identities IN SHARE ROW EXCLUSIVE MODE").execute(conn)?;
+            let existing: Option<String> = identities::table
+                .find(identity_id)
+                .select(identities::commitment)
+                .first(conn)?;
+            if existing.is_some() {
+                return Err(MembershipError::AlreadyRegistered);
+            }
+
+            let last: Option<i64> = identities::table
+                .select(diesel::dsl::max(identities::commitment_index))
+                .first(conn)?;
+            let index = last.map_or(0, |last| last + 1);
+            if index >= capacity {
+                return Err(MembershipError::GroupFull);
+            }
+
+            diesel::update(identities::table.find(identity_id))
+                .set((
+                    identities::commitment.eq(fr_to_hex(commitment)),
+                    identities::commitment_index.eq(index),
+                    identities::updated_at.eq(Utc::now().naive_utc()),
+                ))
+                .execute(conn)
+                .map_err(|e| match e {
+                    diesel::result::Error::DatabaseError(
+                        diesel::result::DatabaseErrorKind::UniqueViolation, _,
+                    ) => MembershipError::DuplicateCommitment,
+                    e => MembershipError::Db(e),
+                })?;
+            Ok(index)
+        })?;
+
+        self.sync(conn)?;
+        Ok(index as usize)
+    }
+}
+
+/// Where the nullifier hashes spent by membership proofs are kept.
+pub trait NullifierStore: Send + Sync {
+    /// Records the nullifier hash of `inputs` for its external nullifier, failing with
+    /// [`MembershipError::NullifierUsed`] if it was recorded before.
+    fn record(&self, inputs: &MembershipInputs) -> Result<(), MembershipError>;
+}
+
+pub struct PgNullifierStore {
+    pool: DbPool,
+}
+
+impl PgNullifierStore {
+    pub fn new(pool: DbPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl NullifierStore for PgNullifierStore {
+    fn record(&self, inputs: &MembershipInputs) -> Result<(), MembershipError> {
+        let mut conn = self.pool.get()?;
+        diesel::insert_into(membership_nullifiers::table)
+            .values((
+                membership_nullifiers::external_nullifier.eq(fr_to_hex(&inputs.external_nullifier)),
+                membership_nullifiers::nullifier_hash.eq(fr_to_hex(&inputs.nullifier_hash)),
+                membership_nullifiers::signal_hash.eq(fr_to_hex(&inputs.signal_hash)),
+            ))
+            .execute(&mut conn)
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation, _,
+                ) => MembershipError::NullifierUsed,
+                e => MembershipError::Db(e),
+            })?;
+        Ok(())
+    }
+}
+
+/// Keeps nullifiers in memory; for tests and local development only, since they are
+/// forgotten on restart.
+#[derive(Default)]
+pub struct MemoryNullifierStore {
+    spent: Mutex<HashSet<(Fr, Fr)>>,
+}
+
+impl MemoryNullifierStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl NullifierStore for MemoryNullifierStore {
+    fn record(&self, inputs: &MembershipInputs) -> Result<(), MembershipError> {
+        let key = (inputs.external_nullifier, inputs.nullifier_hash);
+        match self.spent.lock().unwrap().insert(key) {
+            true => Ok(()),
+            false => Err(MembershipError::NullifierUsed),
+        }
+    }
+}
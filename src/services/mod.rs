@@ -1 +1,12 @@
-pub mod auth; 
\ No newline at end of file
+pub mod auth; 
+pub mod cache;
+pub mod challenges;
+pub mod donation_commitments;
+pub mod donations;
+pub mod email_commitments;
+pub mod email_verification;
+pub mod membership;
+pub mod notifier;
+pub mod pledges;
+pub mod readiness;
+pub mod wallet_signatures;
\ No newline at end of file
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::{rt, web};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use solana_sdk::native_token::sol_to_lamports;
+use uuid::Uuid;
+use crate::DbPool;
+use crate::routes::pledges::models::{NewPledgeReminder, Pledge};
+use crate::routes::projects::models::Donation;
+use crate::schema::{pledge_reminders, pledges, projects};
+use super::notifier::{Notifiers, PledgeNotice};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchedulerError {
+    #[error("Database connection error: {0}")]
+    Pool(#[from] diesel::r2d2::PoolError),
+    #[error("Database error: {0}")]
+    Db(#[from] diesel::result::Error),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FulfilmentError {
+    #[error("Anonymous donations can't fulfil a pledge")]
+    Anonymous,
+    #[error("Donation wasn't made from a wallet of the pledging identity")]
+    NotPledger,
+    #[error("Donation is smaller than the pledged {0} SOL")]
+    TooSmall(f64),
+}
+
+/// Checks that `donation`, made from a wallet of `donor_identity`, fulfils `pledge`. Anyone
+/// can look up a project's donations, so without the identity check a reminder could be
+/// marked fulfilled with somebody else's donation.
+pub fn check_fulfilment(pledge: &Pledge, donation: &Donation, donor_identity: Option<Uuid>) -> Result<(), FulfilmentError> {
+    if donation.donor_wallet.is_none() {
+        return Err(FulfilmentError::Anonymous);
+    }
+    if donor_identity != Some(pledge.identity_id) {
+        return Err(FulfilmentError::NotPledger);
+    }
+    if (donation.amount_lamports as u64) < sol_to_lamports(pledge.amount) {
+        return Err(FulfilmentError::TooSmall(pledge.amount));
+    }
+    Ok(())
+}
+
+/// The first due date after `now` following `due_at` every `interval_days`. Missed periods
+/// are skipped, so a long outage yields a single reminder.
+pub fn next_due_at(due_at: NaiveDateTime, interval_days: i32, now: NaiveDateTime) -> NaiveDateTime {
+    let interval = chrono::Duration::days(interval_days as i64);
+    let mut next_due_at = due_at + interval;
+    while next_due_at <= now {
+        next_due_at += interval;
+    }
+    next_due_at
+}
+
+/// Periodically turns due pledges into donation action links and delivers them.
+#[derive(Clone)]
+pub struct PledgeScheduler {
+    pool: DbPool,
+    notifiers: Arc<Notifiers>,
+    base_url: String,
+}
+
+impl PledgeScheduler {
+    pub fn new(pool: DbPool, notifiers: Arc<Notifiers>, base_url: String) -> Self {
+        Self {
+            pool,
+            notifiers,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Spawns the scheduler loop on the current actix runtime.
+    pub fn start(self, every: Duration) {
+        rt::spawn(async move {
+            let mut ticker = rt::time::interval(every);
+            loop {
+                ticker.tick().await;
+                let scheduler = self.clone();
+                match web::block(move || scheduler.run_due(Utc::now().naive_utc())).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(sent)) => log::info!("Sent {} pledge reminders", sent),
+                    Ok(Err(e)) => log::error!("Pledge scheduler failed: {}", e),
+                    Err(e) => log::error!("Pledge scheduler task failed: {}", e),
+                }
+            }
+        });
+    }
+
+    /// Issues a reminder for every pledge due at `now` and returns how many were generated.
+    ///
+    /// Reminders are created and the pledge's next due date advanced in one transaction
+    /// before delivery, so a failed delivery is recorded on the reminder rather than
+    /// causing duplicate reminders on the next run.
+    pub fn run_due(&self, now: NaiveDateTime) -> Result<usize, SchedulerError> {
+        let mut conn = self.pool.get()?;
+
+        let due = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let due_pledges = pledges::table
+                .filter(pledges::active.eq(true))
+                .filter(pledges::next_due_at.le(now))
+                .for_update()
+                .skip_locked()
+                .load::<Pledge>(conn)?;
+
+            let mut due = Vec::with_capacity(due_pledges.len());
+            for pledge in due_pledges {
+                let project_title = projects::table
+                    .find(pledge.project_id)
+                    .select(projects::title)
+                    .first::<String>(conn)?;
+
+                let reminder = NewPledgeReminder {
+                    id: Uuid::new_v4(),
+                    pledge_id: pledge.id,
+                    due_at: pledge.next_due_at,
+                    action_url: self.action_url(&pledge),
+                };
+                diesel::insert_into(pledge_reminders::table)
+                    .values(&reminder)
+                    .execute(conn)?;
+
+                diesel::update(pledges::table.find(pledge.id))
+                    .set(pledges::next_due_at.eq(next_due_at(pledge.next_due_at, pledge.interval_days, now)))
+                    .execute(conn)?;
+
+                let notice = PledgeNotice {
+                    pledge_id: pledge.id,
+                    reminder_id: reminder.id,
+                    project_id: pledge.project_id,
+                    project_title,
+                    amount: pledge.amount,
+                    due_at: reminder.due_at,
+                    action_url: reminder.action_url,
+                };
+                due.push((pledge, notice));
+            }
+            Ok(due)
+        })?;
+
+        for (pledge, notice) in &due {
+            let result = self.notifiers.notify(&pledge.notify_channel, &pledge.notify_target, notice);
+            let delivery = match result {
+                Ok(()) => (
+                    pledge_reminders::delivered_at.eq(Some(Utc::now().naive_utc())),
+                    pledge_reminders::delivery_error.eq(None::<String>),
+                ),
+                Err(e) => {
+                    log::warn!("Failed to deliver pledge reminder {}: {}", notice.reminder_id, e);
+                    (
+                        pledge_reminders::delivered_at.eq(None),
+                        pledge_reminders::delivery_error.eq(Some(e.to_string())),
+                    )
+                }
+            };
+            diesel::update(pledge_reminders::table.find(notice.reminder_id))
+                .set(delivery)
+                .execute(&mut conn)?;
+        }
+
+        Ok(due.len())
+    }
+
+    fn action_url(&self, pledge: &Pledge) -> String {
+        format!("{}/api/blink-chain?id={}&amount={}", self.base_url, pledge.project_id, pledge.amount)
+    }
+}
@@ -0,0 +1,188 @@
+//! Readiness of the dependencies the server needs to answer requests.
+//!
+//! The database, its schema and the login circuit keys are critical: without them most routes
+//! fail, so a load balancer should stop sending traffic. Solana RPC and the optional circuit
+//! keys only take some routes down, and are reported without failing readiness.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use actix_web::web;
+use diesel::prelude::*;
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use crate::DbPool;
+use crate::config::SolanaCluster;
+use crate::migrations;
+use crate::zk::circuits::CircuitId;
+use crate::zk::ZKVerifier;
+
+/// How long a dependency may take to answer before it counts as down.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadyStatus {
+    Ready,
+    /// Every critical dependency is up, but something else isn't.
+    Degraded,
+    NotReady,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub status: CheckStatus,
+    pub critical: bool,
+    pub latency_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl Check {
+    fn new(critical: bool, started: Instant, result: Result<Option<String>, String>) -> Self {
+        let (status, detail) = match result {
+            Ok(detail) => (CheckStatus::Up, detail),
+            Err(detail) => (CheckStatus::Down, Some(detail)),
+        };
+        Self {
+            status,
+            critical,
+            latency_ms: started.elapsed().as_secs_f64() * 1000.0,
+            detail,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessReport {
+    pub status: ReadyStatus,
+    pub checks: BTreeMap<&'static str, Check>,
+}
+
+impl ReadinessReport {
+    fn new(checks: BTreeMap<&'static str, Check>) -> Self {
+        let down = |critical: bool| {
+            checks
+                .values()
+                .any(|check| check.critical == critical && check.status == CheckStatus::Down)
+        };
+        let status = if down(true) {
+            ReadyStatus::NotReady
+        } else if down(false) {
+            ReadyStatus::Degraded
+        } else {
+            ReadyStatus::Ready
+        };
+        Self { status, checks }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status != ReadyStatus::NotReady
+    }
+}
+
+pub struct ReadinessChecks {
+    pool: DbPool,
+    zk_verifier: Arc<dyn ZKVerifier>,
+    cluster: SolanaCluster,
+}
+
+impl ReadinessChecks {
+    pub fn new(pool: DbPool, zk_verifier: Arc<dyn ZKVerifier>, cluster: SolanaCluster) -> Self {
+        Self { pool, zk_verifier, cluster }
+    }
+
+    pub async fn run(&self) -> ReadinessReport {
+        let ((database, schema), solana_rpc) = tokio::join!(self.check_database(), self.check_solana());
+
+        let mut checks = BTreeMap::new();
+        checks.insert("database", database);
+        checks.insert("migrations", schema);
+        checks.insert("zk_keys", self.check_zk_keys());
+        checks.insert("solana_rpc", solana_rpc);
+        ReadinessReport::new(checks)
+    }
+
+    /// Connectivity, then pending migrations over the same connection.
+    async fn check_database(&self) -> (Check, Check) {
+        let pool = self.pool.clone();
+        let started = Instant::now();
+        let checked = web::block(move || {
+            let started = Instant::now();
+            let connected = pool
+                .get_timeout(CHECK_TIMEOUT)
+                .map_err(|e| e.to_string())
+                .and_then(|mut conn| {
+                    diesel::sql_query("SELECT 1").execute(&mut conn).map_err(|e| e.to_string())?;
+                    Ok(conn)
+                });
+            let mut conn = match connected {
+                Ok(conn) => conn,
+                Err(e) => {
+                    log::warn!("Readiness check: database unavailable: {}", e);
+                    let database = Check::new(true, started, Err("Database unavailable".to_string()));
+                    let schema = Check::new(true, Instant::now(), Err("Database unavailable".to_string()));
+                    return (database, schema);
+                }
+            };
+            let database = Check::new(true, started, Ok(None));
+
+            let started = Instant::now();
+            let pending = match migrations::pending(&mut conn) {
+                Ok(pending) if pending.is_empty() => Ok(None),
+                Ok(pending) => Err(format!("Pending migrations: {}", pending.join(", "))),
+                Err(e) => {
+                    log::warn!("Readiness check: failed to read applied migrations: {}", e);
+                    Err("Failed to read applied migrations".to_string())
+                }
+            };
+            (database, Check::new(true, started, pending))
+        })
+        .await;
+
+        checked.unwrap_or_else(|e| {
+            (
+                Check::new(true, started, Err(e.to_string())),
+                Check::new(true, started, Err(e.to_string())),
+            )
+        })
+    }
+
+    /// The current login circuit must be loaded; missing optional keys are only reported.
+    fn check_zk_keys(&self) -> Check {
+        let started = Instant::now();
+        if let Err(e) = self.zk_verifier.check_circuit(&CircuitId::current()) {
+            return Check::new(true, started, Err(e.to_string()));
+        }
+        let missing: Vec<String> = self
+            .zk_verifier
+            .optional_keys()
+            .into_iter()
+            .filter(|(_, loaded)| !loaded)
+            .map(|(circuit, _)| circuit.to_string())
+            .collect();
+        let detail = (!missing.is_empty()).then(|| format!("Not loaded: {}", missing.join(", ")));
+        Check::new(true, started, Ok(detail))
+    }
+
+    async fn check_solana(&self) -> Check {
+        let url = self.cluster.rpc_url().to_string();
+        let started = Instant::now();
+        let health = web::block(move || {
+            RpcClient::new_with_timeout(url, CHECK_TIMEOUT).get_health().map_err(|e| {
+                log::warn!("Readiness check: Solana RPC unavailable: {}", e);
+                "Solana RPC unavailable".to_string()
+            })
+        })
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+        Check::new(false, started, health.map(|()| None))
+    }
+}
@@ -0,0 +1,53 @@
+use std::str::FromStr;
+use ark_bn254::Fr;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use crate::services::challenges::ChallengeStore;
+use crate::zk::field::{fr_from_hex, fr_to_hex};
+
+#[derive(Debug, thiserror::Error)]
+pub enum WalletSignatureError {
+    #[error("Invalid challenge")]
+    MalformedChallenge,
+    #[error("Challenge expired, already used or issued for another identity")]
+    InvalidChallenge,
+    #[error("Invalid wallet signature")]
+    InvalidSignature,
+}
+
+/// Text a wallet signs to authorize `action` with `challenge`. Naming the action keeps a
+/// signature collected for one purpose from being replayed for another.
+pub fn message(action: &str, challenge: &Fr) -> String {
+    format!("Soulana {}: {}", action, fr_to_hex(challenge))
+}
+
+/// Whether `signature` (base58) is `wallet`'s ed25519 signature over `message`.
+pub fn is_signed_by(wallet: &str, message: &str, signature: &str) -> bool {
+    let (Ok(wallet), Ok(signature)) = (Pubkey::from_str(wallet), Signature::from_str(signature)) else {
+        return false;
+    };
+    signature.verify(wallet.as_ref(), message.as_bytes())
+}
+
+/// Checks that `wallet` signed `action` with a challenge issued for it by `challenges`
+/// (see `/auth/zk/challenge`), and consumes the challenge.
+pub fn authorize(
+    challenges: &ChallengeStore,
+    wallet: &str,
+    action: &str,
+    challenge: &str,
+    signature: &str,
+) -> Result<(), WalletSignatureError> {
+    let challenge = fr_from_hex(challenge).ok_or(WalletSignatureError::MalformedChallenge)?;
+    if !challenges.is_pending(&challenge, wallet) {
+        return Err(WalletSignatureError::InvalidChallenge);
+    }
+    if !is_signed_by(wallet, &message(action, &challenge), signature) {
+        return Err(WalletSignatureError::InvalidSignature);
+    }
+    // consuming only after the signature checks out keeps a forged one from burning it
+    if !challenges.consume(&challenge, wallet) {
+        return Err(WalletSignatureError::InvalidChallenge);
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Private attribute proofs over server-issued commitments.
+//!
+//! Once an email has been verified through a ZK login, the server issues a commitment to
+//! it: the MiMC hash of the hashed local part, the hashed domain and a random salt. The
+//! holder keeps the opening and can later prove that the committed email belongs to a
+//! domain, e.g. `university.edu`, without revealing the rest of the address.
+
+use std::path::Path;
+use ark_bn254::{Bn254, Fr};
+use ark_ff::UniformRand;
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use ark_std::rand::Rng;
+use super::circuits::CircuitId;
+use super::error::ZkError;
+use super::field::hash_to_field;
+use super::keys::KeyFileError;
+use super::mimc::{self, Num};
+use super::CircuitVerifier;
+
+/// Name of the circuit implemented by [`EmailDomainCircuit`].
+pub const EMAIL_DOMAIN_CIRCUIT_NAME: &str = "email-domain";
+/// Version of [`EmailDomainCircuit`] the email domain keys are generated for.
+pub const EMAIL_DOMAIN_CIRCUIT_VERSION: u32 = 1;
+
+/// Names of the email domain circuit's public inputs, in order.
+pub const EMAIL_DOMAIN_INPUT_NAMES: [&str; 3] = ["commitment", "domain_hash", "challenge"];
+
+const EMAIL_LOCAL_DOMAIN: &[u8] = b"soulana:email-local:";
+const EMAIL_DOMAIN_DOMAIN: &[u8] = b"soulana:email-domain:";
+
+/// Splits `email` at its last `@`, lowercasing the domain.
+pub fn split_email(email: &str) -> Option<(&str, String)> {
+    let (local, domain) = email.rsplit_once('@')?;
+    if local.is_empty() || domain.is_empty() {
+        return None;
+    }
+    Some((local, domain.to_ascii_lowercase()))
+}
+
+/// Hashes an email domain to the public input proofs are checked against.
+pub fn domain_to_field(domain: &str) -> Fr {
+    hash_to_field(EMAIL_DOMAIN_DOMAIN, &domain.to_ascii_lowercase())
+}
+
+/// What the holder of an email commitment needs to prove statements about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EmailOpening {
+    pub local_hash: Fr,
+    pub domain_hash: Fr,
+    pub salt: Fr,
+}
+
+impl EmailOpening {
+    /// Opening of a fresh commitment to `email`, `None` if it isn't an email address.
+    pub fn new<R: Rng>(email: &str, rng: &mut R) -> Option<Self> {
+        let (local, domain) = split_email(email)?;
+        Some(Self {
+            local_hash: hash_to_field(EMAIL_LOCAL_DOMAIN, local),
+            domain_hash: domain_to_field(&domain),
+            // salted so the commitment can't be matched against a list of addresses
+            salt: Fr::rand(rng),
+        })
+    }
+
+    pub fn commitment(&self) -> Fr {
+        mimc::hash(&[self.local_hash, self.domain_hash, self.salt])
+    }
+}
+
+/// Proves that `commitment` opens to an email whose domain hashes to `domain_hash`,
+/// bound to a one-time `challenge`.
+#[derive(Clone)]
+pub struct EmailDomainCircuit {
+    pub opening: Option<EmailOpening>,
+    pub commitment: Option<Fr>,
+    pub challenge: Option<Fr>,
+}
+
+impl EmailDomainCircuit {
+    /// The circuit without assignments, as used by the setup.
+    pub fn blank() -> Self {
+        Self {
+            opening: None,
+            commitment: None,
+            challenge: None,
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for EmailDomainCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = Num::input(&cs, self.commitment)?;
+        let domain_hash = Num::input(&cs, self.opening.map(|o| o.domain_hash))?;
+        let challenge = Num::input(&cs, self.challenge)?;
+
+        let local_hash = Num::witness(&cs, self.opening.map(|o| o.local_hash))?;
+        let salt = Num::witness(&cs, self.opening.map(|o| o.salt))?;
+        let expected = mimc::hash_gadget(&cs, &[local_hash, domain_hash, salt])?;
+        expected.enforce_equal(&cs, &commitment)?;
+
+        // binds the proof to the challenge, as in the identity circuit
+        challenge.mul(&cs, &challenge)?;
+        Ok(())
+    }
+}
+
+/// Verifying key of the email domain circuit.
+pub struct EmailDomainVerifier {
+    verifier: CircuitVerifier,
+}
+
+impl EmailDomainVerifier {
+    pub fn circuit_id() -> CircuitId {
+        CircuitId::new(EMAIL_DOMAIN_CIRCUIT_NAME, EMAIL_DOMAIN_CIRCUIT_VERSION)
+    }
+
+    /// Loads the verifying key exported by the email domain ceremony.
+    pub fn load(keys_dir: &Path) -> Result<Self, KeyFileError> {
+        let verifier = CircuitVerifier::load_circuit(keys_dir, &Self::circuit_id(), EMAIL_DOMAIN_INPUT_NAMES.len())?;
+        Ok(Self { verifier })
+    }
+
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            verifier: CircuitVerifier::from_vk(vk),
+        }
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        self.verifier.verifying_key()
+    }
+
+    /// Checks the proof only; whether the server issued `commitment` is up to the caller.
+    pub fn verify(&self, proof_bytes: &[u8], commitment: Fr, domain: &str, challenge: Fr) -> bool {
+        self.check(proof_bytes, commitment, domain, challenge).is_ok()
+    }
+
+    pub fn check(&self, proof_bytes: &[u8], commitment: Fr, domain: &str, challenge: Fr) -> Result<(), ZkError> {
+        self.verifier.check_proof(proof_bytes, &[commitment, domain_to_field(domain), challenge])
+    }
+}
+
+/// Creates email domain proofs from an opening, on the holder's side.
+pub struct EmailDomainProver {
+    proving_key: ProvingKey<Bn254>,
+}
+
+impl EmailDomainProver {
+    pub fn from_keys(proving_key: ProvingKey<Bn254>) -> Self {
+        Self { proving_key }
+    }
+
+    pub fn verifier(&self) -> EmailDomainVerifier {
+        EmailDomainVerifier::from_vk(&self.proving_key.vk)
+    }
+
+    pub fn create_proof(&self, opening: &EmailOpening, challenge: Fr) -> Result<Vec<u8>, SynthesisError> {
+        let circuit = EmailDomainCircuit {
+            opening: Some(*opening),
+            commitment: Some(opening.commitment()),
+            challenge: Some(challenge),
+        };
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, &mut ark_std::rand::thread_rng())?;
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        Ok(proof_bytes)
+    }
+}
@@ -0,0 +1,459 @@
+//! Groth16 trusted setup ceremony for [`ZKCircuit`] and the membership, email domain and
+//! donation threshold circuits.
+//!
+//! `init` derives the keys from the powers of tau of a public Phase 1 ceremony (see
+//! [`super::ptau`]) with `gamma` and `delta` set to one, so it involves no secrets and
+//! anyone can re-run it. Any number of participants then re-randomize `delta` in turn
+//! (the Phase 2 of a Groth16 MPC), each publishing a proof of knowledge of their share.
+//! Proofs can't be forged as long as one Phase 1 participant and one Phase 2 participant
+//! discarded their secrets. A public random beacon can seal the ceremony with a final,
+//! reproducible contribution.
+//!
+//! The coordinator keeps every intermediate file so each step can be checked with
+//! [`verify_contribution`]; [`verify_transcript`] checks the final file against the powers
+//! of tau on its own.
+
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{Field, One, UniformRand, Zero};
+use ark_groth16::{ProvingKey, VerifyingKey};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, OptimizationGoal, SynthesisMode};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::{rngs::StdRng, CryptoRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+use super::attributes::{EmailDomainCircuit, EMAIL_DOMAIN_CIRCUIT_NAME, EMAIL_DOMAIN_CIRCUIT_VERSION, EMAIL_DOMAIN_INPUT_NAMES};
+use super::circuits::{CircuitId, CIRCUIT_NAME};
+use super::donations::{
+    DonationThresholdCircuit, DONATION_THRESHOLD_CIRCUIT_NAME, DONATION_THRESHOLD_CIRCUIT_VERSION,
+    DONATION_THRESHOLD_INPUT_NAMES,
+};
+use super::field::PUBLIC_INPUT_NAMES;
+use super::keys::CIRCUIT_VERSION;
+use super::membership::{MembershipCircuit, MEMBERSHIP_CIRCUIT_NAME, MEMBERSHIP_CIRCUIT_VERSION, MEMBERSHIP_DEPTH, MEMBERSHIP_INPUT_NAMES};
+use super::ptau::{PowersOfTau, PtauError};
+use super::ZKCircuit;
+
+/// Most iterations of [`contribute_beacon`]; `2^28` hashes take about a minute.
+pub const MAX_BEACON_ITERATIONS: u8 = 28;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CeremonyError {
+    #[error("No ceremony is defined for circuit {0}")]
+    UnknownCircuit(CircuitId),
+    #[error("Circuit setup failed: {0}")]
+    Setup(String),
+    #[error(transparent)]
+    Ptau(#[from] PtauError),
+    #[error("Beacon iterations must be at most {MAX_BEACON_ITERATIONS}, got {0}")]
+    BeaconIterations(u8),
+    #[error("Initial keys were not derived from these powers of tau")]
+    NotFromPowersOfTau,
+    #[error("Malformed ceremony file: {0}")]
+    Malformed(String),
+    #[error("Proving key does not match circuit {0}")]
+    CircuitMismatch(String),
+    #[error("Ceremony has no contributions yet")]
+    NoContributions,
+    #[error("Contribution {0} does not extend the previous ceremony state")]
+    NotAnExtension(usize),
+    #[error("Contribution {0} changed parameters other than delta")]
+    ParametersChanged(usize),
+    #[error("Contribution {0} has an invalid proof of knowledge")]
+    InvalidProofOfKnowledge(usize),
+    #[error("Contribution {0} did not update delta consistently")]
+    InconsistentDelta(usize),
+    #[error("Contribution {0} did not rescale the H and L queries by delta")]
+    InconsistentQueries(usize),
+    #[error("Contribution {0} has a wrong transcript hash")]
+    TranscriptMismatch(usize),
+}
+
+/// One participant's re-randomization of delta.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution {
+    pub name: String,
+    /// `delta_g1` after this contribution.
+    pub delta_after: G1Affine,
+    /// Random `s` and `s * d`, proving knowledge of the contributed `d`.
+    pub s: G1Affine,
+    pub s_delta: G1Affine,
+    /// `r * d` with `r` derived from the transcript, binding the proof to it.
+    pub r_delta: G2Affine,
+    /// Hash of the transcript up to and including this contribution.
+    pub transcript_hash: [u8; 32],
+}
+
+/// Ceremony state passed between participants.
+#[derive(Clone, CanonicalSerialize, CanonicalDeserialize)]
+pub struct CeremonyParams {
+    /// Id of the circuit the keys are for, e.g. `identity@v2`.
+    pub circuit: String,
+    pub proving_key: ProvingKey<Bn254>,
+    pub initial_delta_g1: G1Affine,
+    pub initial_hash: [u8; 32],
+    pub contributions: Vec<Contribution>,
+}
+
+impl CeremonyParams {
+    /// Hash every contribution so far commits to.
+    pub fn transcript_hash(&self) -> [u8; 32] {
+        self.contributions
+            .last()
+            .map(|c| c.transcript_hash)
+            .unwrap_or(self.initial_hash)
+    }
+
+    /// The circuit these keys are for, once checked against the proving key's public inputs.
+    pub fn circuit_id(&self) -> Result<CircuitId, CeremonyError> {
+        let mismatch = || CeremonyError::CircuitMismatch(self.circuit.clone());
+        let circuit: CircuitId = self.circuit.parse().map_err(|_| mismatch())?;
+        let public_inputs = match (circuit.name.as_str(), circuit.version) {
+            (CIRCUIT_NAME, CIRCUIT_VERSION) => PUBLIC_INPUT_NAMES.len(),
+            (MEMBERSHIP_CIRCUIT_NAME, MEMBERSHIP_CIRCUIT_VERSION) => MEMBERSHIP_INPUT_NAMES.len(),
+            (EMAIL_DOMAIN_CIRCUIT_NAME, EMAIL_DOMAIN_CIRCUIT_VERSION) => EMAIL_DOMAIN_INPUT_NAMES.len(),
+            (DONATION_THRESHOLD_CIRCUIT_NAME, DONATION_THRESHOLD_CIRCUIT_VERSION) => DONATION_THRESHOLD_INPUT_NAMES.len(),
+            _ => return Err(CeremonyError::UnknownCircuit(circuit)),
+        };
+        if self.proving_key.vk.gamma_abc_g1.len() != public_inputs + 1 {
+            return Err(mismatch());
+        }
+        Ok(circuit)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CeremonyError> {
+        Self::deserialize_compressed(bytes).map_err(|e| CeremonyError::Malformed(e.to_string()))
+    }
+}
+
+/// Derives the initial keys of the current identity circuit, see [`init_for`].
+pub fn init(ptau: &PowersOfTau) -> Result<CeremonyParams, CeremonyError> {
+    init_for(&CircuitId::current(), ptau)
+}
+
+/// Derives the initial keys of `circuit` from `ptau` that contributions build on.
+pub fn init_for(circuit: &CircuitId, ptau: &PowersOfTau) -> Result<CeremonyParams, CeremonyError> {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    cs.set_optimization_goal(OptimizationGoal::Constraints);
+    cs.set_mode(SynthesisMode::Setup);
+    let synthesized = match (circuit.name.as_str(), circuit.version) {
+        (CIRCUIT_NAME, CIRCUIT_VERSION) => ZKCircuit::<Fr> {
+            input: Some(Fr::from(1u64)),
+            challenge: Some(Fr::from(1u64)),
+        }
+        .generate_constraints(cs.clone()),
+        (MEMBERSHIP_CIRCUIT_NAME, MEMBERSHIP_CIRCUIT_VERSION) => {
+            MembershipCircuit::blank(MEMBERSHIP_DEPTH).generate_constraints(cs.clone())
+        }
+        (EMAIL_DOMAIN_CIRCUIT_NAME, EMAIL_DOMAIN_CIRCUIT_VERSION) => EmailDomainCircuit::blank().generate_constraints(cs.clone()),
+        (DONATION_THRESHOLD_CIRCUIT_NAME, DONATION_THRESHOLD_CIRCUIT_VERSION) => {
+            DonationThresholdCircuit::blank().generate_constraints(cs.clone())
+        }
+        _ => return Err(CeremonyError::UnknownCircuit(circuit.clone())),
+    };
+    synthesized.map_err(|e| CeremonyError::Setup(e.to_string()))?;
+    cs.finalize();
+    let proving_key = phase2_keys(&cs, ptau)?;
+
+    let mut encoded = Vec::new();
+    proving_key.serialize_compressed(&mut encoded).unwrap();
+    let circuit = circuit.to_string();
+
+    Ok(CeremonyParams {
+        initial_delta_g1: proving_key.delta_g1,
+        initial_hash: Sha256::new().chain_update(circuit.as_bytes()).chain_update(&encoded).finalize().into(),
+        circuit,
+        proving_key,
+        contributions: Vec::new(),
+    })
+}
+
+/// Groth16 keys for the synthesized `cs` with `gamma` and `delta` one. The QAP polynomials
+/// are evaluated at tau in the exponent, over the Lagrange basis of the domain, and laid out
+/// as ark-groth16's generator does, including the extra constraint per public input.
+fn phase2_keys(cs: &ConstraintSystemRef<Fr>, ptau: &PowersOfTau) -> Result<ProvingKey<Bn254>, CeremonyError> {
+    let matrices = cs.to_matrices().ok_or_else(|| CeremonyError::Setup("constraint system is gone".to_string()))?;
+    let num_constraints = cs.num_constraints();
+    let num_instance = cs.num_instance_variables();
+    let num_variables = num_instance + cs.num_witness_variables();
+    let domain = GeneralEvaluationDomain::<Fr>::new(num_constraints + num_instance)
+        .ok_or_else(|| CeremonyError::Setup("circuit is too large".to_string()))?;
+    let size = domain.size();
+    let lagrange = ptau.lagrange(&domain)?;
+
+    fn scaled<G: CurveGroup<ScalarField = Fr>>(point: G, coeff: &Fr) -> G {
+        if coeff.is_one() { point } else { point * coeff }
+    }
+
+    // u_i, v_i and beta u_i + alpha v_i + w_i at tau, for every variable i
+    let mut a = vec![G1Projective::zero(); num_variables];
+    let mut b_g1 = vec![G1Projective::zero(); num_variables];
+    let mut b_g2 = vec![G2Projective::zero(); num_variables];
+    let mut abc = vec![G1Projective::zero(); num_variables];
+    for i in 0..num_instance {
+        a[i] += lagrange.tau_g1[num_constraints + i];
+        abc[i] += lagrange.beta_tau_g1[num_constraints + i];
+    }
+    for j in 0..num_constraints {
+        for (coeff, index) in &matrices.a[j] {
+            a[*index] += scaled(lagrange.tau_g1[j], coeff);
+            abc[*index] += scaled(lagrange.beta_tau_g1[j], coeff);
+        }
+        for (coeff, index) in &matrices.b[j] {
+            b_g1[*index] += scaled(lagrange.tau_g1[j], coeff);
+            b_g2[*index] += scaled(lagrange.tau_g2[j], coeff);
+            abc[*index] += scaled(lagrange.alpha_tau_g1[j], coeff);
+        }
+        for (coeff, index) in &matrices.c[j] {
+            abc[*index] += scaled(lagrange.tau_g1[j], coeff);
+        }
+    }
+
+    // tau^i (tau^n - 1) for i < n - 1
+    let h_query: Vec<G1Projective> = (0..size - 1)
+        .map(|i| ptau.tau_g1[i + size].into_group() - ptau.tau_g1[i])
+        .collect();
+
+    Ok(ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: ptau.alpha_tau_g1[0],
+            beta_g2: ptau.beta_g2,
+            gamma_g2: G2Affine::generator(),
+            delta_g2: G2Affine::generator(),
+            gamma_abc_g1: G1Projective::normalize_batch(&abc[..num_instance]),
+        },
+        beta_g1: ptau.beta_tau_g1[0],
+        delta_g1: G1Affine::generator(),
+        a_query: G1Projective::normalize_batch(&a),
+        b_g1_query: G1Projective::normalize_batch(&b_g1),
+        b_g2_query: G2Projective::normalize_batch(&b_g2),
+        h_query: G1Projective::normalize_batch(&h_query),
+        l_query: G1Projective::normalize_batch(&abc[num_instance..]),
+    })
+}
+
+/// Multiplies delta by a fresh secret drawn from `rng` and records the contribution.
+/// Returns the new transcript hash for the participant to publish.
+pub fn contribute<R: Rng + CryptoRng>(params: &mut CeremonyParams, name: &str, rng: &mut R) -> [u8; 32] {
+    let delta = Fr::rand(rng);
+    apply_contribution(params, name, delta, rng)
+}
+
+/// Final contribution derived from a public random `beacon` (e.g. a future block hash),
+/// hashed `2^iterations` times so nobody can bias it by choosing the beacon.
+pub fn contribute_beacon(params: &mut CeremonyParams, beacon: &[u8], iterations: u8) -> Result<[u8; 32], CeremonyError> {
+    if iterations > MAX_BEACON_ITERATIONS {
+        return Err(CeremonyError::BeaconIterations(iterations));
+    }
+    let mut seed: [u8; 32] = Sha256::digest(beacon).into();
+    for _ in 0..(1u64 << iterations) {
+        seed = Sha256::digest(seed).into();
+    }
+    let mut rng = StdRng::from_seed(seed);
+    let delta = Fr::rand(&mut rng);
+    Ok(apply_contribution(params, "beacon", delta, &mut rng))
+}
+
+fn apply_contribution<R: Rng>(params: &mut CeremonyParams, name: &str, delta: Fr, rng: &mut R) -> [u8; 32] {
+    let delta_inv = delta.inverse().expect("delta is nonzero");
+    let before_hash = params.transcript_hash();
+    let delta_before = params.proving_key.delta_g1;
+
+    let s = G1Projective::rand(rng).into_affine();
+    let s_delta = (s * delta).into_affine();
+    let r = transcript_g2(&before_hash, &s, &s_delta);
+    let r_delta = (r * delta).into_affine();
+
+    let pk = &mut params.proving_key;
+    pk.delta_g1 = (pk.delta_g1 * delta).into_affine();
+    pk.vk.delta_g2 = (pk.vk.delta_g2 * delta).into_affine();
+    pk.h_query = scale_all(&pk.h_query, delta_inv);
+    pk.l_query = scale_all(&pk.l_query, delta_inv);
+
+    let transcript_hash = hash_contribution(&before_hash, name, &delta_before, pk.delta_g1, &s, &s_delta, &r_delta);
+    params.contributions.push(Contribution {
+        name: name.to_string(),
+        delta_after: pk.delta_g1,
+        s,
+        s_delta,
+        r_delta,
+        transcript_hash,
+    });
+    transcript_hash
+}
+
+/// Checks that `after` is `before` plus exactly one valid contribution.
+pub fn verify_contribution(before: &CeremonyParams, after: &CeremonyParams) -> Result<(), CeremonyError> {
+    let index = before.contributions.len();
+    if after.contributions.len() != index + 1
+        || after.circuit != before.circuit
+        || after.initial_hash != before.initial_hash
+        || after.initial_delta_g1 != before.initial_delta_g1
+        || after.contributions[..index].iter().zip(&before.contributions)
+            .any(|(a, b)| a.transcript_hash != b.transcript_hash)
+    {
+        return Err(CeremonyError::NotAnExtension(index));
+    }
+
+    let (old, new) = (&before.proving_key, &after.proving_key);
+    if !same_fixed_parameters(old, new) {
+        return Err(CeremonyError::ParametersChanged(index));
+    }
+
+    check_step(before.transcript_hash(), old.delta_g1, &after.contributions[index], index)?;
+    if new.delta_g1 != after.contributions[index].delta_after {
+        return Err(CeremonyError::InconsistentDelta(index));
+    }
+    check_delta_pair(new, index)?;
+    check_queries(old, new, index)
+}
+
+/// Checks that the initial keys are the ones [`init_for`] derives from `ptau`, every
+/// contribution's proof of knowledge, and that they chain up to the final delta.
+pub fn verify_transcript(params: &CeremonyParams, ptau: &PowersOfTau) -> Result<(), CeremonyError> {
+    let circuit = params.circuit_id()?;
+    if params.contributions.is_empty() {
+        return Err(CeremonyError::NoContributions);
+    }
+    let initial = init_for(&circuit, ptau)?;
+    if initial.initial_hash != params.initial_hash
+        || initial.initial_delta_g1 != params.initial_delta_g1
+        || !same_fixed_parameters(&initial.proving_key, &params.proving_key)
+    {
+        return Err(CeremonyError::NotFromPowersOfTau);
+    }
+
+    let mut hash = params.initial_hash;
+    let mut delta = params.initial_delta_g1;
+    for (index, contribution) in params.contributions.iter().enumerate() {
+        check_step(hash, delta, contribution, index)?;
+        hash = contribution.transcript_hash;
+        delta = contribution.delta_after;
+    }
+
+    let last = params.contributions.len() - 1;
+    if params.proving_key.delta_g1 != delta {
+        return Err(CeremonyError::InconsistentDelta(last));
+    }
+    check_delta_pair(&params.proving_key, last)?;
+    check_queries(&initial.proving_key, &params.proving_key, last)
+}
+
+/// H and L must be divided by the same secret delta was multiplied by.
+fn check_queries(old: &ProvingKey<Bn254>, new: &ProvingKey<Bn254>, index: usize) -> Result<(), CeremonyError> {
+    let mut rng = ark_std::rand::thread_rng();
+    if old.h_query.len() != new.h_query.len() || old.l_query.len() != new.l_query.len() {
+        return Err(CeremonyError::InconsistentQueries(index));
+    }
+    for (old_query, new_query) in [(&old.h_query, &new.h_query), (&old.l_query, &new.l_query)] {
+        let coeffs: Vec<Fr> = (0..old_query.len()).map(|_| Fr::rand(&mut rng)).collect();
+        let old_sum = G1Projective::msm(old_query, &coeffs).unwrap();
+        let new_sum = G1Projective::msm(new_query, &coeffs).unwrap();
+        if Bn254::pairing(old_sum, old.vk.delta_g2) != Bn254::pairing(new_sum, new.vk.delta_g2) {
+            return Err(CeremonyError::InconsistentQueries(index));
+        }
+    }
+    Ok(())
+}
+
+fn check_step(
+    before_hash: [u8; 32],
+    delta_before: G1Affine,
+    contribution: &Contribution,
+    index: usize,
+) -> Result<(), CeremonyError> {
+    let r = transcript_g2(&before_hash, &contribution.s, &contribution.s_delta);
+
+    // e(s, r*d) == e(s*d, r): the contributor knows d
+    if Bn254::pairing(contribution.s, contribution.r_delta) != Bn254::pairing(contribution.s_delta, r) {
+        return Err(CeremonyError::InvalidProofOfKnowledge(index));
+    }
+
+    // e(delta_before, r*d) == e(delta_after, r): delta was multiplied by the same d
+    if Bn254::pairing(delta_before, contribution.r_delta) != Bn254::pairing(contribution.delta_after, r) {
+        return Err(CeremonyError::InconsistentDelta(index));
+    }
+
+    let expected = hash_contribution(
+        &before_hash, &contribution.name, &delta_before, contribution.delta_after,
+        &contribution.s, &contribution.s_delta, &contribution.r_delta,
+    );
+    if expected != contribution.transcript_hash {
+        return Err(CeremonyError::TranscriptMismatch(index));
+    }
+    Ok(())
+}
+
+/// delta_g1 and delta_g2 must encode the same scalar, compared against the beta pair.
+fn check_delta_pair(pk: &ProvingKey<Bn254>, index: usize) -> Result<(), CeremonyError> {
+    if Bn254::pairing(pk.delta_g1, pk.vk.beta_g2) != Bn254::pairing(pk.beta_g1, pk.vk.delta_g2) {
+        return Err(CeremonyError::InconsistentDelta(index));
+    }
+    Ok(())
+}
+
+fn same_fixed_parameters(a: &ProvingKey<Bn254>, b: &ProvingKey<Bn254>) -> bool {
+    fn encode<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.serialize_compressed(&mut bytes).unwrap();
+        bytes
+    }
+
+    a.vk.alpha_g1 == b.vk.alpha_g1
+        && a.vk.beta_g2 == b.vk.beta_g2
+        && a.vk.gamma_g2 == b.vk.gamma_g2
+        && a.vk.gamma_abc_g1 == b.vk.gamma_abc_g1
+        && a.beta_g1 == b.beta_g1
+        && encode(&a.a_query) == encode(&b.a_query)
+        && encode(&a.b_g1_query) == encode(&b.b_g1_query)
+        && encode(&a.b_g2_query) == encode(&b.b_g2_query)
+}
+
+fn scale_all(points: &[G1Affine], scalar: Fr) -> Vec<G1Affine> {
+    let scaled: Vec<G1Projective> = points.iter().map(|p| *p * scalar).collect();
+    G1Projective::normalize_batch(&scaled)
+}
+
+/// Derives the G2 point `r` a contribution's proof of knowledge is bound to.
+fn transcript_g2(before_hash: &[u8; 32], s: &G1Affine, s_delta: &G1Affine) -> G2Affine {
+    let mut hasher = Sha256::new();
+    hasher.update(b"soulana-zk-ceremony-r");
+    hasher.update(before_hash);
+    let mut bytes = Vec::new();
+    s.serialize_compressed(&mut bytes).unwrap();
+    s_delta.serialize_compressed(&mut bytes).unwrap();
+    hasher.update(&bytes);
+
+    let mut rng = StdRng::from_seed(hasher.finalize().into());
+    G2Projective::rand(&mut rng).into_affine()
+}
+
+fn hash_contribution(
+    before_hash: &[u8; 32],
+    name: &str,
+    delta_before: &G1Affine,
+    delta_after: G1Affine,
+    s: &G1Affine,
+    s_delta: &G1Affine,
+    r_delta: &G2Affine,
+) -> [u8; 32] {
+    let mut bytes = Vec::new();
+    delta_before.serialize_compressed(&mut bytes).unwrap();
+    delta_after.serialize_compressed(&mut bytes).unwrap();
+    s.serialize_compressed(&mut bytes).unwrap();
+    s_delta.serialize_compressed(&mut bytes).unwrap();
+    r_delta.serialize_compressed(&mut bytes).unwrap();
+
+    let mut hasher = Sha256::new();
+    hasher.update(before_hash);
+    hasher.update((name.len() as u64).to_le_bytes());
+    hasher.update(name.as_bytes());
+    hasher.update(&bytes);
+    hasher.finalize().into()
+}
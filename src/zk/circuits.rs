@@ -0,0 +1,345 @@
+//! Registry of the circuit versions proofs are accepted for.
+//!
+//! Keys live in `<keys dir>/<name>/v<version>/`. The version compiled into this build
+//! (see [`keys::CIRCUIT_VERSION`]) needs both keys, since it is the one new proofs are
+//! created with. Earlier versions only need `verifying_key.bin` and are listed in
+//! `<keys dir>/circuits.json` with the date until which their proofs still verify:
+//!
+//! ```json
+//! { "deprecated": [{ "circuit_id": "identity@v1", "verify_until": "2025-03-01T00:00:00Z" }] }
+//! ```
+//!
+//! Circuits are Groth16 unless `proof_systems` selects Halo 2 for them, in which case
+//! they need no key files, see [`super::halo2`]:
+//!
+//! ```json
+//! { "proof_systems": { "identity@v2": "halo2" } }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use ark_bn254::Fr;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use super::envelope::{ProofEnvelope, ProofSystem};
+use super::error::ZkError;
+use super::halo2::{Halo2Prover, Halo2Verifier};
+use super::keys::{self, KeyFileError};
+use super::{CircuitVerifier, ZKProver};
+
+/// Name of the identity circuit implemented by `ZKCircuit`.
+pub const CIRCUIT_NAME: &str = "identity";
+const MANIFEST_FILE: &str = "circuits.json";
+
+/// A circuit name and version, written `<name>@v<version>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct CircuitId {
+    pub name: String,
+    pub version: u32,
+}
+
+impl CircuitId {
+    pub fn new(name: &str, version: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            version,
+        }
+    }
+
+    /// The circuit this build creates proofs with.
+    pub fn current() -> Self {
+        Self::new(CIRCUIT_NAME, keys::CIRCUIT_VERSION)
+    }
+
+    pub fn key_dir(&self, keys_dir: &Path) -> PathBuf {
+        keys_dir.join(&self.name).join(format!("v{}", self.version))
+    }
+}
+
+impl fmt::Display for CircuitId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@v{}", self.name, self.version)
+    }
+}
+
+impl FromStr for CircuitId {
+    type Err = CircuitError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CircuitError::InvalidId(s.to_string());
+        let (name, version) = s.split_once("@v").ok_or_else(invalid)?;
+        let valid_name = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(invalid());
+        }
+        let version = version.parse().map_err(|_| invalid())?;
+        Ok(Self::new(name, version))
+    }
+}
+
+impl TryFrom<String> for CircuitId {
+    type Error = CircuitError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CircuitId> for String {
+    fn from(id: CircuitId) -> Self {
+        id.to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum CircuitError {
+    #[error("Invalid circuit id '{0}', expected <name>@v<version>")]
+    InvalidId(String),
+    #[error("Circuit {0} is not supported")]
+    Unknown(CircuitId),
+    #[error("Circuit {0} stopped being accepted on {1}, create a new proof")]
+    Retired(CircuitId, DateTime<Utc>),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    proof_systems: HashMap<CircuitId, ProofSystem>,
+    #[serde(default)]
+    deprecated: Vec<DeprecatedEntry>,
+}
+
+impl Manifest {
+    fn proof_system(&self, id: &CircuitId) -> ProofSystem {
+        self.proof_systems.get(id).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeprecatedEntry {
+    circuit_id: CircuitId,
+    verify_until: DateTime<Utc>,
+}
+
+/// Prover of the current circuit, for the proof system it is set up with.
+pub enum AnyProver {
+    Groth16(Box<ZKProver>),
+    Halo2(Box<Halo2Prover>),
+}
+
+impl AnyProver {
+    pub fn proof_system(&self) -> ProofSystem {
+        match self {
+            Self::Groth16(_) => ProofSystem::Groth16,
+            Self::Halo2(_) => ProofSystem::Halo2,
+        }
+    }
+
+    pub fn verifier(&self) -> AnyVerifier {
+        match self {
+            Self::Groth16(prover) => AnyVerifier::Groth16(Box::new(prover.verifier().clone())),
+            Self::Halo2(prover) => AnyVerifier::Halo2(prover.verifier().clone()),
+        }
+    }
+
+    /// Creates a proof for `input` bound to `challenge`, wrapped in a [`ProofEnvelope`].
+    pub fn create_proof(&self, input: Fr, challenge: Fr) -> Result<Vec<u8>, ZkError> {
+        let proof = match self {
+            Self::Groth16(prover) => prover.create_proof(input, challenge)?,
+            Self::Halo2(prover) => prover.create_proof(input, challenge)?,
+        };
+        Ok(ProofEnvelope::new(self.proof_system(), proof).to_bytes())
+    }
+}
+
+impl From<ZKProver> for AnyProver {
+    fn from(prover: ZKProver) -> Self {
+        Self::Groth16(Box::new(prover))
+    }
+}
+
+impl From<Halo2Prover> for AnyProver {
+    fn from(prover: Halo2Prover) -> Self {
+        Self::Halo2(Box::new(prover))
+    }
+}
+
+/// Verifier of one circuit version, for the proof system it is set up with.
+#[derive(Clone)]
+pub enum AnyVerifier {
+    Groth16(Box<CircuitVerifier>),
+    Halo2(Halo2Verifier),
+}
+
+impl AnyVerifier {
+    pub fn proof_system(&self) -> ProofSystem {
+        match self {
+            Self::Groth16(_) => ProofSystem::Groth16,
+            Self::Halo2(_) => ProofSystem::Halo2,
+        }
+    }
+
+    /// The Groth16 verifier, e.g. to export its key for snarkjs or Solana.
+    pub fn groth16(&self) -> Option<&CircuitVerifier> {
+        match self {
+            Self::Groth16(verifier) => Some(verifier),
+            Self::Halo2(_) => None,
+        }
+    }
+
+    pub fn verify_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> bool {
+        self.check_proof(proof_bytes, public_inputs).is_ok()
+    }
+
+    /// Checks a proof in a [`ProofEnvelope`], or a bare Groth16 proof.
+    pub fn check_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> Result<(), ZkError> {
+        let proof = ProofEnvelope::open(proof_bytes, self.proof_system())?;
+        match self {
+            Self::Groth16(verifier) => verifier.check_proof(&proof, public_inputs),
+            Self::Halo2(verifier) => verifier.check_proof(&proof, public_inputs),
+        }
+    }
+
+    /// Verifies many proofs, returning one result per proof in order. Only Groth16
+    /// proofs share a pairing check, Halo 2 proofs are checked one by one.
+    pub fn verify_proofs(&self, proofs: &[(&[u8], &[Fr])]) -> Vec<bool> {
+        let Self::Groth16(verifier) = self else {
+            return proofs.iter().map(|(bytes, inputs)| self.verify_proof(bytes, inputs)).collect();
+        };
+        // proofs of another system are left empty, which fails to deserialize
+        let opened: Vec<Vec<u8>> = proofs
+            .iter()
+            .map(|(bytes, _)| ProofEnvelope::open(bytes, ProofSystem::Groth16).unwrap_or_default())
+            .collect();
+        let batch: Vec<(&[u8], &[Fr])> =
+            opened.iter().zip(proofs).map(|(bytes, (_, inputs))| (bytes.as_slice(), *inputs)).collect();
+        verifier.verify_proofs(&batch)
+    }
+}
+
+impl From<CircuitVerifier> for AnyVerifier {
+    fn from(verifier: CircuitVerifier) -> Self {
+        Self::Groth16(Box::new(verifier))
+    }
+}
+
+impl From<Halo2Verifier> for AnyVerifier {
+    fn from(verifier: Halo2Verifier) -> Self {
+        Self::Halo2(verifier)
+    }
+}
+
+struct DeprecatedCircuit {
+    verifier: AnyVerifier,
+    verify_until: DateTime<Utc>,
+}
+
+pub struct CircuitRegistry {
+    current: CircuitId,
+    prover: AnyProver,
+    verifier: AnyVerifier,
+    deprecated: HashMap<CircuitId, DeprecatedCircuit>,
+}
+
+impl CircuitRegistry {
+    /// A registry accepting only proofs for the current circuit.
+    pub fn new(prover: impl Into<AnyProver>) -> Self {
+        let prover = prover.into();
+        Self {
+            current: CircuitId::current(),
+            verifier: prover.verifier(),
+            prover,
+            deprecated: HashMap::new(),
+        }
+    }
+
+    /// Keeps accepting proofs for `id` until `verify_until`.
+    pub fn with_deprecated(
+        mut self,
+        id: CircuitId,
+        verifier: impl Into<AnyVerifier>,
+        verify_until: DateTime<Utc>,
+    ) -> Self {
+        let verifier = verifier.into();
+        self.deprecated.insert(id, DeprecatedCircuit { verifier, verify_until });
+        self
+    }
+
+    pub fn load(keys_dir: &Path) -> Result<Self, KeyFileError> {
+        let manifest_path = keys_dir.join(MANIFEST_FILE);
+        let manifest: Manifest = match fs::read(&manifest_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| KeyFileError::Manifest(manifest_path.clone(), e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(KeyFileError::Io(manifest_path, e)),
+        };
+        let unsupported = |e: CircuitError| KeyFileError::Manifest(manifest_path.clone(), e.to_string());
+
+        let current = CircuitId::current();
+        let dir = current.key_dir(keys_dir);
+        let system = manifest.proof_system(&current);
+        log::info!("Proving {} with {}", current, system);
+        let prover: AnyProver = match system {
+            ProofSystem::Groth16 => {
+                ZKProver::load_from(&dir.join("proving_key.bin"), &dir.join("verifying_key.bin"))?.into()
+            }
+            ProofSystem::Halo2 => Halo2Prover::for_circuit(&current).map_err(unsupported)?.into(),
+        };
+        let mut registry = Self::new(prover);
+
+        let now = Utc::now();
+        for entry in &manifest.deprecated {
+            if entry.circuit_id == current {
+                return Err(KeyFileError::Manifest(
+                    manifest_path.clone(),
+                    format!("{} is the current circuit and can't be deprecated", current),
+                ));
+            }
+            if entry.verify_until <= now {
+                log::warn!("Skipping {}: deprecation window ended {}", entry.circuit_id, entry.verify_until);
+                continue;
+            }
+
+            let verifier: AnyVerifier = match manifest.proof_system(&entry.circuit_id) {
+                ProofSystem::Groth16 => {
+                    let path = entry.circuit_id.key_dir(keys_dir).join("verifying_key.bin");
+                    CircuitVerifier::load(&path, entry.circuit_id.version)?.into()
+                }
+                ProofSystem::Halo2 => Halo2Verifier::for_circuit(&entry.circuit_id).map_err(unsupported)?.into(),
+            };
+            log::info!("Accepting {} proofs until {}", entry.circuit_id, entry.verify_until);
+            registry = registry.with_deprecated(entry.circuit_id.clone(), verifier, entry.verify_until);
+        }
+        Ok(registry)
+    }
+
+    pub fn current(&self) -> &CircuitId {
+        &self.current
+    }
+
+    pub fn prover(&self) -> &AnyProver {
+        &self.prover
+    }
+
+    pub fn verifier(&self, id: &CircuitId) -> Result<&AnyVerifier, CircuitError> {
+        self.verifier_at(id, Utc::now())
+    }
+
+    /// The verifier for `id`, as long as its proofs are still accepted at `now`.
+    pub fn verifier_at(&self, id: &CircuitId, now: DateTime<Utc>) -> Result<&AnyVerifier, CircuitError> {
+        if *id == self.current {
+            return Ok(&self.verifier);
+        }
+        match self.deprecated.get(id) {
+            Some(circuit) if circuit.verify_until > now => Ok(&circuit.verifier),
+            Some(circuit) => Err(CircuitError::Retired(id.clone(), circuit.verify_until)),
+            None => Err(CircuitError::Unknown(id.clone())),
+        }
+    }
+}
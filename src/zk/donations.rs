@@ -0,0 +1,201 @@
+//! Donation threshold proofs over server-issued donation commitments.
+//!
+//! Once a donation is confirmed on chain and recorded, its donor can ask the server for
+//! a commitment to it: the MiMC hash of the project id, the amount in lamports and a
+//! random salt. The holder keeps the opening and can later prove that they gave at least
+//! some amount to the project, without revealing their wallet or the exact amount.
+//!
+//! The range check decomposes `amount - threshold` into [`AMOUNT_BITS`] bits, which only
+//! succeeds if the difference didn't wrap around the field, i.e. the amount is at least
+//! the threshold.
+
+use std::path::Path;
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, Field, PrimeField, UniformRand};
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use ark_std::rand::Rng;
+use super::circuits::CircuitId;
+use super::error::ZkError;
+use super::keys::KeyFileError;
+use super::mimc::{self, Num};
+use super::CircuitVerifier;
+
+/// Name of the circuit implemented by [`DonationThresholdCircuit`].
+pub const DONATION_THRESHOLD_CIRCUIT_NAME: &str = "donation-threshold";
+/// Version of [`DonationThresholdCircuit`] the donation threshold keys are generated for.
+pub const DONATION_THRESHOLD_CIRCUIT_VERSION: u32 = 1;
+
+/// Names of the donation threshold circuit's public inputs, in order.
+pub const DONATION_THRESHOLD_INPUT_NAMES: [&str; 4] = ["commitment", "project_id", "threshold", "challenge"];
+
+/// Width of the range check, enough for any amount in lamports.
+pub const AMOUNT_BITS: usize = 64;
+
+/// Public input a project id is proven as.
+pub fn project_to_field(project_id: i32) -> Fr {
+    Fr::from(project_id)
+}
+
+/// What the holder of a donation commitment needs to prove statements about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DonationOpening {
+    pub project_id: i32,
+    pub amount_lamports: u64,
+    pub salt: Fr,
+}
+
+impl DonationOpening {
+    /// Opening of a fresh commitment to a donation of `amount_lamports` to `project_id`.
+    pub fn new<R: Rng>(project_id: i32, amount_lamports: u64, rng: &mut R) -> Self {
+        Self {
+            project_id,
+            amount_lamports,
+            // salted so the commitment can't be matched against the donations ledger
+            salt: Fr::rand(rng),
+        }
+    }
+
+    pub fn commitment(&self) -> Fr {
+        mimc::hash(&[project_to_field(self.project_id), Fr::from(self.amount_lamports), self.salt])
+    }
+}
+
+/// Proves that `commitment` opens to a donation to `project_id` of at least `threshold`
+/// lamports, bound to a one-time `challenge`.
+#[derive(Clone)]
+pub struct DonationThresholdCircuit {
+    pub opening: Option<DonationOpening>,
+    pub commitment: Option<Fr>,
+    pub threshold: Option<u64>,
+    pub challenge: Option<Fr>,
+}
+
+impl DonationThresholdCircuit {
+    /// The circuit without assignments, as used by the setup.
+    pub fn blank() -> Self {
+        Self {
+            opening: None,
+            commitment: None,
+            threshold: None,
+            challenge: None,
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for DonationThresholdCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        let commitment = Num::input(&cs, self.commitment)?;
+        let project = Num::input(&cs, self.opening.map(|o| project_to_field(o.project_id)))?;
+        let threshold = Num::input(&cs, self.threshold.map(Fr::from))?;
+        let challenge = Num::input(&cs, self.challenge)?;
+
+        let amount = Num::witness(&cs, self.opening.map(|o| Fr::from(o.amount_lamports)))?;
+        let salt = Num::witness(&cs, self.opening.map(|o| o.salt))?;
+        let expected = mimc::hash_gadget(&cs, &[project, amount.clone(), salt])?;
+        expected.enforce_equal(&cs, &commitment)?;
+
+        // amount - threshold must fit in AMOUNT_BITS bits; below the threshold it wraps
+        // around to a field element far larger than that
+        let excess = amount.sub(&threshold);
+        let excess_bits = excess.value.map(|value| value.into_bigint().to_bits_le());
+        let mut recomposed = Num {
+            lc: lc!(),
+            value: Some(Fr::ZERO),
+        };
+        let mut power = Fr::ONE;
+        for i in 0..AMOUNT_BITS {
+            let bit = Num::bit(&cs, excess_bits.as_ref().map(|bits| bits[i]))?;
+            recomposed = recomposed.add(&bit.scale(power));
+            power.double_in_place();
+        }
+        recomposed.enforce_equal(&cs, &excess)?;
+
+        // binds the proof to the challenge, as in the identity circuit
+        challenge.mul(&cs, &challenge)?;
+        Ok(())
+    }
+}
+
+/// Verifying key of the donation threshold circuit.
+pub struct DonationThresholdVerifier {
+    verifier: CircuitVerifier,
+}
+
+impl DonationThresholdVerifier {
+    pub fn circuit_id() -> CircuitId {
+        CircuitId::new(DONATION_THRESHOLD_CIRCUIT_NAME, DONATION_THRESHOLD_CIRCUIT_VERSION)
+    }
+
+    /// Loads the verifying key exported by the donation threshold ceremony.
+    pub fn load(keys_dir: &Path) -> Result<Self, KeyFileError> {
+        let verifier =
+            CircuitVerifier::load_circuit(keys_dir, &Self::circuit_id(), DONATION_THRESHOLD_INPUT_NAMES.len())?;
+        Ok(Self { verifier })
+    }
+
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            verifier: CircuitVerifier::from_vk(vk),
+        }
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        self.verifier.verifying_key()
+    }
+
+    /// Checks the proof only; whether the server issued `commitment` is up to the caller.
+    pub fn verify(&self, proof_bytes: &[u8], commitment: Fr, project_id: i32, threshold: u64, challenge: Fr) -> bool {
+        self.check(proof_bytes, commitment, project_id, threshold, challenge).is_ok()
+    }
+
+    pub fn check(
+        &self,
+        proof_bytes: &[u8],
+        commitment: Fr,
+        project_id: i32,
+        threshold: u64,
+        challenge: Fr,
+    ) -> Result<(), ZkError> {
+        let inputs = [commitment, project_to_field(project_id), Fr::from(threshold), challenge];
+        self.verifier.check_proof(proof_bytes, &inputs)
+    }
+}
+
+/// Creates donation threshold proofs from an opening, on the holder's side.
+pub struct DonationThresholdProver {
+    proving_key: ProvingKey<Bn254>,
+}
+
+impl DonationThresholdProver {
+    pub fn from_keys(proving_key: ProvingKey<Bn254>) -> Self {
+        Self { proving_key }
+    }
+
+    pub fn verifier(&self) -> DonationThresholdVerifier {
+        DonationThresholdVerifier::from_vk(&self.proving_key.vk)
+    }
+
+    /// Fails with `Unsatisfiable` if the donation is below `threshold`.
+    pub fn create_proof(&self, opening: &DonationOpening, threshold: u64, challenge: Fr) -> Result<Vec<u8>, SynthesisError> {
+        if opening.amount_lamports < threshold {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let circuit = DonationThresholdCircuit {
+            opening: Some(*opening),
+            commitment: Some(opening.commitment()),
+            threshold: Some(threshold),
+            challenge: Some(challenge),
+        };
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, &mut ark_std::rand::thread_rng())?;
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        Ok(proof_bytes)
+    }
+}
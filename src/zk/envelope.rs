@@ -0,0 +1,85 @@
+//! Proof bytes tagged with the proof system that produced them.
+//!
+//! An envelope is `SLNZKPRF | system (u8) | proof`. Bytes without the magic are a bare
+//! compressed Groth16 proof, as sent by clients from before Halo 2 circuits existed and
+//! by converted snarkjs proofs, so those keep verifying.
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use super::error::ZkError;
+
+/// Identifies enveloped proofs.
+const MAGIC: &[u8; 8] = b"SLNZKPRF";
+
+/// A proof system a circuit can be set up with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum ProofSystem {
+    /// Groth16 over BN254, with per-circuit keys from a setup ceremony.
+    #[default]
+    Groth16 = 1,
+    /// Halo 2 over the Pasta curves, with keys derived from the circuit alone, see [`super::halo2`].
+    Halo2 = 2,
+}
+
+impl ProofSystem {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::Groth16),
+            2 => Some(Self::Halo2),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ProofSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Groth16 => write!(f, "groth16"),
+            Self::Halo2 => write!(f, "halo2"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofEnvelope {
+    pub system: ProofSystem,
+    pub proof: Vec<u8>,
+}
+
+impl ProofEnvelope {
+    pub fn new(system: ProofSystem, proof: Vec<u8>) -> Self {
+        Self { system, proof }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 1 + self.proof.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.system as u8);
+        bytes.extend_from_slice(&self.proof);
+        bytes
+    }
+
+    /// Reads an envelope, taking bytes without one for a bare Groth16 proof.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ZkError> {
+        let Some(rest) = bytes.strip_prefix(MAGIC) else {
+            return Ok(Self::new(ProofSystem::Groth16, bytes.to_vec()));
+        };
+        let (system, proof) = rest.split_first().ok_or(ZkError::MalformedProof)?;
+        let system = ProofSystem::from_byte(*system).ok_or(ZkError::MalformedProof)?;
+        Ok(Self::new(system, proof.to_vec()))
+    }
+
+    /// The proof, as long as it was made with `system`.
+    pub fn open(bytes: &[u8], system: ProofSystem) -> Result<Vec<u8>, ZkError> {
+        let envelope = Self::from_bytes(bytes)?;
+        if envelope.system != system {
+            return Err(ZkError::WrongProofSystem {
+                expected: system,
+                actual: envelope.system,
+            });
+        }
+        Ok(envelope.proof)
+    }
+}
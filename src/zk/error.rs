@@ -0,0 +1,57 @@
+use ark_relations::r1cs::SynthesisError;
+use super::circuits::{CircuitError, CircuitId};
+use super::envelope::ProofSystem;
+use super::field::PublicInputError;
+use super::snarkjs::SnarkjsError;
+
+/// Why a proof was rejected or couldn't be created.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ZkError {
+    #[error("Proof is not valid base64")]
+    MalformedBase64,
+    #[error("Proof is not a valid proof for its proof system")]
+    MalformedProof,
+    #[error("Circuit is proven with {expected}, got a {actual} proof")]
+    WrongProofSystem { expected: ProofSystem, actual: ProofSystem },
+    #[error(transparent)]
+    Circuit(#[from] CircuitError),
+    #[error(transparent)]
+    PublicInputs(#[from] PublicInputError),
+    #[error("Circuit takes {expected} public inputs, got {actual}")]
+    InputCount { expected: usize, actual: usize },
+    #[error(transparent)]
+    Snarkjs(#[from] SnarkjsError),
+    #[error("ZK proof verification failed")]
+    PairingCheckFailed,
+    #[error("No verifying key is loaded for {0}")]
+    KeyNotLoaded(CircuitId),
+    #[error("Failed to create proof: {0}")]
+    Proving(String),
+}
+
+impl ZkError {
+    /// Stable identifier of the error, for clients to match on instead of the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::MalformedBase64 => "malformed_base64",
+            Self::MalformedProof => "malformed_proof",
+            Self::WrongProofSystem { .. } => "wrong_proof_system",
+            Self::Circuit(CircuitError::InvalidId(_)) => "invalid_circuit_id",
+            Self::Circuit(CircuitError::Unknown(_)) => "unsupported_circuit",
+            Self::Circuit(CircuitError::Retired(..)) => "retired_circuit",
+            Self::PublicInputs(PublicInputError::WrongCount { .. }) | Self::InputCount { .. } => "public_input_count",
+            Self::PublicInputs(PublicInputError::Invalid { .. }) => "malformed_public_input",
+            Self::PublicInputs(PublicInputError::Mismatch { .. }) => "public_input_mismatch",
+            Self::Snarkjs(_) => "malformed_snarkjs",
+            Self::PairingCheckFailed => "invalid_proof",
+            Self::KeyNotLoaded(_) => "key_not_loaded",
+            Self::Proving(_) => "proving_failed",
+        }
+    }
+}
+
+impl From<SynthesisError> for ZkError {
+    fn from(error: SynthesisError) -> Self {
+        Self::Proving(error.to_string())
+    }
+}
@@ -0,0 +1,102 @@
+//! Hex encoding of BN254 scalar field elements, as carried by the API, and the
+//! public inputs of the identity circuit.
+
+use ark_bn254::Fr;
+use ark_ff::{BigInt, BigInteger, PrimeField};
+use sha2::{Digest, Sha256};
+
+/// Domain separator for identity hashes, so they can't collide with other uses of SHA-256.
+const IDENTITY_DOMAIN: &[u8] = b"soulana:identity:";
+
+/// Names of the identity circuit's public inputs, in order.
+pub const PUBLIC_INPUT_NAMES: [&str; 2] = ["identity_hash", "challenge"];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FieldError {
+    #[error("expected 64 lowercase hex digits")]
+    Malformed,
+    #[error("value is not in the BN254 scalar field")]
+    OutOfField,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PublicInputError {
+    #[error("Expected {expected} public inputs ({names}), got {actual}", names = PUBLIC_INPUT_NAMES.join(", "))]
+    WrongCount { expected: usize, actual: usize },
+    #[error("public_inputs[{index}] ({name}) is invalid: {error}")]
+    Invalid { index: usize, name: &'static str, error: FieldError },
+    #[error("public_inputs[{index}] ({name}) is {actual}, expected {expected}")]
+    Mismatch { index: usize, name: &'static str, expected: String, actual: String },
+}
+
+/// Encodes `value` as 64 lowercase hex digits, big-endian.
+pub fn fr_to_hex(value: &Fr) -> String {
+    hex::encode(value.into_bigint().to_bytes_be())
+}
+
+/// Decodes a value written by [`fr_to_hex`]. Anything but its exact output is rejected,
+/// including values outside the field, so every element has exactly one encoding.
+pub fn parse_fr(value: &str) -> Result<Fr, FieldError> {
+    let canonical = value.len() == 64
+        && value.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if !canonical {
+        return Err(FieldError::Malformed);
+    }
+    let bytes: [u8; 32] = hex::decode(value)
+        .map_err(|_| FieldError::Malformed)?
+        .try_into()
+        .map_err(|_| FieldError::Malformed)?;
+
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks_exact(8)) {
+        *limb = u64::from_be_bytes(chunk.try_into().unwrap());
+    }
+    Fr::from_bigint(BigInt::new(limbs)).ok_or(FieldError::OutOfField)
+}
+
+pub fn fr_from_hex(value: &str) -> Option<Fr> {
+    parse_fr(value).ok()
+}
+
+/// Hashes a wallet address, email or statement to the identity circuit's first input.
+pub fn identity_to_field(identity: &str) -> Fr {
+    hash_to_field(IDENTITY_DOMAIN, identity)
+}
+
+/// SHA-256 of `domain` followed by `value`, reduced into the field.
+pub fn hash_to_field(domain: &[u8], value: &str) -> Fr {
+    let digest = Sha256::new()
+        .chain_update(domain)
+        .chain_update(value.as_bytes())
+        .finalize();
+    Fr::from_be_bytes_mod_order(&digest)
+}
+
+/// Public inputs of a proof for `identity` bound to `challenge`.
+pub fn public_inputs(identity: &str, challenge: Fr) -> [Fr; 2] {
+    [identity_to_field(identity), challenge]
+}
+
+/// Checks hex `provided` inputs against the values the server computed itself.
+pub fn check_public_inputs(provided: &[String], expected: &[Fr; 2]) -> Result<(), PublicInputError> {
+    if provided.len() != expected.len() {
+        return Err(PublicInputError::WrongCount {
+            expected: expected.len(),
+            actual: provided.len(),
+        });
+    }
+
+    for (index, (value, expected)) in provided.iter().zip(expected).enumerate() {
+        let name = PUBLIC_INPUT_NAMES[index];
+        let actual = parse_fr(value).map_err(|error| PublicInputError::Invalid { index, name, error })?;
+        if actual != *expected {
+            return Err(PublicInputError::Mismatch {
+                index,
+                name,
+                expected: fr_to_hex(expected),
+                actual: value.clone(),
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Halo 2 proofs for circuits that `circuits.json` sets up with it, see [`super::circuits`].
+//!
+//! Halo 2 is PLONK with inner product argument commitments over the Pasta curves, as
+//! implemented by the zcash `halo2_proofs` crate. Its parameters come from hashing to the
+//! curve, so there is no trusted setup at all, per circuit or universal: the keys are
+//! derived from the circuit when it is loaded and every server derives the same ones.
+//!
+//! Public inputs are the same BN254 scalars as for Groth16. They are smaller than the
+//! Pallas modulus, so they are embedded unchanged.
+
+use std::sync::Arc;
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField as _};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+use halo2_proofs::pasta::group::ff::PrimeField;
+use halo2_proofs::pasta::{EqAffine, Fp};
+use halo2_proofs::plonk::{
+    self, Advice, Circuit, Column, ConstraintSystem, Instance, ProvingKey, Selector, SingleVerifier, VerifyingKey,
+};
+use halo2_proofs::poly::{commitment::Params, Rotation};
+use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255};
+use super::circuits::{CircuitError, CircuitId};
+use super::error::ZkError;
+
+/// Circuits have `2^K` rows, enough for the identity circuit and the blinding rows.
+const K: u32 = 4;
+
+/// Public inputs of the identity circuit: the identity hash and the challenge.
+const PUBLIC_INPUTS: usize = 2;
+
+/// The identity circuit of [`super::ZKCircuit`]: the identity hash and the challenge are
+/// the instance, and the challenge is squared so the proof is bound to it.
+#[derive(Clone, Default)]
+pub struct IdentityCircuit;
+
+#[derive(Clone)]
+pub struct IdentityConfig {
+    instance: Column<Instance>,
+    input: Column<Advice>,
+    challenge: Column<Advice>,
+    square: Column<Advice>,
+    squaring: Selector,
+}
+
+impl Circuit<Fp> for IdentityCircuit {
+    type Config = IdentityConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> IdentityConfig {
+        let config = IdentityConfig {
+            instance: meta.instance_column(),
+            input: meta.advice_column(),
+            challenge: meta.advice_column(),
+            square: meta.advice_column(),
+            squaring: meta.selector(),
+        };
+        meta.enable_equality(config.instance);
+        meta.enable_equality(config.input);
+        meta.enable_equality(config.challenge);
+        meta.create_gate("challenge squared", |meta| {
+            let selector = meta.query_selector(config.squaring);
+            let challenge = meta.query_advice(config.challenge, Rotation::cur());
+            let square = meta.query_advice(config.square, Rotation::cur());
+            vec![selector * (challenge.clone() * challenge - square)]
+        });
+        config
+    }
+
+    fn synthesize(&self, config: IdentityConfig, mut layouter: impl Layouter<Fp>) -> Result<(), plonk::Error> {
+        layouter.assign_region(
+            || "identity",
+            |mut region| {
+                config.squaring.enable(&mut region, 0)?;
+                region.assign_advice_from_instance(|| "input", config.instance, 0, config.input, 0)?;
+                let challenge =
+                    region.assign_advice_from_instance(|| "challenge", config.instance, 1, config.challenge, 0)?;
+                region.assign_advice(
+                    || "square",
+                    config.square,
+                    0,
+                    || challenge.value().map(|c| c.square()).ok_or(plonk::Error::Synthesis),
+                )?;
+                Ok(())
+            },
+        )
+    }
+}
+
+/// A BN254 scalar as a Pallas base field element.
+fn to_pasta(value: &Fr) -> Fp {
+    let mut repr = [0u8; 32];
+    repr.copy_from_slice(&value.into_bigint().to_bytes_le());
+    Option::from(Fp::from_repr(repr)).expect("BN254 scalars are smaller than the Pallas modulus")
+}
+
+/// Only the identity circuit this build creates proofs with has a Halo 2 version.
+fn check_circuit(circuit: &CircuitId) -> Result<(), CircuitError> {
+    if *circuit != CircuitId::current() {
+        return Err(CircuitError::Unknown(circuit.clone()));
+    }
+    Ok(())
+}
+
+/// Verifying key of the Halo 2 identity circuit.
+#[derive(Clone)]
+pub struct Halo2Verifier {
+    params: Arc<Params<EqAffine>>,
+    vk: Arc<VerifyingKey<EqAffine>>,
+}
+
+impl Halo2Verifier {
+    /// Derives the verifying key of `circuit`.
+    pub fn for_circuit(circuit: &CircuitId) -> Result<Self, CircuitError> {
+        check_circuit(circuit)?;
+        let params = Params::new(K);
+        let vk = plonk::keygen_vk(&params, &IdentityCircuit).expect("the identity circuit fits in 2^K rows");
+        Ok(Self {
+            params: Arc::new(params),
+            vk: Arc::new(vk),
+        })
+    }
+
+    pub fn verify_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> bool {
+        self.check_proof(proof_bytes, public_inputs).is_ok()
+    }
+
+    /// Checks a bare Halo 2 proof, as opened from its [`super::envelope::ProofEnvelope`].
+    pub fn check_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> Result<(), ZkError> {
+        if public_inputs.len() != PUBLIC_INPUTS {
+            return Err(ZkError::InputCount {
+                expected: PUBLIC_INPUTS,
+                actual: public_inputs.len(),
+            });
+        }
+        let instance: Vec<Fp> = public_inputs.iter().map(to_pasta).collect();
+        let mut transcript = Blake2bRead::<_, EqAffine, Challenge255<_>>::init(proof_bytes);
+        plonk::verify_proof(
+            &self.params,
+            &self.vk,
+            SingleVerifier::new(&self.params),
+            &[&[&instance]],
+            &mut transcript,
+        )
+        .map_err(|e| match e {
+            plonk::Error::Transcript(_) => ZkError::MalformedProof,
+            e => {
+                log::debug!("Failed to verify Halo 2 proof: {}", e);
+                ZkError::PairingCheckFailed
+            }
+        })
+    }
+}
+
+/// Proving key of the Halo 2 identity circuit, plus the matching verifier.
+pub struct Halo2Prover {
+    proving_key: ProvingKey<EqAffine>,
+    verifier: Halo2Verifier,
+}
+
+impl Halo2Prover {
+    /// Derives the proving key of `circuit`.
+    pub fn for_circuit(circuit: &CircuitId) -> Result<Self, CircuitError> {
+        let verifier = Halo2Verifier::for_circuit(circuit)?;
+        let proving_key = plonk::keygen_pk(&verifier.params, (*verifier.vk).clone(), &IdentityCircuit)
+            .expect("the identity circuit fits in 2^K rows");
+        Ok(Self { proving_key, verifier })
+    }
+
+    pub fn verifier(&self) -> &Halo2Verifier {
+        &self.verifier
+    }
+
+    /// Creates a bare proof for `input` bound to `challenge`.
+    pub fn create_proof(&self, input: Fr, challenge: Fr) -> Result<Vec<u8>, ZkError> {
+        let instance = [to_pasta(&input), to_pasta(&challenge)];
+        let mut transcript = Blake2bWrite::<_, EqAffine, Challenge255<_>>::init(Vec::new());
+        plonk::create_proof(
+            &self.verifier.params,
+            &self.proving_key,
+            &[IdentityCircuit],
+            &[&[&instance]],
+            rand::rngs::OsRng,
+            &mut transcript,
+        )
+        .map_err(|e| ZkError::Proving(e.to_string()))?;
+        Ok(transcript.finalize())
+    }
+}
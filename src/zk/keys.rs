@@ -0,0 +1,137 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use sha2::{Digest, Sha256};
+
+/// Identifies Soulana ZK key files.
+const MAGIC: &[u8; 8] = b"SLNZKKEY";
+/// Current layout of the header below.
+pub const FORMAT_VERSION: u16 = 1;
+/// Version of `ZKCircuit` the keys were generated for.
+pub const CIRCUIT_VERSION: u32 = 2;
+
+// magic | format version (u16) | kind (u8) | circuit version (u32) | payload len (u64) | sha256(payload)
+const HEADER_LEN: usize = 8 + 2 + 1 + 4 + 8 + 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum KeyKind {
+    Proving = 1,
+    Verifying = 2,
+    /// Intermediate proving key plus contribution transcript of a setup ceremony.
+    Ceremony = 3,
+}
+
+impl KeyKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Self::Proving),
+            2 => Some(Self::Verifying),
+            3 => Some(Self::Ceremony),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum KeyFileError {
+    #[error("Key file {0} not found, generate keys with the soulana-zk-setup ceremony tool")]
+    Missing(PathBuf),
+    #[error("Failed to access key file {0}: {1}")]
+    Io(PathBuf, io::Error),
+    #[error("{0} is not a Soulana ZK key file")]
+    BadMagic(PathBuf),
+    #[error("{0} uses unsupported key file format version {1}")]
+    UnsupportedFormat(PathBuf, u16),
+    #[error("{0} holds a {1:?} key, expected {2:?}")]
+    WrongKind(PathBuf, Option<KeyKind>, KeyKind),
+    #[error("{0} was generated for circuit version {1}, expected {2}")]
+    WrongCircuitVersion(PathBuf, u32, u32),
+    #[error("{0} is truncated")]
+    Truncated(PathBuf),
+    #[error("{0} failed its checksum, the file is corrupted")]
+    ChecksumMismatch(PathBuf),
+    #[error("{0} does not contain a valid key: {1}")]
+    Malformed(PathBuf, String),
+    #[error("Invalid circuit manifest {0}: {1}")]
+    Manifest(PathBuf, String),
+}
+
+/// Writes `payload` to `path` behind a versioned, checksummed header.
+pub fn write_key_file(path: &Path, kind: KeyKind, payload: &[u8]) -> Result<(), KeyFileError> {
+    write_key_file_for(path, kind, CIRCUIT_VERSION, payload)
+}
+
+/// Like [`write_key_file`], for keys of another circuit version.
+pub fn write_key_file_for(path: &Path, kind: KeyKind, circuit_version: u32, payload: &[u8]) -> Result<(), KeyFileError> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(kind as u8);
+    bytes.extend_from_slice(&circuit_version.to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&Sha256::digest(payload));
+    bytes.extend_from_slice(payload);
+
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| KeyFileError::Io(dir.to_path_buf(), e))?;
+    }
+    fs::write(path, bytes).map_err(|e| KeyFileError::Io(path.to_path_buf(), e))
+}
+
+/// Reads a key file written by [`write_key_file`], checking its header and checksum.
+pub fn read_key_file(path: &Path, kind: KeyKind) -> Result<Vec<u8>, KeyFileError> {
+    read_key_file_for(path, kind, CIRCUIT_VERSION)
+}
+
+/// Like [`read_key_file`], for keys of an earlier circuit version that is still verified.
+pub fn read_key_file_for(path: &Path, kind: KeyKind, circuit_version: u32) -> Result<Vec<u8>, KeyFileError> {
+    let (file_version, payload) = read_key_file_versioned(path, kind)?;
+    if file_version != circuit_version {
+        return Err(KeyFileError::WrongCircuitVersion(path.to_path_buf(), file_version, circuit_version));
+    }
+    Ok(payload)
+}
+
+/// Reads a key file of any circuit version, returning the version with the payload.
+pub fn read_key_file_versioned(path: &Path, kind: KeyKind) -> Result<(u32, Vec<u8>), KeyFileError> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(KeyFileError::Missing(path.to_path_buf()))
+        }
+        Err(e) => return Err(KeyFileError::Io(path.to_path_buf(), e)),
+    };
+    let path = path.to_path_buf();
+
+    if bytes.len() < HEADER_LEN {
+        return Err(KeyFileError::Truncated(path));
+    }
+    if &bytes[..8] != MAGIC {
+        return Err(KeyFileError::BadMagic(path));
+    }
+
+    let format_version = u16::from_le_bytes([bytes[8], bytes[9]]);
+    if format_version != FORMAT_VERSION {
+        return Err(KeyFileError::UnsupportedFormat(path, format_version));
+    }
+
+    let file_kind = KeyKind::from_byte(bytes[10]);
+    if file_kind != Some(kind) {
+        return Err(KeyFileError::WrongKind(path, file_kind, kind));
+    }
+
+    let file_version = u32::from_le_bytes(bytes[11..15].try_into().unwrap());
+
+    let payload_len = u64::from_le_bytes(bytes[15..23].try_into().unwrap()) as usize;
+    let checksum = &bytes[23..HEADER_LEN];
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() != payload_len {
+        return Err(KeyFileError::Truncated(path));
+    }
+    if Sha256::digest(payload)[..] != *checksum {
+        return Err(KeyFileError::ChecksumMismatch(path));
+    }
+
+    Ok((file_version, payload.to_vec()))
+}
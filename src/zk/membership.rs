@@ -0,0 +1,236 @@
+//! Semaphore-style group membership proofs.
+//!
+//! Every member holds a secret [`MembershipIdentity`] and registers only its commitment,
+//! which becomes a leaf of the group's [`MerkleTree`]. A proof shows that the prover
+//! knows the secrets behind some leaf under a given root without revealing which, and
+//! exposes a nullifier hash derived from the identity and an external nullifier (the
+//! scope, e.g. a poll id). The nullifier hash is the same every time an identity proves
+//! for the same scope, so the verifier can reject double use while proofs for different
+//! scopes stay unlinkable.
+
+use std::path::Path;
+use ark_bn254::{Bn254, Fr};
+use ark_ff::UniformRand;
+use ark_groth16::{Groth16, ProvingKey, VerifyingKey};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalSerialize;
+use ark_snark::SNARK;
+use ark_std::rand::Rng;
+use super::circuits::CircuitId;
+use super::error::ZkError;
+use super::field::hash_to_field;
+use super::keys::KeyFileError;
+use super::merkle::{MerklePath, MerkleTree};
+use super::mimc::{self, Num};
+use super::CircuitVerifier;
+
+/// Name of the circuit implemented by [`MembershipCircuit`].
+pub const MEMBERSHIP_CIRCUIT_NAME: &str = "membership";
+/// Version of [`MembershipCircuit`] the membership keys are generated for.
+pub const MEMBERSHIP_CIRCUIT_VERSION: u32 = 1;
+/// Depth of the group tree, i.e. room for 65536 members.
+pub const MEMBERSHIP_DEPTH: usize = 16;
+
+/// Names of the membership circuit's public inputs, in order.
+pub const MEMBERSHIP_INPUT_NAMES: [&str; 4] = ["root", "nullifier_hash", "external_nullifier", "signal_hash"];
+
+const SCOPE_DOMAIN: &[u8] = b"soulana:scope:";
+const SIGNAL_DOMAIN: &[u8] = b"soulana:signal:";
+
+/// Hashes a scope, e.g. `poll:42`, to the external nullifier it stands for.
+pub fn scope_to_field(scope: &str) -> Fr {
+    hash_to_field(SCOPE_DOMAIN, scope)
+}
+
+/// Hashes the message a proof vouches for, e.g. a vote.
+pub fn signal_to_field(signal: &str) -> Fr {
+    hash_to_field(SIGNAL_DOMAIN, signal)
+}
+
+/// Secrets of a group member. Only the [`commitment`](Self::commitment) ever leaves the
+/// member's device.
+#[derive(Clone)]
+pub struct MembershipIdentity {
+    pub nullifier: Fr,
+    pub trapdoor: Fr,
+}
+
+impl MembershipIdentity {
+    pub fn random<R: Rng>(rng: &mut R) -> Self {
+        Self {
+            nullifier: Fr::rand(rng),
+            trapdoor: Fr::rand(rng),
+        }
+    }
+
+    pub fn commitment(&self) -> Fr {
+        mimc::hash(&[self.nullifier, self.trapdoor])
+    }
+
+    /// Nullifier hash this identity exposes when proving for `external_nullifier`.
+    pub fn nullifier_hash(&self, external_nullifier: Fr) -> Fr {
+        mimc::hash(&[external_nullifier, self.nullifier])
+    }
+}
+
+/// Public inputs of a membership proof.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MembershipInputs {
+    pub root: Fr,
+    pub nullifier_hash: Fr,
+    pub external_nullifier: Fr,
+    pub signal_hash: Fr,
+}
+
+impl MembershipInputs {
+    pub fn to_array(&self) -> [Fr; 4] {
+        [self.root, self.nullifier_hash, self.external_nullifier, self.signal_hash]
+    }
+}
+
+/// Proves knowledge of the secrets behind a leaf of a tree with root `root`, and that
+/// `nullifier_hash` was derived from the same identity and `external_nullifier`.
+#[derive(Clone)]
+pub struct MembershipCircuit {
+    pub depth: usize,
+    pub identity: Option<MembershipIdentity>,
+    pub path: Option<MerklePath>,
+    pub inputs: Option<MembershipInputs>,
+}
+
+impl MembershipCircuit {
+    /// The circuit without assignments, as used by the setup.
+    pub fn blank(depth: usize) -> Self {
+        Self {
+            depth,
+            identity: None,
+            path: None,
+            inputs: None,
+        }
+    }
+}
+
+impl ConstraintSynthesizer<Fr> for MembershipCircuit {
+    fn generate_constraints(self, cs: ConstraintSystemRef<Fr>) -> Result<(), SynthesisError> {
+        if self.path.as_ref().is_some_and(|path| path.siblings.len() != self.depth) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+
+        let inputs = self.inputs;
+        let root = Num::input(&cs, inputs.map(|i| i.root))?;
+        let nullifier_hash = Num::input(&cs, inputs.map(|i| i.nullifier_hash))?;
+        let external_nullifier = Num::input(&cs, inputs.map(|i| i.external_nullifier))?;
+        let signal_hash = Num::input(&cs, inputs.map(|i| i.signal_hash))?;
+
+        let nullifier = Num::witness(&cs, self.identity.as_ref().map(|id| id.nullifier))?;
+        let trapdoor = Num::witness(&cs, self.identity.as_ref().map(|id| id.trapdoor))?;
+
+        // hash the commitment up the tree, swapping each pair when the node is a right child
+        let mut node = mimc::hash_gadget(&cs, &[nullifier.clone(), trapdoor])?;
+        for level in 0..self.depth {
+            let sibling = Num::witness(&cs, self.path.as_ref().map(|p| p.siblings[level]))?;
+            let is_right = Num::bit(&cs, self.path.as_ref().map(|p| p.is_right[level]))?;
+            let swap = is_right.mul(&cs, &sibling.sub(&node))?;
+            let left = node.add(&swap);
+            let right = sibling.sub(&swap);
+            node = mimc::hash_gadget(&cs, &[left, right])?;
+        }
+        node.enforce_equal(&cs, &root)?;
+
+        let expected_nullifier_hash = mimc::hash_gadget(&cs, &[external_nullifier, nullifier])?;
+        expected_nullifier_hash.enforce_equal(&cs, &nullifier_hash)?;
+
+        // the signal takes part in no other constraint, square it so the proof commits to it
+        signal_hash.mul(&cs, &signal_hash)?;
+        Ok(())
+    }
+}
+
+/// Verifying key of the membership circuit.
+pub struct MembershipVerifier {
+    verifier: CircuitVerifier,
+}
+
+impl MembershipVerifier {
+    pub fn circuit_id() -> CircuitId {
+        CircuitId::new(MEMBERSHIP_CIRCUIT_NAME, MEMBERSHIP_CIRCUIT_VERSION)
+    }
+
+    /// Loads the verifying key exported by the membership ceremony.
+    pub fn load(keys_dir: &Path) -> Result<Self, KeyFileError> {
+        let verifier = CircuitVerifier::load_circuit(keys_dir, &Self::circuit_id(), MEMBERSHIP_INPUT_NAMES.len())?;
+        Ok(Self { verifier })
+    }
+
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            verifier: CircuitVerifier::from_vk(vk),
+        }
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        self.verifier.verifying_key()
+    }
+
+    /// Checks the proof only; whether `inputs.root` is a root of the group and the
+    /// nullifier hash is unused is up to the caller.
+    pub fn verify(&self, proof_bytes: &[u8], inputs: &MembershipInputs) -> bool {
+        self.check(proof_bytes, inputs).is_ok()
+    }
+
+    pub fn check(&self, proof_bytes: &[u8], inputs: &MembershipInputs) -> Result<(), ZkError> {
+        self.verifier.check_proof(proof_bytes, &inputs.to_array())
+    }
+}
+
+/// Creates membership proofs. Proving needs the member's secrets, so servers normally
+/// only verify; this is for clients built on this crate, tools and tests.
+pub struct MembershipProver {
+    proving_key: ProvingKey<Bn254>,
+    depth: usize,
+}
+
+impl MembershipProver {
+    /// A prover for trees of `depth`, which must match the depth `proving_key` was set up for.
+    pub fn from_keys(proving_key: ProvingKey<Bn254>, depth: usize) -> Self {
+        Self { proving_key, depth }
+    }
+
+    pub fn verifier(&self) -> MembershipVerifier {
+        MembershipVerifier::from_vk(&self.proving_key.vk)
+    }
+
+    /// Proves that `identity`'s commitment is in `tree`, for `external_nullifier` and
+    /// `signal_hash`. Returns `None` if the commitment isn't a leaf of the tree.
+    pub fn create_proof(
+        &self,
+        identity: &MembershipIdentity,
+        tree: &MerkleTree,
+        external_nullifier: Fr,
+        signal_hash: Fr,
+    ) -> Result<Option<(Vec<u8>, MembershipInputs)>, SynthesisError> {
+        let commitment = identity.commitment();
+        let Some(index) = tree.leaves().iter().position(|leaf| *leaf == commitment) else {
+            return Ok(None);
+        };
+        let path = tree.path(index).expect("index is a leaf");
+        let inputs = MembershipInputs {
+            root: tree.root(),
+            nullifier_hash: identity.nullifier_hash(external_nullifier),
+            external_nullifier,
+            signal_hash,
+        };
+
+        let circuit = MembershipCircuit {
+            depth: self.depth,
+            identity: Some(identity.clone()),
+            path: Some(path),
+            inputs: Some(inputs),
+        };
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, &mut ark_std::rand::thread_rng())?;
+
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).unwrap();
+        Ok(Some((proof_bytes, inputs)))
+    }
+}
@@ -0,0 +1,118 @@
+//! Append-only Merkle tree of identity commitments, hashed with [`mimc`](super::mimc).
+
+use ark_bn254::Fr;
+use ark_ff::Field;
+use super::mimc;
+
+/// Siblings from the leaf up to the root, and whether the node on the way up is the
+/// right child at each level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerklePath {
+    pub siblings: Vec<Fr>,
+    pub is_right: Vec<bool>,
+}
+
+impl MerklePath {
+    /// Root reached by hashing `leaf` up along this path.
+    pub fn root(&self, leaf: Fr) -> Fr {
+        self.siblings
+            .iter()
+            .zip(&self.is_right)
+            .fold(leaf, |node, (sibling, is_right)| match is_right {
+                true => hash_pair(*sibling, node),
+                false => hash_pair(node, *sibling),
+            })
+    }
+}
+
+pub fn hash_pair(left: Fr, right: Fr) -> Fr {
+    mimc::hash(&[left, right])
+}
+
+pub struct MerkleTree {
+    depth: usize,
+    /// Root of an empty subtree at each height, `zeros[0]` being the empty leaf.
+    zeros: Vec<Fr>,
+    /// Nodes computed so far at each height; `layers[0]` are the leaves.
+    layers: Vec<Vec<Fr>>,
+}
+
+impl MerkleTree {
+    pub fn new(depth: usize) -> Self {
+        let mut zeros = vec![Fr::ZERO];
+        for height in 0..depth {
+            zeros.push(hash_pair(zeros[height], zeros[height]));
+        }
+        Self {
+            depth,
+            zeros,
+            layers: vec![Vec::new(); depth + 1],
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn capacity(&self) -> usize {
+        1 << self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    pub fn leaves(&self) -> &[Fr] {
+        &self.layers[0]
+    }
+
+    pub fn root(&self) -> Fr {
+        self.layers[self.depth].first().copied().unwrap_or(self.zeros[self.depth])
+    }
+
+    /// Appends `leaf`, returning the new root, or `None` if the tree is full.
+    pub fn push(&mut self, leaf: Fr) -> Option<Fr> {
+        if self.len() == self.capacity() {
+            return None;
+        }
+
+        let mut index = self.len();
+        self.layers[0].push(leaf);
+        for height in 0..self.depth {
+            let parent = index / 2;
+            let left = self.node(height, parent * 2);
+            let right = self.node(height, parent * 2 + 1);
+            let hash = hash_pair(left, right);
+            match self.layers[height + 1].get_mut(parent) {
+                Some(node) => *node = hash,
+                None => self.layers[height + 1].push(hash),
+            }
+            index = parent;
+        }
+        Some(self.root())
+    }
+
+    pub fn path(&self, index: usize) -> Option<MerklePath> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut siblings = Vec::with_capacity(self.depth);
+        let mut is_right = Vec::with_capacity(self.depth);
+        let mut index = index;
+        for height in 0..self.depth {
+            siblings.push(self.node(height, index ^ 1));
+            is_right.push(index % 2 == 1);
+            index /= 2;
+        }
+        Some(MerklePath { siblings, is_right })
+    }
+
+    fn node(&self, height: usize, index: usize) -> Fr {
+        self.layers[height].get(index).copied().unwrap_or(self.zeros[height])
+    }
+}
@@ -0,0 +1,94 @@
+use std::time::Instant;
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::VerifyingKey;
+use crate::metrics;
+use super::circuits::{CircuitError, CircuitId};
+use super::error::ZkError;
+use super::membership::MembershipInputs;
+use super::{BatchProof, ZKProverBackend, ZKVerifier};
+
+/// Wraps a backend, recording how long every proof took to verify or create and whether it
+/// succeeded, see [`metrics::observe_proof`].
+pub struct Metered<B> {
+    inner: B,
+}
+
+impl<B> Metered<B> {
+    pub fn new(inner: B) -> Self {
+        Self { inner }
+    }
+}
+
+fn timed<T>(operation: &str, kind: &str, f: impl FnOnce() -> Result<T, ZkError>) -> Result<T, ZkError> {
+    let started = Instant::now();
+    let result = f();
+    metrics::observe_proof(operation, kind, started, result.is_ok());
+    result
+}
+
+impl<B: ZKVerifier> ZKVerifier for Metered<B> {
+    fn verify_wallet(&self, circuit: &CircuitId, wallet: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        timed("verify", "wallet", || self.inner.verify_wallet(circuit, wallet, challenge, proof))
+    }
+
+    fn verify_email(&self, circuit: &CircuitId, email: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        timed("verify", "email", || self.inner.verify_email(circuit, email, challenge, proof))
+    }
+
+    fn verify_membership(&self, proof: &str, inputs: &MembershipInputs) -> Result<(), ZkError> {
+        timed("verify", "membership", || self.inner.verify_membership(proof, inputs))
+    }
+
+    fn verify_email_domain(&self, commitment: Fr, domain: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        timed("verify", "email_domain", || {
+            self.inner.verify_email_domain(commitment, domain, challenge, proof)
+        })
+    }
+
+    fn verify_donation_threshold(
+        &self,
+        commitment: Fr,
+        project_id: i32,
+        threshold: u64,
+        challenge: Fr,
+        proof: &str,
+    ) -> Result<(), ZkError> {
+        timed("verify", "donation_threshold", || {
+            self.inner.verify_donation_threshold(commitment, project_id, threshold, challenge, proof)
+        })
+    }
+
+    fn check_circuit(&self, circuit: &CircuitId) -> Result<(), CircuitError> {
+        self.inner.check_circuit(circuit)
+    }
+
+    fn optional_keys(&self) -> Vec<(CircuitId, bool)> {
+        self.inner.optional_keys()
+    }
+
+    fn verifying_key(&self, circuit: &CircuitId) -> Option<VerifyingKey<Bn254>> {
+        self.inner.verifying_key(circuit)
+    }
+
+    /// Timed as a whole, since backends check batches together.
+    fn verify_batch(&self, proofs: &[BatchProof]) -> Vec<bool> {
+        let started = Instant::now();
+        let results = self.inner.verify_batch(proofs);
+        metrics::observe_proof("verify", "batch", started, results.iter().all(|valid| *valid));
+        results
+    }
+}
+
+impl<B: ZKProverBackend> ZKProverBackend for Metered<B> {
+    fn circuit_id(&self) -> CircuitId {
+        self.inner.circuit_id()
+    }
+
+    fn create_wallet_proof(&self, wallet: &str, challenge: Fr) -> Result<String, ZkError> {
+        timed("prove", "wallet", || self.inner.create_wallet_proof(wallet, challenge))
+    }
+
+    fn create_email_proof(&self, email: &str, challenge: Fr) -> Result<String, ZkError> {
+        timed("prove", "email", || self.inner.create_email_proof(email, challenge))
+    }
+}
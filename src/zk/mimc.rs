@@ -0,0 +1,147 @@
+//! MiMC-7 hash over the BN254 scalar field, computed natively and as R1CS constraints.
+//!
+//! [`hash`] chains the MiMC-7 block cipher in Miyaguchi-Preneel mode, the construction
+//! of circomlib's `MultiMiMC7`, with round constants derived from SHA-256. A round costs
+//! four constraints, which keeps the Merkle paths of the membership circuit cheap to prove.
+
+use std::sync::OnceLock;
+use ark_bn254::Fr;
+use ark_ff::{Field, PrimeField};
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSystemRef, LinearCombination, SynthesisError, Variable},
+};
+use sha2::{Digest, Sha256};
+
+/// Rounds of the cipher, enough for x^7 over a 254-bit field.
+pub const ROUNDS: usize = 91;
+
+const CONSTANTS_DOMAIN: &[u8] = b"soulana:mimc7:";
+
+fn round_constants() -> &'static [Fr] {
+    static CONSTANTS: OnceLock<Vec<Fr>> = OnceLock::new();
+    CONSTANTS.get_or_init(|| {
+        // the first round only adds the key, as in the reference implementation
+        let mut constants = vec![Fr::ZERO];
+        constants.extend((1..ROUNDS as u32).map(|i| {
+            let digest = Sha256::new()
+                .chain_update(CONSTANTS_DOMAIN)
+                .chain_update(i.to_be_bytes())
+                .finalize();
+            Fr::from_be_bytes_mod_order(&digest)
+        }));
+        constants
+    })
+}
+
+fn encrypt(key: Fr, mut x: Fr) -> Fr {
+    for constant in round_constants() {
+        let t = x + key + constant;
+        let t2 = t.square();
+        let t4 = t2.square();
+        x = t4 * t2 * t;
+    }
+    x + key
+}
+
+/// Hashes `inputs` into one field element.
+pub fn hash(inputs: &[Fr]) -> Fr {
+    inputs
+        .iter()
+        .fold(Fr::ZERO, |state, input| state + input + encrypt(state, *input))
+}
+
+/// A linear combination of circuit variables, plus its value when a witness is being
+/// generated.
+#[derive(Clone)]
+pub struct Num {
+    pub lc: LinearCombination<Fr>,
+    pub value: Option<Fr>,
+}
+
+impl Num {
+    pub fn input(cs: &ConstraintSystemRef<Fr>, value: Option<Fr>) -> Result<Self, SynthesisError> {
+        let var = cs.new_input_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+        Ok(Self::from_var(var, value))
+    }
+
+    pub fn witness(cs: &ConstraintSystemRef<Fr>, value: Option<Fr>) -> Result<Self, SynthesisError> {
+        let var = cs.new_witness_variable(|| value.ok_or(SynthesisError::AssignmentMissing))?;
+        Ok(Self::from_var(var, value))
+    }
+
+    /// A witness constrained to be 0 or 1.
+    pub fn bit(cs: &ConstraintSystemRef<Fr>, value: Option<bool>) -> Result<Self, SynthesisError> {
+        let bit = Self::witness(cs, value.map(Fr::from))?;
+        cs.enforce_constraint(bit.lc.clone(), lc!() + Variable::One - &bit.lc, lc!())?;
+        Ok(bit)
+    }
+
+    fn from_var(var: Variable, value: Option<Fr>) -> Self {
+        Self { lc: lc!() + var, value }
+    }
+
+    pub fn add(&self, other: &Num) -> Num {
+        Num {
+            lc: &self.lc + &other.lc,
+            value: self.value.zip(other.value).map(|(a, b)| a + b),
+        }
+    }
+
+    pub fn sub(&self, other: &Num) -> Num {
+        Num {
+            lc: &self.lc - &other.lc,
+            value: self.value.zip(other.value).map(|(a, b)| a - b),
+        }
+    }
+
+    pub fn add_constant(&self, constant: Fr) -> Num {
+        Num {
+            lc: self.lc.clone() + (constant, Variable::One),
+            value: self.value.map(|v| v + constant),
+        }
+    }
+
+    pub fn scale(&self, factor: Fr) -> Num {
+        Num {
+            lc: self.lc.clone() * factor,
+            value: self.value.map(|v| v * factor),
+        }
+    }
+
+    /// A new witness constrained to `self * other`.
+    pub fn mul(&self, cs: &ConstraintSystemRef<Fr>, other: &Num) -> Result<Num, SynthesisError> {
+        let product = Self::witness(cs, self.value.zip(other.value).map(|(a, b)| a * b))?;
+        cs.enforce_constraint(self.lc.clone(), other.lc.clone(), product.lc.clone())?;
+        Ok(product)
+    }
+
+    pub fn enforce_equal(&self, cs: &ConstraintSystemRef<Fr>, other: &Num) -> Result<(), SynthesisError> {
+        cs.enforce_constraint(self.lc.clone(), lc!() + Variable::One, other.lc.clone())
+    }
+}
+
+fn encrypt_gadget(cs: &ConstraintSystemRef<Fr>, key: &Num, x: &Num) -> Result<Num, SynthesisError> {
+    let mut x = x.clone();
+    for constant in round_constants() {
+        let t = x.add(key).add_constant(*constant);
+        let t2 = t.mul(cs, &t)?;
+        let t4 = t2.mul(cs, &t2)?;
+        let t6 = t4.mul(cs, &t2)?;
+        x = t6.mul(cs, &t)?;
+    }
+    Ok(x.add(key))
+}
+
+/// Constrains the result of [`hash`] over `inputs`.
+pub fn hash_gadget(cs: &ConstraintSystemRef<Fr>, inputs: &[Num]) -> Result<Num, SynthesisError> {
+    let mut state = Num {
+        lc: lc!(),
+        value: Some(Fr::ZERO),
+    };
+    for input in inputs {
+        let encrypted = encrypt_gadget(cs, &state, input)?;
+        state = state.add(input).add(&encrypted);
+    }
+    Ok(state)
+}
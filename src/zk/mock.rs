@@ -1,6 +1,11 @@
 use std::collections::HashMap;
 use std::sync::Mutex;
-use super::ZKVerifier;
+use ark_bn254::Fr;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use super::circuits::CircuitId;
+use super::error::ZkError;
+use super::membership::MembershipInputs;
+use super::{ZKProverBackend, ZKVerifier};
 
 #[derive(Debug, Default)]
 pub struct MockZKVerifier {
@@ -11,16 +16,64 @@ impl MockZKVerifier {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Overrides the verification result for `proof`; unknown proofs verify.
+    pub fn set_proof_valid(&self, proof: &str, valid: bool) {
+        self.valid_proofs.lock().unwrap().insert(proof.to_string(), valid);
+    }
+
+    fn check(&self, proof: &str) -> Result<(), ZkError> {
+        match self.valid_proofs.lock().unwrap().get(proof).copied().unwrap_or(true) {
+            true => Ok(()),
+            false => Err(ZkError::PairingCheckFailed),
+        }
+    }
+
+    fn mock_proof(statement: &str) -> String {
+        BASE64.encode(format!("mock-proof:{}", statement))
+    }
 }
 
 impl ZKVerifier for MockZKVerifier {
-    fn verify_wallet(&self, _wallet: &str, _proof: &str) -> bool {
-        // return true always for mock
-        true
+    fn verify_wallet(&self, _circuit: &CircuitId, _wallet: &str, _challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        // valid unless marked invalid
+        self.check(proof)
+    }
+
+    fn verify_email(&self, _circuit: &CircuitId, _email: &str, _challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        // valid unless marked invalid
+        self.check(proof)
+    }
+
+    fn verify_membership(&self, proof: &str, _inputs: &MembershipInputs) -> Result<(), ZkError> {
+        // valid unless marked invalid
+        self.check(proof)
+    }
+
+    fn verify_email_domain(&self, _commitment: Fr, _domain: &str, _challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        // valid unless marked invalid
+        self.check(proof)
+    }
+
+    fn verify_donation_threshold(
+        &self,
+        _commitment: Fr,
+        _project_id: i32,
+        _threshold: u64,
+        _challenge: Fr,
+        proof: &str,
+    ) -> Result<(), ZkError> {
+        // valid unless marked invalid
+        self.check(proof)
+    }
+}
+
+impl ZKProverBackend for MockZKVerifier {
+    fn create_wallet_proof(&self, wallet: &str, _challenge: Fr) -> Result<String, ZkError> {
+        Ok(Self::mock_proof(wallet))
     }
 
-    fn verify_email(&self, _email: &str, _proof: &str) -> bool {
-        // return true always for mock
-        true
+    fn create_email_proof(&self, email: &str, _challenge: Fr) -> Result<String, ZkError> {
+        Ok(Self::mock_proof(email))
     }
-} 
\ No newline at end of file
+}
\ No newline at end of file
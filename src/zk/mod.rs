@@ -1,39 +1,66 @@
+pub mod attributes;
+pub mod ceremony;
+pub mod circuits;
+pub mod donations;
+pub mod envelope;
+pub mod error;
+pub mod field;
+pub mod halo2;
+pub mod keys;
+pub mod membership;
+pub mod merkle;
+pub mod metered;
+pub mod mimc;
+pub mod ptau;
 pub mod real;
 pub mod mock;
+pub mod snarkjs;
+pub mod solana;
 
 use ark_relations::{
     lc,
-    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError},
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable},
 };
-use ark_ff::PrimeField;
-use ark_bn254::{Bn254, Fr};
+use ark_ec::{pairing::Pairing, CurveGroup};
+use ark_ff::{Field, PrimeField, Zero};
+use ark_bn254::{Bn254, Fr, G1Projective};
 use ark_groth16::{
     Groth16,
+    PreparedVerifyingKey,
     Proof,
     ProvingKey,
     VerifyingKey,
 };
 use ark_snark::SNARK;
-use ark_std::{One, Zero, UniformRand};
 use ark_serialize::{CanonicalSerialize, CanonicalDeserialize};
-use hex;
-use std::fs;
+use ark_std::rand::Rng;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::sync::Arc;
+use circuits::{CircuitError, CircuitId};
+use error::ZkError;
+use membership::MembershipInputs;
+use keys::{read_key_file, read_key_file_for, KeyFileError, KeyKind};
+use snarkjs::{SnarkjsError, SnarkjsProof, SnarkjsVerifyingKey};
+use solana::SolanaVerifyingKey;
 
-const PROVING_KEY_PATH: &str = "keys/proving_key.bin";
-const VERIFYING_KEY_PATH: &str = "keys/verifying_key.bin";
+/// Directory holding one `<name>/v<version>/` key directory per circuit version.
+pub const KEYS_DIR: &str = "keys";
 
+/// Identity circuit. Public inputs are the identity hash and a one-time challenge, so a
+/// proof is only good for the login it was created for.
 #[derive(Clone)]
 pub struct ZKCircuit<F: PrimeField> {
     pub input: Option<F>,
+    pub challenge: Option<F>,
 }
 
 impl<F: PrimeField> ConstraintSynthesizer<F> for ZKCircuit<F> {
     fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
-        println!("Generating constraints for ZK circuit");
-        
         // Input variable - this is the public input
         let input_var = cs.new_input_variable(|| self.input.ok_or(SynthesisError::AssignmentMissing))?;
+        let challenge_var = cs.new_input_variable(|| self.challenge.ok_or(SynthesisError::AssignmentMissing))?;
         
         // Witness variable - this is the private input
         let witness = cs.new_witness_variable(|| {
@@ -41,149 +68,408 @@ impl<F: PrimeField> ConstraintSynthesizer<F> for ZKCircuit<F> {
             Ok(input_value)
         })?;
 
-        // Constraint: input_var = witness
-        cs.enforce_constraint(lc!() + input_var, lc!() + witness, lc!() + witness)?;
+        // Constraint: witness = input_var
+        cs.enforce_constraint(lc!() + witness, lc!() + Variable::One, lc!() + input_var)?;
+
+        // Constraint: challenge_squared = challenge * challenge, binds the proof to the challenge
+        let challenge_squared = cs.new_witness_variable(|| {
+            let challenge = self.challenge.ok_or(SynthesisError::AssignmentMissing)?;
+            Ok(challenge.square())
+        })?;
+        cs.enforce_constraint(lc!() + challenge_var, lc!() + challenge_var, lc!() + challenge_squared)?;
 
-        println!("Constraints generated successfully");
         Ok(())
     }
 }
 
+/// Verifying key of one circuit version, prepared once when loaded.
+#[derive(Clone)]
+pub struct CircuitVerifier {
+    prepared_vk: PreparedVerifyingKey<Bn254>,
+}
+
+/// Proving key for the compiled `ZKCircuit`, plus the matching verifier.
 pub struct ZKProver {
-    proving_key: Vec<u8>,
-    verifying_key: Vec<u8>,
+    proving_key: ProvingKey<Bn254>,
+    verifier: CircuitVerifier,
 }
 
 impl ZKProver {
-    pub fn new() -> Self {
-        println!("Creating new ZKProver");
-        
-        // create keys directory if it doesn't exist
-        if !Path::new("keys").exists() {
-            fs::create_dir("keys").expect("Failed to create keys directory");
-        }
+    /// Loads the keys produced by the `soulana-zk-setup` ceremony. Missing or corrupted
+    /// key files are an error: keys are never generated on the fly, so every
+    /// environment verifies against the same ceremony output.
+    pub fn load_from(proving_key_path: &Path, verifying_key_path: &Path) -> Result<Self, KeyFileError> {
+        log::info!("Loading ZK keys from {} and {}", proving_key_path.display(), verifying_key_path.display());
+        let proving_key_bytes = read_key_file(proving_key_path, KeyKind::Proving)?;
+        let proving_key = ProvingKey::<Bn254>::deserialize_compressed(&proving_key_bytes[..])
+            .map_err(|e| KeyFileError::Malformed(proving_key_path.to_path_buf(), e.to_string()))?;
+        let verifier = CircuitVerifier::load(verifying_key_path, keys::CIRCUIT_VERSION)?;
 
-        // if keys exist, load them
-        if Path::new(PROVING_KEY_PATH).exists() && Path::new(VERIFYING_KEY_PATH).exists() {
-            println!("Loading existing keys");
-            let proving_key = fs::read(PROVING_KEY_PATH).expect("Failed to read proving key");
-            let verifying_key = fs::read(VERIFYING_KEY_PATH).expect("Failed to read verifying key");
-            return Self {
-                proving_key,
-                verifying_key,
-            };
+        // the verifying key must come from the same ceremony as the proving key
+        if *verifier.verifying_key() != proving_key.vk {
+            return Err(KeyFileError::Malformed(
+                verifying_key_path.to_path_buf(),
+                "does not match the proving key".to_string(),
+            ));
         }
 
-        // if keys don't exist, generate new ones
-        println!("Generating new keys");
-        let rng = &mut ark_std::rand::thread_rng();
-        
-        // use a fixed input value
-        let input = Fr::from(1u64);
-        let circuit = ZKCircuit::<Fr> { input: Some(input) };
-        
-        println!("Generating circuit setup parameters");
-        let (pk, vk) = Groth16::<Bn254>::circuit_specific_setup(circuit, rng).unwrap();
-        
-        println!("Serializing proving key");
-        let mut proving_key = Vec::new();
-        pk.serialize_compressed(&mut proving_key).unwrap();
-
-        println!("Serializing verifying key");
-        let mut verifying_key = Vec::new();
-        vk.serialize_compressed(&mut verifying_key).unwrap();
-
-        // save keys to files
-        fs::write(PROVING_KEY_PATH, &proving_key).expect("Failed to save proving key");
-        fs::write(VERIFYING_KEY_PATH, &verifying_key).expect("Failed to save verifying key");
+        Ok(Self { proving_key, verifier })
+    }
 
-        println!("ZKProver created successfully");
+    /// Builds a prover straight from a proving key, e.g. one produced by a ceremony.
+    pub fn from_keys(proving_key: ProvingKey<Bn254>) -> Self {
+        let verifier = CircuitVerifier::from_vk(&proving_key.vk);
         Self {
             proving_key,
-            verifying_key,
+            verifier,
         }
     }
 
-    pub fn create_proof(&self, input: Fr) -> Result<Vec<u8>, SynthesisError> {
-        println!("Creating proof for input");
+    pub fn verifier(&self) -> &CircuitVerifier {
+        &self.verifier
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        self.verifier.verifying_key()
+    }
+
+    /// The verifying key in the layout groth16-solana expects, see [`solana`].
+    pub fn solana_verifying_key(&self) -> SolanaVerifyingKey {
+        self.verifier.solana_verifying_key()
+    }
+
+    /// Creates a proof for `input` bound to `challenge`. Proving is CPU-bound, so callers
+    /// on an actix worker should run it through `web::block`.
+    pub fn create_proof(&self, input: Fr, challenge: Fr) -> Result<Vec<u8>, SynthesisError> {
         let rng = &mut ark_std::rand::thread_rng();
-        
-        println!("Deserializing proving key");
-        let pk = match ProvingKey::<Bn254>::deserialize_compressed(&self.proving_key[..]) {
-            Ok(key) => key,
-            Err(e) => {
-                println!("Failed to deserialize proving key: {:?}", e);
-                return Err(SynthesisError::Unsatisfiable);
-            }
-        };
 
-        let circuit = ZKCircuit { input: Some(input) };
-        
-        println!("Creating proof");
-        let proof = match Groth16::<Bn254>::prove(&pk, circuit, rng) {
-            Ok(p) => p,
-            Err(e) => {
-                println!("Failed to create proof: {:?}", e);
-                return Err(e);
-            }
+        let circuit = ZKCircuit {
+            input: Some(input),
+            challenge: Some(challenge),
         };
-        
-        println!("Serializing proof");
+        let proof = Groth16::<Bn254>::prove(&self.proving_key, circuit, rng)?;
+
         let mut proof_bytes = Vec::new();
         proof.serialize_compressed(&mut proof_bytes).unwrap();
-        
-        println!("Proof created successfully");
+
         Ok(proof_bytes)
     }
 
-    pub fn verify_proof(&self, proof_bytes: &[u8], public_input: Fr) -> bool {
-        println!("Starting proof verification");
-        println!("Proof bytes length: {}", proof_bytes.len());
-        println!("Public input (raw): {:?}", public_input);
-        
-        let mut bytes = Vec::new();
-        public_input.serialize_compressed(&mut bytes).unwrap();
-        println!("Public input (hex): 0x{}", hex::encode(&bytes));
-        
-        println!("Deserializing verifying key");
-        let vk = match VerifyingKey::<Bn254>::deserialize_compressed(&self.verifying_key[..]) {
-            Ok(key) => {
-                println!("Successfully deserialized verifying key");
-                key
-            },
-            Err(e) => {
-                println!("Failed to deserialize verifying key: {:?}", e);
-                return false;
-            }
-        };
+    pub fn verify_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> bool {
+        self.verifier.verify_proof(proof_bytes, public_inputs)
+    }
 
-        println!("Deserializing proof");
-        let proof = match Proof::<Bn254>::deserialize_compressed(proof_bytes) {
-            Ok(p) => {
-                println!("Successfully deserialized proof");
-                p
-            },
-            Err(e) => {
-                println!("Failed to deserialize proof: {:?}", e);
-                return false;
-            }
-        };
+    pub fn verify_proofs(&self, proofs: &[(&[u8], &[Fr])]) -> Vec<bool> {
+        self.verifier.verify_proofs(proofs)
+    }
+
+    /// Checks a snarkjs `proof.json` against the decimal public inputs of its `public.json`.
+    pub fn verify_snarkjs_proof(&self, proof: &SnarkjsProof, public_signals: &[String]) -> Result<(), ZkError> {
+        self.verifier.check_snarkjs_proof(proof, public_signals)
+    }
+}
+
+impl CircuitVerifier {
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        let prepared_vk = Groth16::<Bn254>::process_vk(vk)
+            .expect("processing a verifying key cannot fail");
+        Self { prepared_vk }
+    }
+
+    /// Loads a verifying key written for `circuit_version`.
+    pub fn load(path: &Path, circuit_version: u32) -> Result<Self, KeyFileError> {
+        let bytes = read_key_file_for(path, KeyKind::Verifying, circuit_version)?;
+        let vk = VerifyingKey::<Bn254>::deserialize_compressed(&bytes[..])
+            .map_err(|e| KeyFileError::Malformed(path.to_path_buf(), e.to_string()))?;
+        Ok(Self::from_vk(&vk))
+    }
+
+    /// Loads the verifying key of `circuit` from its directory under `keys_dir`, checking
+    /// it takes `public_inputs` inputs.
+    pub fn load_circuit(keys_dir: &Path, circuit: &CircuitId, public_inputs: usize) -> Result<Self, KeyFileError> {
+        let path = circuit.key_dir(keys_dir).join("verifying_key.bin");
+        let verifier = Self::load(&path, circuit.version)?;
+        let input_count = verifier.verifying_key().gamma_abc_g1.len() - 1;
+        if input_count != public_inputs {
+            return Err(KeyFileError::Malformed(
+                path,
+                format!("expected a key for {} public inputs, got {}", public_inputs, input_count),
+            ));
+        }
+        Ok(verifier)
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<Bn254> {
+        &self.prepared_vk.vk
+    }
+
+    pub fn solana_verifying_key(&self) -> SolanaVerifyingKey {
+        SolanaVerifyingKey::from_vk(&self.prepared_vk.vk)
+    }
+
+    /// Imports a circom verifying key, as exported by `snarkjs zkey export verificationkey`.
+    pub fn from_snarkjs(vk: &SnarkjsVerifyingKey) -> Result<Self, SnarkjsError> {
+        Ok(Self::from_vk(&vk.to_vk()?))
+    }
+
+    pub fn snarkjs_verifying_key(&self) -> SnarkjsVerifyingKey {
+        SnarkjsVerifyingKey::from_vk(&self.prepared_vk.vk)
+    }
+
+    pub fn check_snarkjs_proof(&self, proof: &SnarkjsProof, public_signals: &[String]) -> Result<(), ZkError> {
+        let proof_bytes = proof.to_compressed()?;
+        self.check_proof(&proof_bytes, &snarkjs::parse_public_signals(public_signals)?)
+    }
+
+    pub fn verify_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> bool {
+        self.check_proof(proof_bytes, public_inputs).is_ok()
+    }
+
+    /// Like [`verify_proof`](Self::verify_proof), but says why a proof was rejected.
+    pub fn check_proof(&self, proof_bytes: &[u8], public_inputs: &[Fr]) -> Result<(), ZkError> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes).map_err(|_| ZkError::MalformedProof)?;
+        let expected = self.prepared_vk.vk.gamma_abc_g1.len() - 1;
+        if public_inputs.len() != expected {
+            return Err(ZkError::InputCount {
+                expected,
+                actual: public_inputs.len(),
+            });
+        }
 
-        println!("Verifying proof with public input");
-        match Groth16::<Bn254>::verify(&vk, &[public_input], &proof) {
-            Ok(result) => {
-                println!("Proof verification completed: {}", result);
-                result
-            },
+        match Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, public_inputs, &proof) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ZkError::PairingCheckFailed),
             Err(e) => {
-                println!("Failed to verify proof: {:?}", e);
-                false
+                log::debug!("Failed to verify proof: {}", e);
+                Err(ZkError::PairingCheckFailed)
             }
         }
     }
+
+    /// Verifies many proofs, returning one result per proof in order. Proofs are checked
+    /// together with a single randomized pairing check; only if that fails is each proof
+    /// checked on its own to find the invalid ones.
+    pub fn verify_proofs(&self, proofs: &[(&[u8], &[Fr])]) -> Vec<bool> {
+        let decoded: Vec<Option<Proof<Bn254>>> = proofs
+            .iter()
+            .map(|(bytes, _)| Proof::<Bn254>::deserialize_compressed(*bytes).ok())
+            .collect();
+
+        let batch: Vec<(&Proof<Bn254>, &[Fr])> = decoded
+            .iter()
+            .zip(proofs)
+            .filter_map(|(proof, (_, inputs))| proof.as_ref().map(|p| (p, *inputs)))
+            .collect();
+
+        if self.batch_check(&batch) {
+            log::debug!("Batch of {} proofs verified", batch.len());
+            return decoded.iter().map(Option::is_some).collect();
+        }
+
+        log::debug!("Batch verification failed, checking proofs individually");
+        decoded
+            .iter()
+            .zip(proofs)
+            .map(|(proof, (_, inputs))| match proof {
+                Some(proof) => matches!(
+                    Groth16::<Bn254>::verify_with_processed_vk(&self.prepared_vk, inputs, proof),
+                    Ok(true)
+                ),
+                None => false,
+            })
+            .collect()
+    }
+
+    /// Checks `prod e(r_i * A_i, B_i) == e(alpha, beta)^sum(r_i) * e(sum(r_i * IC_i), gamma) *
+    /// e(sum(r_i * C_i), delta)` for random `r_i`, so a forged proof can't be cancelled out
+    /// by another one in the batch.
+    fn batch_check(&self, proofs: &[(&Proof<Bn254>, &[Fr])]) -> bool {
+        let mut rng = ark_std::rand::thread_rng();
+        let mut g1 = Vec::with_capacity(proofs.len() + 2);
+        let mut g2 = Vec::with_capacity(proofs.len() + 2);
+        let mut inputs_sum = G1Projective::zero();
+        let mut c_sum = G1Projective::zero();
+        let mut r_sum = Fr::zero();
+
+        for (proof, inputs) in proofs {
+            let r = Fr::from(rng.gen::<u128>());
+            let prepared_inputs = match Groth16::<Bn254>::prepare_inputs(&self.prepared_vk, inputs) {
+                Ok(prepared) => prepared,
+                Err(_) => return false,
+            };
+            g1.push((proof.a * r).into_affine());
+            g2.push(<Bn254 as Pairing>::G2Prepared::from(proof.b));
+            inputs_sum += prepared_inputs * r;
+            c_sum += proof.c * r;
+            r_sum += r;
+        }
+
+        g1.push(inputs_sum.into_affine());
+        g2.push(self.prepared_vk.gamma_g2_neg_pc.clone());
+        g1.push(c_sum.into_affine());
+        g2.push(self.prepared_vk.delta_g2_neg_pc.clone());
+
+        match Bn254::final_exponentiation(Bn254::multi_miller_loop(g1, g2)) {
+            Some(result) => result.0 == self.prepared_vk.alpha_g1_beta_g2.pow(r_sum.into_bigint()),
+            None => false,
+        }
+    }
+}
+
+/// What a proof attests to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProofKind {
+    Wallet,
+    Email,
+}
+
+/// How a proof is carried in API requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ProofEncoding {
+    /// Base64 of the arkworks compressed proof, as created by the prover backend.
+    Compressed(String),
+    /// A snarkjs `proof.json`, e.g. from a circom toolchain on the client.
+    Snarkjs(Box<SnarkjsProof>),
+}
+
+impl ProofEncoding {
+    /// The proof as base64 compressed bytes, converting snarkjs proofs.
+    pub fn to_base64(&self) -> Result<String, ZkError> {
+        match self {
+            Self::Compressed(proof) => Ok(proof.clone()),
+            Self::Snarkjs(proof) => Ok(BASE64.encode(proof.to_compressed()?)),
+        }
+    }
+}
+
+/// One entry of a batch verification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchProof {
+    pub kind: ProofKind,
+    #[serde(default)]
+    pub identity: String,
+    /// The current circuit when missing.
+    #[serde(default = "CircuitId::current")]
+    pub circuit_id: CircuitId,
+    pub proof: String,
+    /// Hex challenge a login proof was bound to. Auditing a batch doesn't consume it.
+    #[serde(default)]
+    pub challenge: Option<String>,
+}
+
+impl BatchProof {
+    /// The challenge public input, `None` if `challenge` is missing or isn't a valid field element.
+    pub fn challenge(&self) -> Option<Fr> {
+        self.challenge.as_deref().and_then(field::fr_from_hex)
+    }
+}
+
+/// Proofs are checked against the keys of the circuit version they were created with.
+pub trait ZKVerifier: Send + Sync {
+    /// Checks a login proof for `wallet` bound to `challenge`. Callers are responsible
+    /// for only passing challenges they issued and haven't seen used yet.
+    fn verify_wallet(&self, circuit: &CircuitId, wallet: &str, challenge: Fr, proof: &str) -> Result<(), ZkError>;
+    fn verify_email(&self, circuit: &CircuitId, email: &str, challenge: Fr, proof: &str) -> Result<(), ZkError>;
+    /// Checks a group membership proof. Whether `inputs.root` is a root of the group and
+    /// the nullifier hash is still unused is up to the caller.
+    fn verify_membership(&self, proof: &str, inputs: &MembershipInputs) -> Result<(), ZkError>;
+    /// Checks that the email behind `commitment` belongs to `domain`, for a proof bound to
+    /// `challenge`. Whether the server issued `commitment` is up to the caller.
+    fn verify_email_domain(&self, commitment: Fr, domain: &str, challenge: Fr, proof: &str) -> Result<(), ZkError>;
+    /// Checks that the donation behind `commitment` went to `project_id` and was at least
+    /// `threshold` lamports, for a proof bound to `challenge`. Whether the server issued
+    /// `commitment` is up to the caller.
+    fn verify_donation_threshold(
+        &self,
+        commitment: Fr,
+        project_id: i32,
+        threshold: u64,
+        challenge: Fr,
+        proof: &str,
+    ) -> Result<(), ZkError>;
+
+    /// Whether proofs for `circuit` are currently accepted, and if not, why.
+    fn check_circuit(&self, _circuit: &CircuitId) -> Result<(), CircuitError> {
+        Ok(())
+    }
+
+    /// Circuits whose ceremony may not have been run yet, and whether their keys are loaded.
+    fn optional_keys(&self) -> Vec<(CircuitId, bool)> {
+        Vec::new()
+    }
+
+    /// Verifying key proofs for `circuit` are checked against, e.g. to export it for
+    /// on-chain verification. `None` if no key is loaded for it.
+    fn verifying_key(&self, _circuit: &CircuitId) -> Option<VerifyingKey<Bn254>> {
+        None
+    }
+
+    /// Verifies every proof in `proofs`, returning one result per proof in order.
+    fn verify_batch(&self, proofs: &[BatchProof]) -> Vec<bool> {
+        proofs
+            .iter()
+            .map(|item| match (item.kind, item.challenge()) {
+                (_, None) => false,
+                (ProofKind::Wallet, Some(challenge)) => {
+                    self.verify_wallet(&item.circuit_id, &item.identity, challenge, &item.proof).is_ok()
+                }
+                (ProofKind::Email, Some(challenge)) => {
+                    self.verify_email(&item.circuit_id, &item.identity, challenge, &item.proof).is_ok()
+                }
+            })
+            .collect()
+    }
+}
+
+/// Creates the base64 proofs checked by a [`ZKVerifier`].
+pub trait ZKProverBackend: Send + Sync {
+    /// Circuit the created proofs are for.
+    fn circuit_id(&self) -> CircuitId {
+        CircuitId::current()
+    }
+
+    fn create_wallet_proof(&self, wallet: &str, challenge: Fr) -> Result<String, ZkError>;
+    fn create_email_proof(&self, email: &str, challenge: Fr) -> Result<String, ZkError>;
+}
+
+/// Which proof system implementation the server runs with, selected by `[zk] backend`
+/// or `ZK_BACKEND`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ZKBackendKind {
+    /// The circuit registry in `keys/`, Groth16 unless `circuits.json` selects Halo 2.
+    Real,
+    /// Accepts every proof unless told otherwise; for local development and tests, and
+    /// rejected by `Config::validate` in any other environment.
+    Mock,
+}
+
+impl std::str::FromStr for ZKBackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "real" => Ok(Self::Real),
+            "mock" => Ok(Self::Mock),
+            other => Err(format!("Unknown ZK backend '{}', expected 'real' or 'mock'", other)),
+        }
+    }
 }
 
-pub trait ZKVerifier {
-    fn verify_wallet(&self, wallet: &str, proof: &str) -> bool;
-    fn verify_email(&self, email: &str, proof: &str) -> bool;
+pub type ZKBackend = (Arc<dyn ZKVerifier>, Arc<dyn ZKProverBackend>);
+
+/// Builds the verifier and prover for `kind`, both backed by the same instance and metered.
+pub fn build_backend(kind: ZKBackendKind, keys_dir: &Path) -> Result<ZKBackend, KeyFileError> {
+    match kind {
+        ZKBackendKind::Real => {
+            let backend = Arc::new(metered::Metered::new(real::RealZKVerifier::load(keys_dir)?));
+            Ok((backend.clone(), backend))
+        }
+        ZKBackendKind::Mock => {
+            let backend = Arc::new(metered::Metered::new(mock::MockZKVerifier::new()));
+            Ok((backend.clone(), backend))
+        }
+    }
 } 
\ No newline at end of file
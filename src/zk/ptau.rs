@@ -0,0 +1,352 @@
+//! Powers of tau in the snarkjs `.ptau` format, the circuit-independent first phase the
+//! Groth16 keys of [`super::ceremony`] are derived from.
+//!
+//! Use the output of a public multi-party ceremony, such as the Hermez
+//! `powersOfTau28_hez_final_<power>.ptau` files, and check the file against its published
+//! hash. [`PowersOfTau::read`] only checks that the powers are consistent with each other,
+//! not who contributed to them.
+
+use std::collections::HashMap;
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, scalar_mul::fixed_base::FixedBase, AffineRepr, CurveGroup, Group, VariableBaseMSM};
+use ark_ff::{BigInt, BigInteger, One, PrimeField, UniformRand};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use rand::{CryptoRng, Rng};
+
+const MAGIC: &[u8; 4] = b"ptau";
+const VERSION: u32 = 1;
+
+const HEADER_SECTION: u32 = 1;
+const TAU_G1_SECTION: u32 = 2;
+const TAU_G2_SECTION: u32 = 3;
+const ALPHA_TAU_G1_SECTION: u32 = 4;
+const BETA_TAU_G1_SECTION: u32 = 5;
+const BETA_G2_SECTION: u32 = 6;
+
+/// Bytes of a base field element.
+const N8: usize = 32;
+
+/// Largest power of a ptau file, that of the Hermez ceremony.
+pub const MAX_POWER: u32 = 28;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PtauError {
+    #[error("Malformed ptau file: {0}")]
+    Malformed(String),
+    #[error("The ptau file is for another curve than BN254")]
+    WrongCurve,
+    #[error("Section {0} of the ptau file holds a point that isn't on the curve")]
+    InvalidPoint(u32),
+    #[error("The powers of tau are inconsistent: {0}")]
+    Inconsistent(&'static str),
+    #[error("The circuit needs 2^{needed} powers of tau, the file has 2^{available}")]
+    TooSmall { needed: u32, available: u32 },
+}
+
+/// `[tau^i]`, `[alpha tau^i]` and `[beta tau^i]` in G1, `[tau^i]` and `[beta]` in G2.
+#[derive(Clone)]
+pub struct PowersOfTau {
+    pub power: u32,
+    /// `2^(power + 1) - 1` powers, the Groth16 H query needs twice as many as the domain size.
+    pub tau_g1: Vec<G1Affine>,
+    pub tau_g2: Vec<G2Affine>,
+    pub alpha_tau_g1: Vec<G1Affine>,
+    pub beta_tau_g1: Vec<G1Affine>,
+    pub beta_g2: G2Affine,
+}
+
+/// The Lagrange basis of an evaluation domain at tau, in the groups of [`PowersOfTau`].
+pub struct LagrangeBasis {
+    pub tau_g1: Vec<G1Projective>,
+    pub alpha_tau_g1: Vec<G1Projective>,
+    pub beta_tau_g1: Vec<G1Projective>,
+    pub tau_g2: Vec<G2Projective>,
+}
+
+impl PowersOfTau {
+    /// Powers of a tau drawn from `rng`. Whoever runs this knows tau and can forge proofs
+    /// for every circuit set up from it, so it is only for tests.
+    pub fn generate<R: Rng + CryptoRng>(power: u32, rng: &mut R) -> Self {
+        let (tau, alpha, beta) = (Fr::rand(rng), Fr::rand(rng), Fr::rand(rng));
+        let n = 1usize << power;
+        let powers: Vec<Fr> = std::iter::successors(Some(Fr::one()), |p| Some(*p * tau))
+            .take(2 * n - 1)
+            .collect();
+        let alpha_powers: Vec<Fr> = powers[..n].iter().map(|p| *p * alpha).collect();
+        let beta_powers: Vec<Fr> = powers[..n].iter().map(|p| *p * beta).collect();
+
+        let scalar_bits = Fr::MODULUS_BIT_SIZE as usize;
+        let g1_window = FixedBase::get_mul_window_size(4 * n);
+        let g1_table = FixedBase::get_window_table(scalar_bits, g1_window, G1Projective::generator());
+        let g1 = |scalars: &[Fr]| {
+            G1Projective::normalize_batch(&FixedBase::msm::<G1Projective>(scalar_bits, g1_window, &g1_table, scalars))
+        };
+        let g2_window = FixedBase::get_mul_window_size(n);
+        let g2_table = FixedBase::get_window_table(scalar_bits, g2_window, G2Projective::generator());
+        let tau_g2 = FixedBase::msm::<G2Projective>(scalar_bits, g2_window, &g2_table, &powers[..n]);
+
+        Self {
+            power,
+            tau_g1: g1(&powers),
+            tau_g2: G2Projective::normalize_batch(&tau_g2),
+            alpha_tau_g1: g1(&alpha_powers),
+            beta_tau_g1: g1(&beta_powers),
+            beta_g2: (G2Projective::generator() * beta).into_affine(),
+        }
+    }
+
+    /// Reads a snarkjs `.ptau` file and checks its powers, see [`PowersOfTau::check`].
+    /// Sections other than the powers, e.g. contributions or prepared Lagrange points, are
+    /// skipped.
+    pub fn read(bytes: &[u8]) -> Result<Self, PtauError> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC {
+            return Err(malformed("not a ptau file"));
+        }
+        if reader.u32()? != VERSION {
+            return Err(malformed("unsupported version"));
+        }
+        let mut sections = HashMap::new();
+        for _ in 0..reader.u32()? {
+            let kind = reader.u32()?;
+            let size = usize::try_from(reader.u64()?).map_err(|_| malformed("section too large"))?;
+            if sections.insert(kind, reader.take(size)?).is_some() {
+                return Err(malformed("duplicate section"));
+            }
+        }
+        let section = |kind: u32| {
+            sections
+                .get(&kind)
+                .map(|data| Reader(data))
+                .ok_or_else(|| malformed(&format!("missing section {}", kind)))
+        };
+
+        let mut header = section(HEADER_SECTION)?;
+        if header.u32()? as usize != N8 || header.take(N8)? != Fq::MODULUS.to_bytes_le().as_slice() {
+            return Err(PtauError::WrongCurve);
+        }
+        let power = header.u32()?;
+        if !(1..=MAX_POWER).contains(&power) {
+            return Err(malformed("power out of range"));
+        }
+        let n = 1usize << power;
+
+        let powers = Self {
+            power,
+            tau_g1: section(TAU_G1_SECTION)?.g1s(TAU_G1_SECTION, 2 * n - 1)?,
+            tau_g2: section(TAU_G2_SECTION)?.g2s(TAU_G2_SECTION, n)?,
+            alpha_tau_g1: section(ALPHA_TAU_G1_SECTION)?.g1s(ALPHA_TAU_G1_SECTION, n)?,
+            beta_tau_g1: section(BETA_TAU_G1_SECTION)?.g1s(BETA_TAU_G1_SECTION, n)?,
+            beta_g2: section(BETA_G2_SECTION)?.g2s(BETA_G2_SECTION, 1)?[0],
+        };
+        powers.check()?;
+        Ok(powers)
+    }
+
+    /// The powers as a snarkjs `.ptau` file, without contributions.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend((N8 as u32).to_le_bytes());
+        header.extend(Fq::MODULUS.to_bytes_le());
+        header.extend(self.power.to_le_bytes());
+        header.extend(self.power.to_le_bytes());
+
+        let sections = [
+            (HEADER_SECTION, header),
+            (TAU_G1_SECTION, g1_bytes(&self.tau_g1)),
+            (TAU_G2_SECTION, g2_bytes(&self.tau_g2)),
+            (ALPHA_TAU_G1_SECTION, g1_bytes(&self.alpha_tau_g1)),
+            (BETA_TAU_G1_SECTION, g1_bytes(&self.beta_tau_g1)),
+            (BETA_G2_SECTION, g2_bytes(&[self.beta_g2])),
+        ];
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        bytes.extend((sections.len() as u32).to_le_bytes());
+        for (kind, data) in sections {
+            bytes.extend(kind.to_le_bytes());
+            bytes.extend((data.len() as u64).to_le_bytes());
+            bytes.extend(data);
+        }
+        bytes
+    }
+
+    /// Checks that every list holds successive powers of the same tau, scaled by the same
+    /// alpha and beta, using random linear combinations of neighbouring powers.
+    pub fn check(&self) -> Result<(), PtauError> {
+        let n = 1usize << self.power;
+        if self.tau_g1.len() != 2 * n - 1
+            || self.tau_g2.len() != n
+            || self.alpha_tau_g1.len() != n
+            || self.beta_tau_g1.len() != n
+        {
+            return Err(malformed("wrong number of powers"));
+        }
+        let (g1, g2) = (G1Affine::generator(), G2Affine::generator());
+        if self.tau_g1[0] != g1 || self.tau_g2[0] != g2 {
+            return Err(PtauError::Inconsistent("the powers don't start at the generators"));
+        }
+        if [self.tau_g1[1], self.alpha_tau_g1[0], self.beta_tau_g1[0]].iter().any(|p| p.is_zero())
+            || self.beta_g2.is_zero()
+        {
+            return Err(PtauError::Inconsistent("tau, alpha and beta must not be zero"));
+        }
+
+        let mut rng = ark_std::rand::thread_rng();
+        let tau_g2 = self.tau_g2[1];
+        for (points, what) in [
+            (&self.tau_g1, "tau in G1"),
+            (&self.alpha_tau_g1, "alpha tau in G1"),
+            (&self.beta_tau_g1, "beta tau in G1"),
+        ] {
+            let (lower, upper) = neighbours::<G1Projective, _>(points, &mut rng);
+            if Bn254::pairing(lower, tau_g2) != Bn254::pairing(upper, g2) {
+                return Err(PtauError::Inconsistent(what));
+            }
+        }
+        let (lower, upper) = neighbours::<G2Projective, _>(&self.tau_g2, &mut rng);
+        if Bn254::pairing(self.tau_g1[1], lower) != Bn254::pairing(g1, upper) {
+            return Err(PtauError::Inconsistent("tau in G2"));
+        }
+        if Bn254::pairing(self.beta_tau_g1[0], g2) != Bn254::pairing(g1, self.beta_g2) {
+            return Err(PtauError::Inconsistent("beta in G2"));
+        }
+        Ok(())
+    }
+
+    /// The Lagrange basis of `domain` at tau, from an inverse FFT over the powers.
+    pub fn lagrange(&self, domain: &GeneralEvaluationDomain<Fr>) -> Result<LagrangeBasis, PtauError> {
+        let size = domain.size();
+        let needed = size.trailing_zeros();
+        if needed > self.power {
+            return Err(PtauError::TooSmall { needed, available: self.power });
+        }
+        Ok(LagrangeBasis {
+            tau_g1: ifft(&self.tau_g1[..size], domain),
+            alpha_tau_g1: ifft(&self.alpha_tau_g1[..size], domain),
+            beta_tau_g1: ifft(&self.beta_tau_g1[..size], domain),
+            tau_g2: ifft(&self.tau_g2[..size], domain),
+        })
+    }
+}
+
+fn ifft<G: CurveGroup<ScalarField = Fr>>(points: &[G::Affine], domain: &GeneralEvaluationDomain<Fr>) -> Vec<G> {
+    let mut values: Vec<G> = points.iter().map(|p| p.into_group()).collect();
+    domain.ifft_in_place(&mut values);
+    values
+}
+
+/// `sum r_i P_i` and `sum r_i P_(i+1)` for random `r_i`, equal up to tau if `points` are powers of it.
+fn neighbours<G: VariableBaseMSM<ScalarField = Fr>, R: Rng>(points: &[G::MulBase], rng: &mut R) -> (G, G) {
+    let coeffs: Vec<Fr> = (1..points.len()).map(|_| Fr::rand(rng)).collect();
+    let lower = G::msm(&points[..points.len() - 1], &coeffs).expect("lengths match");
+    let upper = G::msm(&points[1..], &coeffs).expect("lengths match");
+    (lower, upper)
+}
+
+fn malformed(reason: &str) -> PtauError {
+    PtauError::Malformed(reason.to_string())
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], PtauError> {
+        if self.0.len() < len {
+            return Err(malformed("unexpected end of file"));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, PtauError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().expect("4 bytes")))
+    }
+
+    fn u64(&mut self) -> Result<u64, PtauError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().expect("8 bytes")))
+    }
+
+    /// A base field element, little-endian in Montgomery form as snarkjs writes it.
+    fn fq(&mut self, section: u32) -> Result<Fq, PtauError> {
+        let bytes = self.take(N8)?;
+        let mut limbs = [0u64; 4];
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().expect("8 bytes"));
+        }
+        let montgomery = BigInt::new(limbs);
+        if montgomery >= Fq::MODULUS {
+            return Err(PtauError::InvalidPoint(section));
+        }
+        Ok(Fq::new_unchecked(montgomery))
+    }
+
+    fn g1s(&mut self, section: u32, count: usize) -> Result<Vec<G1Affine>, PtauError> {
+        let points = (0..count)
+            .map(|_| {
+                let (x, y) = (self.fq(section)?, self.fq(section)?);
+                if x == Fq::from(0u8) && y == Fq::from(0u8) {
+                    return Ok(G1Affine::identity());
+                }
+                let point = G1Affine::new_unchecked(x, y);
+                if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+                    return Err(PtauError::InvalidPoint(section));
+                }
+                Ok(point)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.finish(points)
+    }
+
+    fn g2s(&mut self, section: u32, count: usize) -> Result<Vec<G2Affine>, PtauError> {
+        let points = (0..count)
+            .map(|_| {
+                let x = Fq2::new(self.fq(section)?, self.fq(section)?);
+                let y = Fq2::new(self.fq(section)?, self.fq(section)?);
+                if x == Fq2::from(0u8) && y == Fq2::from(0u8) {
+                    return Ok(G2Affine::identity());
+                }
+                let point = G2Affine::new_unchecked(x, y);
+                if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+                    return Err(PtauError::InvalidPoint(section));
+                }
+                Ok(point)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.finish(points)
+    }
+
+    fn finish<T>(&self, points: Vec<T>) -> Result<Vec<T>, PtauError> {
+        if !self.0.is_empty() {
+            return Err(malformed("section has trailing bytes"));
+        }
+        Ok(points)
+    }
+}
+
+fn fq_bytes(bytes: &mut Vec<u8>, value: &Fq) {
+    for limb in value.0 .0 {
+        bytes.extend(limb.to_le_bytes());
+    }
+}
+
+fn g1_bytes(points: &[G1Affine]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(points.len() * 2 * N8);
+    for point in points {
+        let (x, y) = point.xy().map(|(x, y)| (*x, *y)).unwrap_or_default();
+        fq_bytes(&mut bytes, &x);
+        fq_bytes(&mut bytes, &y);
+    }
+    bytes
+}
+
+fn g2_bytes(points: &[G2Affine]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(points.len() * 4 * N8);
+    for point in points {
+        let (x, y) = point.xy().map(|(x, y)| (*x, *y)).unwrap_or_default();
+        for coordinate in [x.c0, x.c1, y.c0, y.c1] {
+            fq_bytes(&mut bytes, &coordinate);
+        }
+    }
+    bytes
+}
@@ -1,155 +1,216 @@
-use super::{ZKVerifier, ZKProver};
+use std::collections::HashMap;
+use std::path::Path;
+use super::attributes::EmailDomainVerifier;
+use super::circuits::{AnyProver, CircuitError, CircuitId, CircuitRegistry};
+use super::donations::DonationThresholdVerifier;
+use super::error::ZkError;
+use super::{BatchProof, ZKProverBackend, ZKVerifier};
+use super::field::identity_to_field;
+use super::keys::KeyFileError;
+use super::membership::{MembershipInputs, MembershipVerifier};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use ark_bn254::Fr;
-use ark_std::{One, Zero, str::FromStr};
-use sha3::{Digest, Keccak256};
-use num_bigint;
-use ark_serialize::CanonicalSerialize;
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::VerifyingKey;
+
+/// Index into the batch, decoded proof and public inputs.
+type PendingProof = (usize, Vec<u8>, [Fr; 2]);
 
 pub struct RealZKVerifier {
-    prover: ZKProver,
+    registry: CircuitRegistry,
+    membership: Option<MembershipVerifier>,
+    email_domain: Option<EmailDomainVerifier>,
+    donation_threshold: Option<DonationThresholdVerifier>,
 }
 
 impl RealZKVerifier {
-    pub fn new() -> Self {
-        println!("Creating new RealZKVerifier");
+    /// Loads every accepted circuit version from `keys_dir`, see [`CircuitRegistry::load`],
+    /// and the membership, email domain and donation threshold verifying keys if their
+    /// ceremonies have been run.
+    pub fn load(keys_dir: &Path) -> Result<Self, KeyFileError> {
+        Ok(Self {
+            registry: CircuitRegistry::load(keys_dir)?,
+            membership: optional_key(MembershipVerifier::load(keys_dir))?,
+            email_domain: optional_key(EmailDomainVerifier::load(keys_dir))?,
+            donation_threshold: optional_key(DonationThresholdVerifier::load(keys_dir))?,
+        })
+    }
+
+    /// A verifier accepting only proofs for the current circuit, made with `prover`.
+    pub fn from_prover(prover: impl Into<AnyProver>) -> Self {
+        Self::from_registry(CircuitRegistry::new(prover))
+    }
+
+    pub fn from_registry(registry: CircuitRegistry) -> Self {
         Self {
-            prover: ZKProver::new(),
+            registry,
+            membership: None,
+            email_domain: None,
+            donation_threshold: None,
         }
     }
 
-    pub fn create_wallet_proof(&self, wallet: &str) -> Option<String> {
-        println!("\n=== Creating Wallet Proof ===");
-        println!("Wallet address: {}", wallet);
-        
-        // catch panics
-        let result = std::panic::catch_unwind(|| {
-            println!("Calculating field element...");
-            let input = Self::hash_to_field(wallet);
-            println!("Field element calculated successfully");
-            
-            println!("Creating proof with input...");
-            match self.prover.create_proof(input) {
-                Ok(proof_bytes) => {
-                    println!("Proof bytes created successfully, length: {}", proof_bytes.len());
-                    let proof_base64 = BASE64.encode(&proof_bytes);
-                    println!("Proof encoded to base64: {}", proof_base64);
-                    Some(proof_base64)
-                },
-                Err(e) => {
-                    println!("Error creating proof: {:?}", e);
-                    None
-                }
-            }
-        });
-
-        match result {
-            Ok(proof_option) => {
-                println!("Proof creation completed successfully");
-                proof_option
-            },
-            Err(e) => {
-                println!("Panic occurred while creating proof: {:?}", e);
-                None
-            }
-        }
+    pub fn with_membership(mut self, verifier: MembershipVerifier) -> Self {
+        self.membership = Some(verifier);
+        self
     }
 
-    pub fn create_email_proof(&self, email: &str) -> Option<String> {
-        println!("\n=== Creating Email Proof ===");
-        println!("Email address: {}", email);
-        
-        // catch panics
-        let result = std::panic::catch_unwind(|| {
-            println!("Calculating field element...");
-            let input = Self::hash_to_field(email);
-            println!("Field element calculated successfully");
-            
-            println!("Creating proof with input...");
-            match self.prover.create_proof(input) {
-                Ok(proof_bytes) => {
-                    println!("Proof bytes created successfully, length: {}", proof_bytes.len());
-                    let proof_base64 = BASE64.encode(&proof_bytes);
-                    println!("Proof encoded to base64: {}", proof_base64);
-                    Some(proof_base64)
-                },
-                Err(e) => {
-                    println!("Error creating proof: {:?}", e);
-                    None
-                }
-            }
-        });
-
-        match result {
-            Ok(proof_option) => {
-                println!("Proof creation completed successfully");
-                proof_option
-            },
-            Err(e) => {
-                println!("Panic occurred while creating proof: {:?}", e);
-                None
-            }
-        }
+    pub fn with_email_domain(mut self, verifier: EmailDomainVerifier) -> Self {
+        self.email_domain = Some(verifier);
+        self
+    }
+
+    pub fn with_donation_threshold(mut self, verifier: DonationThresholdVerifier) -> Self {
+        self.donation_threshold = Some(verifier);
+        self
+    }
+
+    fn verify_for(&self, circuit: &CircuitId, proof_bytes: &[u8], public_inputs: &[Fr]) -> Result<(), ZkError> {
+        let verifier = self.registry.verifier(circuit).inspect_err(|e| log::debug!("Rejecting proof: {}", e))?;
+        verifier.check_proof(proof_bytes, public_inputs)
+    }
+
+    fn create_proof(&self, input: Fr, challenge: Fr) -> Result<String, ZkError> {
+        let proof_bytes = self.registry.prover().create_proof(input, challenge)?;
+        Ok(BASE64.encode(&proof_bytes))
     }
 
     fn hash_to_field(input: &str) -> Fr {
-        println!("\n=== Hash to Field Process ===");
-        println!("Input: {}", input);
-        
-        // return a fixed value for testing
-        let result = Fr::one();
-        
-        println!("Using test value: Fr::one()");
-        println!("=========================\n");
-        
-        result
+        identity_to_field(input)
     }
 }
 
-impl ZKVerifier for RealZKVerifier {
-    fn verify_wallet(&self, wallet: &str, proof: &str) -> bool {
-        println!("Verifying wallet: {}", wallet);
-        println!("With proof: {}", proof);
-
-        let proof_bytes = match BASE64.decode(proof) {
-            Ok(bytes) => {
-                println!("Successfully decoded proof, length: {}", bytes.len());
-                bytes
-            },
-            Err(e) => {
-                println!("Failed to decode proof: {:?}", e);
-                return false;
-            }
-        };
+impl ZKProverBackend for RealZKVerifier {
+    fn circuit_id(&self) -> CircuitId {
+        self.registry.current().clone()
+    }
+
+    fn create_wallet_proof(&self, wallet: &str, challenge: Fr) -> Result<String, ZkError> {
+        self.create_proof(Self::hash_to_field(wallet), challenge)
+    }
 
+    fn create_email_proof(&self, email: &str, challenge: Fr) -> Result<String, ZkError> {
+        self.create_proof(Self::hash_to_field(email), challenge)
+    }
+}
+
+impl ZKVerifier for RealZKVerifier {
+    fn verify_wallet(&self, circuit: &CircuitId, wallet: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        let proof_bytes = decode_proof(proof)?;
         let input = Self::hash_to_field(wallet);
-        println!("Generated field element from wallet");
+        self.verify_for(circuit, &proof_bytes, &[input, challenge])
+    }
+
+    fn verify_email(&self, circuit: &CircuitId, email: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        let proof_bytes = decode_proof(proof)?;
+        let input = Self::hash_to_field(email);
+        self.verify_for(circuit, &proof_bytes, &[input, challenge])
+    }
 
-        let result = self.prover.verify_proof(&proof_bytes, input);
-        println!("Verification result: {}", result);
-        result
+    fn verify_membership(&self, proof: &str, inputs: &MembershipInputs) -> Result<(), ZkError> {
+        let verifier = self
+            .membership
+            .as_ref()
+            .ok_or_else(|| ZkError::KeyNotLoaded(MembershipVerifier::circuit_id()))?;
+        verifier.check(&decode_proof(proof)?, inputs)
     }
 
-    fn verify_email(&self, email: &str, proof: &str) -> bool {
-        println!("Verifying email: {}", email);
-        println!("With proof: {}", proof);
+    fn verify_email_domain(&self, commitment: Fr, domain: &str, challenge: Fr, proof: &str) -> Result<(), ZkError> {
+        let verifier = self
+            .email_domain
+            .as_ref()
+            .ok_or_else(|| ZkError::KeyNotLoaded(EmailDomainVerifier::circuit_id()))?;
+        verifier.check(&decode_proof(proof)?, commitment, domain, challenge)
+    }
+
+    fn verify_donation_threshold(
+        &self,
+        commitment: Fr,
+        project_id: i32,
+        threshold: u64,
+        challenge: Fr,
+        proof: &str,
+    ) -> Result<(), ZkError> {
+        let verifier = self
+            .donation_threshold
+            .as_ref()
+            .ok_or_else(|| ZkError::KeyNotLoaded(DonationThresholdVerifier::circuit_id()))?;
+        verifier.check(&decode_proof(proof)?, commitment, project_id, threshold, challenge)
+    }
 
-        let proof_bytes = match BASE64.decode(proof) {
-            Ok(bytes) => {
-                println!("Successfully decoded proof, length: {}", bytes.len());
-                bytes
-            },
-            Err(e) => {
-                println!("Failed to decode proof: {:?}", e);
-                return false;
+    fn check_circuit(&self, circuit: &CircuitId) -> Result<(), CircuitError> {
+        self.registry.verifier(circuit).map(|_| ())
+    }
+
+    fn optional_keys(&self) -> Vec<(CircuitId, bool)> {
+        vec![
+            (MembershipVerifier::circuit_id(), self.membership.is_some()),
+            (EmailDomainVerifier::circuit_id(), self.email_domain.is_some()),
+            (DonationThresholdVerifier::circuit_id(), self.donation_threshold.is_some()),
+        ]
+    }
+
+    fn verifying_key(&self, circuit: &CircuitId) -> Option<VerifyingKey<Bn254>> {
+        if let Ok(verifier) = self.registry.verifier(circuit) {
+            return verifier.groth16().map(|verifier| verifier.verifying_key().clone());
+        }
+        if *circuit == MembershipVerifier::circuit_id() {
+            return self.membership.as_ref().map(|v| v.verifying_key().clone());
+        }
+        if *circuit == EmailDomainVerifier::circuit_id() {
+            return self.email_domain.as_ref().map(|v| v.verifying_key().clone());
+        }
+        if *circuit == DonationThresholdVerifier::circuit_id() {
+            return self.donation_threshold.as_ref().map(|v| v.verifying_key().clone());
+        }
+        None
+    }
+
+    fn verify_batch(&self, proofs: &[BatchProof]) -> Vec<bool> {
+        let mut results = vec![false; proofs.len()];
+
+        // proofs that aren't valid base64 or target a retired circuit stay invalid
+        let mut by_circuit: HashMap<&CircuitId, Vec<PendingProof>> = HashMap::new();
+        for (index, item) in proofs.iter().enumerate() {
+            let Ok(bytes) = BASE64.decode(&item.proof) else {
+                continue;
+            };
+            let Some(challenge) = item.challenge() else {
+                continue;
+            };
+            let input = Self::hash_to_field(&item.identity);
+            by_circuit.entry(&item.circuit_id).or_default().push((index, bytes, [input, challenge]));
+        }
+
+        for (circuit, items) in by_circuit {
+            let verifier = match self.registry.verifier(circuit) {
+                Ok(verifier) => verifier,
+                Err(e) => {
+                    log::debug!("Rejecting {} proofs: {}", items.len(), e);
+                    continue;
+                }
+            };
+            let batch: Vec<(&[u8], &[Fr])> = items.iter().map(|(_, bytes, inputs)| (bytes.as_slice(), &inputs[..])).collect();
+            for ((index, _, _), valid) in items.iter().zip(verifier.verify_proofs(&batch)) {
+                results[*index] = valid;
             }
-        };
+        }
+        results
+    }
+}
 
-        let input = Self::hash_to_field(email);
-        println!("Generated field element from email");
+fn decode_proof(proof: &str) -> Result<Vec<u8>, ZkError> {
+    BASE64.decode(proof).map_err(|_| ZkError::MalformedBase64)
+}
 
-        let result = self.prover.verify_proof(&proof_bytes, input);
-        println!("Verification result: {}", result);
-        result
+/// Keys of circuits that only some deployments use may be missing; the proofs they
+/// verify are rejected then.
+fn optional_key<T>(loaded: Result<T, KeyFileError>) -> Result<Option<T>, KeyFileError> {
+    match loaded {
+        Ok(verifier) => Ok(Some(verifier)),
+        Err(KeyFileError::Missing(path)) => {
+            log::warn!("{} not found, proofs for its circuit will be rejected", path.display());
+            Ok(None)
+        }
+        Err(e) => Err(e),
     }
-} 
\ No newline at end of file
+}
@@ -0,0 +1,218 @@
+//! Groth16 proofs and verifying keys in the JSON formats of snarkjs, as produced for
+//! circom circuits: `verification_key.json`, `proof.json` and `public.json`.
+//!
+//! Numbers are decimal strings. Points are projective `[x, y, z]` with `z` being `"1"`,
+//! or `"0"` for the point at infinity, and G2 coordinates are `[c0, c1]` pairs.
+
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{PrimeField, Zero};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::CanonicalSerialize;
+use serde::{Deserialize, Serialize};
+
+const PROTOCOL: &str = "groth16";
+const CURVE: &str = "bn128";
+
+type G1Json = [String; 3];
+type G2Json = [[String; 2]; 3];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SnarkjsError {
+    #[error("Invalid snarkjs JSON: {0}")]
+    Json(String),
+    #[error("Unsupported protocol '{0}', expected groth16")]
+    UnsupportedProtocol(String),
+    #[error("Unsupported curve '{0}', expected bn128")]
+    UnsupportedCurve(String),
+    #[error("'{0}' is not a decimal number in the field")]
+    InvalidNumber(String),
+    #[error("Point is not on the curve or not in its prime order subgroup")]
+    InvalidPoint,
+    #[error("Key declares {declared} public inputs but has {actual} IC points")]
+    InputCountMismatch { declared: usize, actual: usize },
+}
+
+/// `verification_key.json` as written by `snarkjs zkey export verificationkey`.
+/// `vk_alphabeta_12` is derived from the other fields and not read.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnarkjsVerifyingKey {
+    pub protocol: String,
+    pub curve: String,
+    #[serde(rename = "nPublic")]
+    pub n_public: usize,
+    pub vk_alpha_1: G1Json,
+    pub vk_beta_2: G2Json,
+    pub vk_gamma_2: G2Json,
+    pub vk_delta_2: G2Json,
+    #[serde(rename = "IC")]
+    pub ic: Vec<G1Json>,
+}
+
+/// `proof.json` as written by `snarkjs groth16 prove`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnarkjsProof {
+    pub pi_a: G1Json,
+    pub pi_b: G2Json,
+    pub pi_c: G1Json,
+    pub protocol: String,
+    /// Older snarkjs versions leave the curve out.
+    #[serde(default = "default_curve")]
+    pub curve: String,
+}
+
+fn default_curve() -> String {
+    CURVE.to_string()
+}
+
+fn check_header(protocol: &str, curve: &str) -> Result<(), SnarkjsError> {
+    if protocol != PROTOCOL {
+        return Err(SnarkjsError::UnsupportedProtocol(protocol.to_string()));
+    }
+    if curve != CURVE {
+        return Err(SnarkjsError::UnsupportedCurve(curve.to_string()));
+    }
+    Ok(())
+}
+
+/// Parses a canonical decimal field element, rejecting values that are only congruent
+/// to one so every element has a single encoding.
+fn parse_decimal<F: PrimeField>(value: &str) -> Result<F, SnarkjsError> {
+    F::from_str(value)
+        .ok()
+        .filter(|parsed| parsed.into_bigint().to_string() == value)
+        .ok_or_else(|| SnarkjsError::InvalidNumber(value.to_string()))
+}
+
+fn to_decimal<F: PrimeField>(value: &F) -> String {
+    value.into_bigint().to_string()
+}
+
+/// Parses a G1 point from its `[x, y, z]` decimal strings.
+pub fn g1_from_json(point: &G1Json) -> Result<G1Affine, SnarkjsError> {
+    let [x, y, z] = point;
+    match z.as_str() {
+        "0" => Ok(G1Affine::zero()),
+        "1" => {
+            let point = G1Affine::new_unchecked(parse_decimal(x)?, parse_decimal(y)?);
+            if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+                return Err(SnarkjsError::InvalidPoint);
+            }
+            Ok(point)
+        }
+        _ => Err(SnarkjsError::InvalidPoint),
+    }
+}
+
+fn parse_fq2([c0, c1]: &[String; 2]) -> Result<Fq2, SnarkjsError> {
+    Ok(Fq2::new(parse_decimal(c0)?, parse_decimal(c1)?))
+}
+
+/// Parses a G2 point from its `[[x.c0, x.c1], [y.c0, y.c1], z]` decimal strings.
+pub fn g2_from_json(point: &G2Json) -> Result<G2Affine, SnarkjsError> {
+    let [x, y, z] = point;
+    match (z[0].as_str(), z[1].as_str()) {
+        ("0", "0") => Ok(G2Affine::zero()),
+        ("1", "0") => {
+            let point = G2Affine::new_unchecked(parse_fq2(x)?, parse_fq2(y)?);
+            if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+                return Err(SnarkjsError::InvalidPoint);
+            }
+            Ok(point)
+        }
+        _ => Err(SnarkjsError::InvalidPoint),
+    }
+}
+
+fn g1_json(point: &G1Affine) -> G1Json {
+    match point.xy() {
+        Some((x, y)) => [to_decimal(x), to_decimal(y), "1".to_string()],
+        None => ["0".to_string(), "1".to_string(), "0".to_string()],
+    }
+}
+
+fn g2_json(point: &G2Affine) -> G2Json {
+    let fq2 = |value: &Fq2| [to_decimal(&value.c0), to_decimal(&value.c1)];
+    match point.xy() {
+        Some((x, y)) => [fq2(x), fq2(y), ["1".to_string(), "0".to_string()]],
+        None => [fq2(&Fq2::zero()), fq2(&Fq2::new(Fq::from(1u64), Fq::zero())), fq2(&Fq2::zero())],
+    }
+}
+
+impl SnarkjsVerifyingKey {
+    pub fn from_json(json: &str) -> Result<Self, SnarkjsError> {
+        serde_json::from_str(json).map_err(|e| SnarkjsError::Json(e.to_string()))
+    }
+
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            protocol: PROTOCOL.to_string(),
+            curve: CURVE.to_string(),
+            n_public: vk.gamma_abc_g1.len() - 1,
+            vk_alpha_1: g1_json(&vk.alpha_g1),
+            vk_beta_2: g2_json(&vk.beta_g2),
+            vk_gamma_2: g2_json(&vk.gamma_g2),
+            vk_delta_2: g2_json(&vk.delta_g2),
+            ic: vk.gamma_abc_g1.iter().map(g1_json).collect(),
+        }
+    }
+
+    pub fn to_vk(&self) -> Result<VerifyingKey<Bn254>, SnarkjsError> {
+        check_header(&self.protocol, &self.curve)?;
+        if self.ic.len() != self.n_public + 1 {
+            return Err(SnarkjsError::InputCountMismatch {
+                declared: self.n_public,
+                actual: self.ic.len(),
+            });
+        }
+        Ok(VerifyingKey {
+            alpha_g1: g1_from_json(&self.vk_alpha_1)?,
+            beta_g2: g2_from_json(&self.vk_beta_2)?,
+            gamma_g2: g2_from_json(&self.vk_gamma_2)?,
+            delta_g2: g2_from_json(&self.vk_delta_2)?,
+            gamma_abc_g1: self.ic.iter().map(g1_from_json).collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl SnarkjsProof {
+    pub fn from_json(json: &str) -> Result<Self, SnarkjsError> {
+        serde_json::from_str(json).map_err(|e| SnarkjsError::Json(e.to_string()))
+    }
+
+    pub fn from_proof(proof: &Proof<Bn254>) -> Self {
+        Self {
+            pi_a: g1_json(&proof.a),
+            pi_b: g2_json(&proof.b),
+            pi_c: g1_json(&proof.c),
+            protocol: PROTOCOL.to_string(),
+            curve: CURVE.to_string(),
+        }
+    }
+
+    pub fn to_proof(&self) -> Result<Proof<Bn254>, SnarkjsError> {
+        check_header(&self.protocol, &self.curve)?;
+        Ok(Proof {
+            a: g1_from_json(&self.pi_a)?,
+            b: g2_from_json(&self.pi_b)?,
+            c: g1_from_json(&self.pi_c)?,
+        })
+    }
+
+    /// The proof in the compressed encoding `ZKProver::create_proof` produces.
+    pub fn to_compressed(&self) -> Result<Vec<u8>, SnarkjsError> {
+        let mut proof_bytes = Vec::new();
+        self.to_proof()?.serialize_compressed(&mut proof_bytes).unwrap();
+        Ok(proof_bytes)
+    }
+}
+
+/// Parses the decimal strings of a `public.json`.
+pub fn parse_public_signals(signals: &[String]) -> Result<Vec<Fr>, SnarkjsError> {
+    signals.iter().map(|signal| parse_decimal(signal)).collect()
+}
+
+/// Public inputs as the decimal strings of a `public.json`.
+pub fn public_signals(inputs: &[Fr]) -> Vec<String> {
+    inputs.iter().map(to_decimal).collect()
+}
@@ -0,0 +1,207 @@
+//! Groth16 keys and proofs in the layout of the groth16-solana crate, for verifying proofs
+//! in a Solana program with the alt_bn128 syscalls.
+//!
+//! Field elements are 32 bytes big-endian. G1 points are `x || y`, G2 points are
+//! `x.c1 || x.c0 || y.c1 || y.c0` as in EIP-197, and the point at infinity is all zeros.
+//! Proofs carry `-A` rather than `A`, so the program checks
+//! `e(-A, B) * e(inputs, gamma) * e(C, delta) * e(alpha, beta) == 1` in one pairing call.
+
+use std::fmt::Write as _;
+use ark_bn254::{Bn254, Fq, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+use ark_serialize::CanonicalDeserialize;
+use serde::Serialize;
+use solana_program::alt_bn128::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SolanaExportError {
+    #[error("Proof is not a valid compressed Groth16 proof")]
+    MalformedProof,
+    #[error("Expected {expected} public inputs, got {actual}")]
+    WrongInputCount { expected: usize, actual: usize },
+    #[error("Public input {0} is not in the BN254 scalar field")]
+    InputOutOfField(usize),
+    #[error("alt_bn128 operation failed")]
+    Syscall,
+}
+
+fn fq_to_bytes(value: &Fq) -> [u8; 32] {
+    value.into_bigint().to_bytes_be().try_into().unwrap()
+}
+
+pub fn g1_to_bytes(point: &G1Affine) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    if let Some((x, y)) = point.xy() {
+        bytes[..32].copy_from_slice(&fq_to_bytes(x));
+        bytes[32..].copy_from_slice(&fq_to_bytes(y));
+    }
+    bytes
+}
+
+pub fn g2_to_bytes(point: &G2Affine) -> [u8; 128] {
+    let mut bytes = [0u8; 128];
+    if let Some((x, y)) = point.xy() {
+        bytes[..32].copy_from_slice(&fq_to_bytes(&x.c1));
+        bytes[32..64].copy_from_slice(&fq_to_bytes(&x.c0));
+        bytes[64..96].copy_from_slice(&fq_to_bytes(&y.c1));
+        bytes[96..].copy_from_slice(&fq_to_bytes(&y.c0));
+    }
+    bytes
+}
+
+pub fn fr_to_bytes(value: &Fr) -> [u8; 32] {
+    value.into_bigint().to_bytes_be().try_into().unwrap()
+}
+
+/// Verifying key fields of groth16-solana's `Groth16Verifyingkey`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolanaVerifyingKey {
+    pub alpha_g1: [u8; 64],
+    pub beta_g2: [u8; 128],
+    pub gamma_g2: [u8; 128],
+    pub delta_g2: [u8; 128],
+    /// `ic[0]` plus one point per public input.
+    pub ic: Vec<[u8; 64]>,
+}
+
+impl SolanaVerifyingKey {
+    pub fn from_vk(vk: &VerifyingKey<Bn254>) -> Self {
+        Self {
+            alpha_g1: g1_to_bytes(&vk.alpha_g1),
+            beta_g2: g2_to_bytes(&vk.beta_g2),
+            gamma_g2: g2_to_bytes(&vk.gamma_g2),
+            delta_g2: g2_to_bytes(&vk.delta_g2),
+            ic: vk.gamma_abc_g1.iter().map(g1_to_bytes).collect(),
+        }
+    }
+
+    pub fn public_inputs(&self) -> usize {
+        self.ic.len() - 1
+    }
+
+    /// Rust source declaring the key as a groth16-solana `Groth16Verifyingkey` constant.
+    pub fn to_rust_source(&self, name: &str) -> String {
+        fn array(bytes: &[u8]) -> String {
+            let items: Vec<String> = bytes.iter().map(|b| b.to_string()).collect();
+            format!("[{}]", items.join(", "))
+        }
+
+        let mut source = String::new();
+        writeln!(source, "use groth16_solana::groth16::Groth16Verifyingkey;").unwrap();
+        writeln!(source).unwrap();
+        writeln!(source, "pub const {}: Groth16Verifyingkey = Groth16Verifyingkey {{", name).unwrap();
+        writeln!(source, "    nr_pubinputs: {},", self.public_inputs()).unwrap();
+        writeln!(source, "    vk_alpha_g1: {},", array(&self.alpha_g1)).unwrap();
+        writeln!(source, "    vk_beta_g2: {},", array(&self.beta_g2)).unwrap();
+        // sic, the field is spelled this way in groth16-solana
+        writeln!(source, "    vk_gamme_g2: {},", array(&self.gamma_g2)).unwrap();
+        writeln!(source, "    vk_delta_g2: {},", array(&self.delta_g2)).unwrap();
+        writeln!(source, "    vk_ic: &[").unwrap();
+        for point in &self.ic {
+            writeln!(source, "        {},", array(point)).unwrap();
+        }
+        writeln!(source, "    ],").unwrap();
+        writeln!(source, "}};").unwrap();
+        source
+    }
+}
+
+/// A proof as passed to groth16-solana's `Groth16Verifier::new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SolanaProof {
+    /// `-A`, negated so the program doesn't have to.
+    pub a: [u8; 64],
+    pub b: [u8; 128],
+    pub c: [u8; 64],
+}
+
+impl SolanaProof {
+    pub fn from_proof(proof: &Proof<Bn254>) -> Self {
+        Self {
+            a: g1_to_bytes(&(-proof.a)),
+            b: g2_to_bytes(&proof.b),
+            c: g1_to_bytes(&proof.c),
+        }
+    }
+
+    /// Converts a proof as created by `ZKProver::create_proof`.
+    pub fn from_compressed(proof_bytes: &[u8]) -> Result<Self, SolanaExportError> {
+        let proof = Proof::<Bn254>::deserialize_compressed(proof_bytes)
+            .map_err(|_| SolanaExportError::MalformedProof)?;
+        Ok(Self::from_proof(&proof))
+    }
+}
+
+/// The check groth16-solana runs on chain, built from the same alt_bn128 operations the
+/// syscalls provide, for testing exported keys and proofs off chain.
+pub fn verify(vk: &SolanaVerifyingKey, proof: &SolanaProof, public_inputs: &[[u8; 32]]) -> Result<bool, SolanaExportError> {
+    if public_inputs.len() != vk.public_inputs() {
+        return Err(SolanaExportError::WrongInputCount {
+            expected: vk.public_inputs(),
+            actual: public_inputs.len(),
+        });
+    }
+
+    let mut prepared = vk.ic[0].to_vec();
+    for (index, (input, ic)) in public_inputs.iter().zip(&vk.ic[1..]).enumerate() {
+        // the multiplication syscall doesn't reduce scalars, so non-canonical inputs
+        // would alias other inputs
+        let canonical = Fr::from_be_bytes_mod_order(input).into_bigint().to_bytes_be();
+        if canonical[..] != input[..] {
+            return Err(SolanaExportError::InputOutOfField(index));
+        }
+        let product = alt_bn128_multiplication(&[&ic[..], &input[..]].concat())
+            .map_err(|_| SolanaExportError::Syscall)?;
+        prepared = alt_bn128_addition(&[&prepared[..], &product[..]].concat())
+            .map_err(|_| SolanaExportError::Syscall)?;
+    }
+
+    let pairing_input = [
+        &proof.a[..],
+        &proof.b[..],
+        &prepared[..],
+        &vk.gamma_g2[..],
+        &proof.c[..],
+        &vk.delta_g2[..],
+        &vk.alpha_g1[..],
+        &vk.beta_g2[..],
+    ]
+    .concat();
+    let result = alt_bn128_pairing(&pairing_input).map_err(|_| SolanaExportError::Syscall)?;
+    Ok(result.last() == Some(&1))
+}
+
+/// Hex rendering of a [`SolanaVerifyingKey`], as served by the API.
+#[derive(Debug, Serialize)]
+pub struct SolanaVerifyingKeyHex {
+    pub nr_pubinputs: usize,
+    pub vk_alpha_g1: String,
+    pub vk_beta_g2: String,
+    pub vk_gamma_g2: String,
+    pub vk_delta_g2: String,
+    pub vk_ic: Vec<String>,
+}
+
+impl From<&SolanaVerifyingKey> for SolanaVerifyingKeyHex {
+    fn from(vk: &SolanaVerifyingKey) -> Self {
+        Self {
+            nr_pubinputs: vk.public_inputs(),
+            vk_alpha_g1: hex::encode(vk.alpha_g1),
+            vk_beta_g2: hex::encode(vk.beta_g2),
+            vk_gamma_g2: hex::encode(vk.gamma_g2),
+            vk_delta_g2: hex::encode(vk.delta_g2),
+            vk_ic: vk.ic.iter().map(hex::encode).collect(),
+        }
+    }
+}
+
+/// Hex rendering of a [`SolanaProof`] and its public inputs.
+#[derive(Debug, Serialize)]
+pub struct SolanaProofHex {
+    pub proof_a: String,
+    pub proof_b: String,
+    pub proof_c: String,
+    pub public_inputs: Vec<String>,
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App};
+use ark_bn254::Fr;
+use base64::Engine;
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use soulana_backend::config::SolanaCluster;
+use soulana_backend::repos::ProjectRepo;
+use soulana_backend::routes::projects::{self, ANONYMOUS_DONATION_ACTION};
+use soulana_backend::schema::{donations, project_beneficiaries, projects as projects_table};
+use soulana_backend::services::cache::ProjectCache;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::donations::{record_confirmed_donation, AnonymousDonor, DonationError, SignatureKey};
+use soulana_backend::services::membership::MembershipGroup;
+use soulana_backend::services::wallet_signatures;
+use soulana_backend::zk::field::fr_to_hex;
+use soulana_backend::zk::mock::MockZKVerifier;
+use soulana_backend::zk::ZKVerifier;
+
+mod common;
+use common::{test_pool, unreachable_pool};
+
+#[actix_web::test]
+async fn anonymous_donations_need_a_membership_proof_and_the_donor_wallet() {
+    let mock = Arc::new(MockZKVerifier::new());
+    mock.set_proof_valid("forged", false);
+    let verifier: Arc<dyn ZKVerifier> = mock.clone();
+    let group = MembershipGroup::new(4);
+    let root = fr_to_hex(&group.root());
+    let challenges = web::Data::new(ChallengeStore::new(Duration::from_secs(300)));
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(ProjectCache::<Value>::new(Duration::from_secs(60))))
+            .app_data(web::Data::new(SolanaCluster::Localnet))
+            .app_data(web::Data::new(group))
+            .app_data(challenges.clone())
+            .app_data(web::Data::from(verifier))
+            .service(web::scope("/api/projects").configure(projects::projects_config)),
+    )
+    .await;
+
+    let donor = Keypair::new();
+    let other_member = Keypair::new();
+    let signature = Signature::from([7u8; 64]).to_string();
+    let unknown_root = fr_to_hex(&Fr::from(12345u64));
+    let nullifier_hash = fr_to_hex(&Fr::from(1u64));
+    let record = |proof: &str, root: &str, nullifier_hash: &str, signer: &Keypair| {
+        let donor_wallet = donor.pubkey().to_string();
+        let (challenge, _) = challenges.issue(&donor_wallet, "test").unwrap();
+        let message = wallet_signatures::message(ANONYMOUS_DONATION_ACTION, &challenge);
+        TestRequest::post()
+            .uri("/api/projects/1/donations")
+            .set_json(json!({
+                "signature": signature,
+                "anonymous": {
+                    "proof": proof,
+                    "root": root,
+                    "nullifier_hash": nullifier_hash,
+                    "donor_wallet": donor_wallet,
+                    "challenge": fr_to_hex(&challenge),
+                    "wallet_signature": signer.sign_message(message.as_bytes()).to_string(),
+                },
+            }))
+            .to_request()
+    };
+
+    let cases = [
+        ("proof", "not hex", nullifier_hash.as_str(), &donor, StatusCode::BAD_REQUEST),
+        ("proof", root.as_str(), "not hex", &donor, StatusCode::BAD_REQUEST),
+        ("proof", unknown_root.as_str(), nullifier_hash.as_str(), &donor, StatusCode::BAD_REQUEST),
+        ("forged", root.as_str(), nullifier_hash.as_str(), &donor, StatusCode::UNAUTHORIZED),
+        // another member can prove membership but can't sign for the donor's wallet
+        ("proof", root.as_str(), nullifier_hash.as_str(), &other_member, StatusCode::UNAUTHORIZED),
+        // a valid proof and signature get as far as looking up the transaction
+        ("proof", root.as_str(), nullifier_hash.as_str(), &donor, StatusCode::BAD_GATEWAY),
+    ];
+    for (proof, root, nullifier_hash, signer, status) in cases {
+        let resp = call_service(&app, record(proof, root, nullifier_hash, signer)).await;
+        assert_eq!(resp.status(), status, "{} {} {}", proof, root, nullifier_hash);
+    }
+}
+
+/// A confirmed transaction in which `donor` sends `lamports` to `to`, as `getTransaction`
+/// answers it.
+fn confirmed_transfer(donor: &Keypair, to: &Pubkey, lamports: u64) -> EncodedConfirmedTransactionWithStatusMeta {
+    let transaction = Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(&donor.pubkey(), to, lamports)],
+        Some(&donor.pubkey()),
+        &[donor],
+        Default::default(),
+    );
+    let encoded = base64::engine::general_purpose::STANDARD.encode(bincode::serialize(&transaction).unwrap());
+    serde_json::from_value(json!({
+        "slot": 42,
+        "blockTime": 1_710_000_000,
+        "transaction": [encoded, "base64"],
+        "meta": {
+            "err": null,
+            "status": { "Ok": null },
+            "fee": 5000,
+            "preBalances": [],
+            "postBalances": [],
+        },
+    }))
+    .unwrap()
+}
+
+#[actix_web::test]
+async fn members_cannot_claim_someone_elses_donation() {
+    let Some(pool) = test_pool() else { return };
+    let mut conn = pool.get().unwrap();
+    let project_id: i32 = projects_table::table.select(projects_table::id).first(&mut conn).unwrap();
+    let project_wallet = Pubkey::new_unique();
+    diesel::update(projects_table::table.find(project_id))
+        .set(projects_table::wallet.eq(project_wallet.to_string()))
+        .execute(&mut conn)
+        .unwrap();
+    diesel::delete(project_beneficiaries::table.filter(project_beneficiaries::project_id.eq(project_id)))
+        .execute(&mut conn)
+        .unwrap();
+
+    let donor = Keypair::new();
+    let other_member = Keypair::new();
+    let confirmed = confirmed_transfer(&donor, &project_wallet, 1_000_000);
+    let signature = Signature::from([9u8; 64]);
+    let key = SignatureKey::new("test_key");
+
+    // a second member signed with their own wallet, which didn't send the donation
+    let claimed = record_confirmed_donation(
+        &mut conn,
+        &confirmed,
+        &key,
+        project_id,
+        &signature,
+        Some(AnonymousDonor { nullifier_hash: Fr::from(2u64), wallet: other_member.pubkey() }),
+    );
+    assert!(matches!(claimed, Err(DonationError::NotTheDonor)));
+    let recorded: i64 = donations::table
+        .filter(donations::project_id.eq(project_id))
+        .count()
+        .get_result(&mut conn)
+        .unwrap();
+    assert_eq!(recorded, 0);
+
+    // so the donor can still record it
+    let donation = record_confirmed_donation(
+        &mut conn,
+        &confirmed,
+        &key,
+        project_id,
+        &signature,
+        Some(AnonymousDonor { nullifier_hash: Fr::from(1u64), wallet: donor.pubkey() }),
+    )
+    .unwrap();
+    assert!(donation.verified_anonymous);
+    assert_eq!(donation.donor_wallet, None);
+    assert_eq!(donation.amount_lamports, 1_000_000);
+    assert_eq!(donation.donor_nullifier, Some(fr_to_hex(&Fr::from(1u64))));
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+use actix_web::{http::header, http::StatusCode, middleware, test, web, App, HttpResponse, ResponseError};
+use serde_json::Value;
+use soulana_backend::error::{self, AppError, REQUEST_ID_HEADER};
+use soulana_backend::repos::{ProjectRepo, UserRepo};
+use soulana_backend::routes::{blink_chain, projects, users};
+use soulana_backend::services::cache::ProjectCache;
+use soulana_backend::services::donations::SignatureKey;
+use soulana_backend::services::membership::MembershipError;
+use soulana_backend::zk::error::ZkError;
+
+mod common;
+use common::unreachable_pool;
+
+async fn missing_project() -> Result<HttpResponse, AppError> {
+    Err(AppError::not_found("Project not found"))
+}
+
+#[actix_web::test]
+async fn errors_map_to_status_and_code() {
+    let cases = [
+        (AppError::validation("Invalid project ID"), StatusCode::BAD_REQUEST, "invalid_request"),
+        (AppError::BadRequest("Json deserialize error".to_string()), StatusCode::BAD_REQUEST, "bad_request"),
+        (AppError::unauthorized("A valid auth token is required"), StatusCode::UNAUTHORIZED, "unauthorized"),
+        (AppError::not_found("Project not found"), StatusCode::NOT_FOUND, "not_found"),
+        (AppError::conflict("Pledge reminder already fulfilled"), StatusCode::CONFLICT, "conflict"),
+        (AppError::Db(diesel::result::Error::BrokenTransactionManager), StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
+        (AppError::Zk(ZkError::PairingCheckFailed), StatusCode::UNAUTHORIZED, "invalid_proof"),
+        (AppError::Zk(ZkError::MalformedProof), StatusCode::BAD_REQUEST, "malformed_proof"),
+        (AppError::forbidden("Commitment was not issued by this server"), StatusCode::FORBIDDEN, "forbidden"),
+        (MembershipError::NullifierUsed.into(), StatusCode::CONFLICT, "conflict"),
+        (MembershipError::UnknownIdentity.into(), StatusCode::NOT_FOUND, "not_found"),
+        (MembershipError::GroupFull.into(), StatusCode::SERVICE_UNAVAILABLE, "unavailable"),
+    ];
+    for (error, status, code) in cases {
+        assert_eq!(error.status_code(), status, "{}", error);
+        assert_eq!(error.code(), code);
+    }
+
+    // internal details stay in the logs
+    let error = AppError::Internal("Invalid beneficiary configuration: shares add up to 9000".to_string());
+    assert_eq!(error.message(), "Internal server error");
+    assert_eq!(AppError::Zk(ZkError::Proving("unsatisfiable".to_string())).message(), "Failed to create proof");
+}
+
+#[actix_web::test]
+async fn error_bodies_carry_the_request_id() {
+    let app = test::init_service(
+        App::new()
+            .wrap(middleware::from_fn(error::request_id))
+            .route("/missing", web::get().to(missing_project)),
+    )
+    .await;
+
+    let resp = test::call_service(&app, test::TestRequest::get().uri("/missing").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    let header = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["message"], "Project not found");
+    assert_eq!(body["request_id"], header);
+
+    // an id set by the client or a proxy is kept, an unusable one is replaced
+    let req = test::TestRequest::get()
+        .uri("/missing")
+        .insert_header((REQUEST_ID_HEADER, "edge-42"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "edge-42");
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["request_id"], "edge-42");
+
+    let req = test::TestRequest::get()
+        .uri("/missing")
+        .insert_header((REQUEST_ID_HEADER, "x".repeat(500)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_ne!(body["request_id"], "x".repeat(500));
+    assert!(!body["request_id"].as_str().unwrap().is_empty());
+}
+
+#[actix_web::test]
+async fn handlers_answer_with_app_errors() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(UserRepo::new(unreachable_pool())))
+            .wrap(middleware::from_fn(error::request_id))
+            .service(web::scope("/api/blink-chain").configure(blink_chain::blink_chain_config))
+            .service(web::scope("/api/users").configure(users::users_config)),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/api/blink-chain?id=seven").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "invalid_request");
+    assert_eq!(body["message"], "Invalid project ID");
+
+    let req = test::TestRequest::get().uri("/api/users/users").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "database_unavailable");
+    assert_eq!(body["message"], "Database connection error");
+}
+
+#[actix_web::test]
+async fn malformed_requests_answer_with_app_errors() {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(ProjectCache::<Value>::new(Duration::from_secs(60))))
+            .app_data(web::Data::new(UserRepo::new(unreachable_pool())))
+            .wrap(middleware::from_fn(error::request_id))
+            .configure(error::configure_extractors)
+            .service(web::scope("/api/projects").configure(projects::projects_config))
+            .service(web::scope("/api/users").configure(users::users_config)),
+    )
+    .await;
+
+    let requests = [
+        test::TestRequest::post()
+            .uri("/api/users/users")
+            .insert_header(header::ContentType::json())
+            .set_payload("{\"name\": "),
+        test::TestRequest::get().uri("/api/projects/seven/stats"),
+        test::TestRequest::get().uri("/api/projects/1/donors?limit=many"),
+    ];
+    for req in requests {
+        let resp = test::call_service(&app, req.to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let header = resp.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap().to_string();
+        let body: Value = test::read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+        assert!(!body["message"].as_str().unwrap().is_empty());
+        assert_eq!(body["request_id"], header);
+    }
+}
@@ -0,0 +1,383 @@
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::{http::header::HeaderName, http::StatusCode, test, web, App};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use serde_json::{json, Value};
+use soulana_backend::client_ip::ClientIpHeader;
+use soulana_backend::routes::auth::auth_routes;
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::challenges::{ChallengeError, ChallengeStore};
+use soulana_backend::zk::circuits::CircuitId;
+use soulana_backend::zk::field::{fr_from_hex, fr_to_hex, public_inputs};
+use soulana_backend::zk::mock::MockZKVerifier;
+use soulana_backend::zk::{ZKProverBackend, ZKVerifier};
+
+const WALLET: &str = "F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW";
+const EMAIL: &str = "donor@example.com";
+
+fn auth_service() -> web::Data<AuthService> {
+    // the auth routes never touch the database, so the pool is never connected
+    let manager = ConnectionManager::<PgConnection>::new("postgres://localhost/unused");
+    let pool = Pool::builder().build_unchecked(manager);
+    web::Data::new(AuthService::new(pool, "test_secret".to_string()))
+}
+
+/// Public inputs the server expects for a login as `identity` with `challenge`.
+fn inputs(identity: &str, challenge: &str) -> Value {
+    let challenge = fr_from_hex(challenge).unwrap();
+    json!(public_inputs(identity, challenge).iter().map(fr_to_hex).collect::<Vec<_>>())
+}
+
+macro_rules! app {
+    ($mock:expr) => {
+        app!($mock, Duration::from_secs(300))
+    };
+    ($mock:expr, $challenge_ttl:expr) => {{
+        let verifier: Arc<dyn ZKVerifier> = $mock.clone();
+        let prover: Arc<dyn ZKProverBackend> = $mock.clone();
+        test::init_service(
+            App::new()
+                .app_data(auth_service())
+                .app_data(web::Data::new(ChallengeStore::new($challenge_ttl)))
+                .app_data(web::Data::from(verifier))
+                .app_data(web::Data::from(prover))
+                .service(auth_routes()),
+        )
+        .await
+    }};
+}
+
+macro_rules! challenge {
+    ($app:expr, $identity:expr) => {{
+        let req = test::TestRequest::post()
+            .uri("/auth/zk/challenge")
+            .set_json(json!({ "identity": $identity }))
+            .to_request();
+        let body: Value = test::call_and_read_body_json(&$app, req).await;
+        body["challenge"].as_str().unwrap().to_string()
+    }};
+}
+
+#[actix_web::test]
+async fn wallet_auth_returns_identity() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/wallet")
+        .set_json(json!({ "wallet_address": WALLET }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+
+    assert_eq!(body["wallet_address"], WALLET);
+}
+
+#[actix_web::test]
+async fn wallet_zk_auth_issues_token_for_valid_proof() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let challenge = challenge!(app, WALLET);
+    let proof = json!({ "proof": "valid", "public_inputs": inputs(WALLET, &challenge) });
+
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({ "wallet_address": WALLET, "challenge": challenge, "zk_proof": proof }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert!(body["token"].as_str().is_some_and(|t| !t.is_empty()));
+    assert!(body["user_id"].as_str().is_some());
+}
+
+#[actix_web::test]
+async fn wallet_zk_auth_rejects_replayed_proof() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let challenge = challenge!(app, WALLET);
+    let login = json!({
+        "wallet_address": WALLET,
+        "challenge": challenge,
+        "zk_proof": { "proof": "captured", "public_inputs": inputs(WALLET, &challenge) }
+    });
+
+    let req = test::TestRequest::post().uri("/auth/zk/wallet").set_json(&login).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post().uri("/auth/zk/wallet").set_json(&login).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn wallet_zk_auth_rejects_foreign_or_expired_challenge() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    // issued for another identity
+    let challenge = challenge!(app, EMAIL);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({
+            "wallet_address": WALLET,
+            "challenge": challenge,
+            "zk_proof": { "proof": "any", "public_inputs": inputs(WALLET, &challenge) }
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let app = app!(mock, Duration::ZERO);
+    let challenge = challenge!(app, WALLET);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({
+            "wallet_address": WALLET,
+            "challenge": challenge,
+            "zk_proof": { "proof": "any", "public_inputs": inputs(WALLET, &challenge) }
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn wallet_zk_auth_rejects_invalid_proof() {
+    let mock = Arc::new(MockZKVerifier::new());
+    mock.set_proof_valid("forged", false);
+    let app = app!(mock);
+
+    let challenge = challenge!(app, WALLET);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({
+            "wallet_address": WALLET,
+            "challenge": challenge,
+            "zk_proof": { "proof": "forged", "public_inputs": inputs(WALLET, &challenge) }
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["message"], "ZK proof verification failed");
+    assert_eq!(body["code"], "invalid_proof");
+}
+
+#[actix_web::test]
+async fn email_zk_auth_issues_token_for_valid_proof() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let challenge = challenge!(app, EMAIL);
+    let proof = json!({ "proof": "valid", "public_inputs": inputs(EMAIL, &challenge) });
+
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/email")
+        .set_json(json!({ "email": EMAIL, "challenge": challenge, "zk_proof": proof }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn email_zk_auth_rejects_invalid_proof() {
+    let mock = Arc::new(MockZKVerifier::new());
+    mock.set_proof_valid("forged", false);
+    let app = app!(mock);
+
+    let challenge = challenge!(app, EMAIL);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/email")
+        .set_json(json!({
+            "email": EMAIL,
+            "challenge": challenge,
+            "zk_proof": { "proof": "forged", "public_inputs": inputs(EMAIL, &challenge) }
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn unknown_circuits_are_rejected() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let challenge = challenge!(app, WALLET);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({
+            "wallet_address": WALLET,
+            "challenge": challenge,
+            "zk_proof": { "proof": "any", "public_inputs": [], "circuit_id": "not-a-circuit" }
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn created_proofs_carry_public_inputs_and_circuit_id() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+
+    let challenge = challenge!(app, WALLET);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/create-proof")
+        .set_json(json!({ "wallet_address": WALLET, "challenge": challenge }))
+        .to_request();
+    let proof: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(proof["public_inputs"], inputs(WALLET, &challenge));
+    assert_eq!(proof["circuit_id"], CircuitId::current().to_string());
+
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/wallet")
+        .set_json(json!({ "wallet_address": WALLET, "challenge": challenge, "zk_proof": proof }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let challenge = challenge!(app, EMAIL);
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/create-email-proof")
+        .set_json(json!({ "email": EMAIL, "challenge": challenge }))
+        .to_request();
+    let proof: Value = test::call_and_read_body_json(&app, req).await;
+    assert!(proof["proof"].as_str().is_some_and(|p| !p.is_empty()));
+    assert_eq!(proof["public_inputs"], inputs(EMAIL, &challenge));
+}
+
+#[actix_web::test]
+async fn challenges_are_limited_per_client() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(auth_service())
+            .app_data(web::Data::new(ChallengeStore::with_limits(Duration::from_secs(300), 100, 2)))
+            .app_data(web::Data::from(mock as Arc<dyn ZKVerifier>))
+            .service(auth_routes()),
+    )
+    .await;
+
+    let request = |ip: &str| {
+        test::TestRequest::post()
+            .uri("/auth/zk/challenge")
+            .peer_addr(format!("{}:4000", ip).parse().unwrap())
+            .set_json(json!({ "identity": WALLET }))
+            .to_request()
+    };
+    for _ in 0..2 {
+        assert_eq!(test::call_service(&app, request("10.0.0.1")).await.status(), StatusCode::OK);
+    }
+    let resp = test::call_service(&app, request("10.0.0.1")).await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "too_many_requests");
+
+    // other clients aren't affected
+    assert_eq!(test::call_service(&app, request("10.0.0.2")).await.status(), StatusCode::OK);
+
+    // and without a configured header clients can't pick their address
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/challenge")
+        .peer_addr("10.0.0.1:4000".parse().unwrap())
+        .insert_header(("x-forwarded-for", "10.0.0.3"))
+        .set_json(json!({ "identity": WALLET }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn challenges_are_limited_per_client_behind_a_proxy() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(auth_service())
+            .app_data(web::Data::new(ChallengeStore::with_limits(Duration::from_secs(300), 100, 1)))
+            .app_data(web::Data::new(ClientIpHeader(Some(HeaderName::from_static("x-forwarded-for")))))
+            .app_data(web::Data::from(mock as Arc<dyn ZKVerifier>))
+            .service(auth_routes()),
+    )
+    .await;
+
+    // every request comes from the proxy, which appends the address it saw
+    let request = |forwarded_for: &str| {
+        test::TestRequest::post()
+            .uri("/auth/zk/challenge")
+            .peer_addr("10.0.0.254:4000".parse().unwrap())
+            .insert_header(("x-forwarded-for", forwarded_for))
+            .set_json(json!({ "identity": WALLET }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, request("203.0.113.1")).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, request("203.0.113.2")).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, request("203.0.113.1")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    // an address the client put in front of the proxy's is ignored
+    let spoofed = request("198.51.100.7, 203.0.113.1");
+    assert_eq!(test::call_service(&app, spoofed).await.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[actix_web::test]
+async fn challenge_store_is_bounded_and_evicts_expired_challenges() {
+    let store = ChallengeStore::with_limits(Duration::from_secs(300), 2, 10);
+    store.issue(WALLET, "a").unwrap();
+    let (challenge, _) = store.issue(WALLET, "b").unwrap();
+    assert_eq!(store.issue(WALLET, "c"), Err(ChallengeError::Full));
+
+    // using a challenge frees its slot
+    assert!(store.consume(&challenge, WALLET));
+    store.issue(WALLET, "c").unwrap();
+
+    // issuing only drops the expired challenges, outstanding ones keep counting
+    let store = ChallengeStore::with_limits(Duration::from_millis(200), 3, 10);
+    store.issue(WALLET, "a").unwrap();
+    std::thread::sleep(Duration::from_millis(250));
+    store.issue(WALLET, "a").unwrap();
+    store.issue(WALLET, "a").unwrap();
+    store.issue(WALLET, "a").unwrap();
+    assert_eq!(store.issue(WALLET, "a"), Err(ChallengeError::Full));
+
+    let store = ChallengeStore::with_limits(Duration::ZERO, 1, 1);
+    store.issue(WALLET, "a").unwrap();
+    // the expired challenge is dropped rather than counted
+    store.issue(WALLET, "a").unwrap();
+}
+
+#[actix_web::test]
+async fn wallet_zk_auth_rejects_mismatched_public_inputs() {
+    let mock = Arc::new(MockZKVerifier::new());
+    let app = app!(mock);
+    let challenge = challenge!(app, WALLET);
+
+    let login = |public_inputs: Value| {
+        test::TestRequest::post()
+            .uri("/auth/zk/wallet")
+            .set_json(json!({
+                "wallet_address": WALLET,
+                "challenge": challenge,
+                "zk_proof": { "proof": "any", "public_inputs": public_inputs }
+            }))
+            .to_request()
+    };
+
+    let cases = [
+        // inputs of a proof for another identity
+        (inputs(EMAIL, &challenge), "public_inputs[0] (identity_hash) is", "public_input_mismatch"),
+        (json!([inputs(WALLET, &challenge)[0], "0".repeat(64)]), "public_inputs[1] (challenge) is", "public_input_mismatch"),
+        (json!([inputs(WALLET, &challenge)[0]]), "Expected 2 public inputs", "public_input_count"),
+        (json!(["f".repeat(64), challenge]), "not in the BN254 scalar field", "malformed_public_input"),
+        (json!(["0xabc", challenge]), "64 lowercase hex digits", "malformed_public_input"),
+    ];
+    for (public_inputs, message, code) in cases {
+        let resp = test::call_service(&app, login(public_inputs)).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains(message), "{}", body["message"]);
+        assert_eq!(body["code"], code);
+    }
+
+    // rejected inputs don't consume the challenge
+    let resp = test::call_service(&app, login(inputs(WALLET, &challenge))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
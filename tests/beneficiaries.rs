@@ -0,0 +1,111 @@
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use soulana_backend::repos::ProjectRepo;
+use soulana_backend::routes::blink_chain::split::{split_lamports, SplitError};
+use soulana_backend::routes::projects;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::donations::SignatureKey;
+use soulana_backend::services::wallet_signatures::{self, WalletSignatureError};
+use soulana_backend::zk::field::fr_to_hex;
+
+mod common;
+use common::unreachable_pool;
+
+/// Name, lamports, shares and the expected parts.
+type SplitCase = (&'static str, u64, Vec<(Pubkey, i32)>, Vec<(Pubkey, u64)>);
+
+#[test]
+fn split_lamports_table() {
+    let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let cases: Vec<SplitCase> = vec![
+        ("single beneficiary takes everything", 1_000_000_001, vec![(a, 10_000)], vec![(a, 1_000_000_001)]),
+        ("even split", 1_000, vec![(a, 5_000), (b, 5_000)], vec![(a, 500), (b, 500)]),
+        // 10 * 0.3333 = 3.333, each rounds down to 3 and the 1 lamport of dust goes to `b`
+        ("dust goes to the largest share", 10, vec![(a, 3_333), (b, 3_334), (c, 3_333)], vec![(a, 3), (b, 4), (c, 3)]),
+        ("ties go to the first largest share", 3, vec![(a, 2_500), (b, 5_000), (c, 2_500)], vec![(b, 3)]),
+        ("zero lamports transfers nothing", 0, vec![(a, 7_000), (b, 3_000)], vec![]),
+        ("parts rounding to zero are dropped", 1, vec![(a, 1), (b, 9_999)], vec![(b, 1)]),
+    ];
+    for (name, lamports, shares, expected) in cases {
+        let parts = split_lamports(lamports, &shares).unwrap();
+        assert_eq!(parts, expected, "{}", name);
+        assert_eq!(parts.iter().map(|(_, part)| part).sum::<u64>(), lamports, "{}", name);
+    }
+}
+
+#[test]
+fn split_lamports_rejects_bad_shares() {
+    let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    assert_eq!(split_lamports(10, &[]), Err(SplitError::NoBeneficiaries));
+    assert_eq!(split_lamports(10, &[(a, 0), (b, 10_000)]), Err(SplitError::InvalidShare(0)));
+    assert_eq!(split_lamports(10, &[(a, 5_000), (b, 4_000)]), Err(SplitError::InvalidTotal(9_000)));
+}
+
+#[test]
+fn wallet_signatures_are_bound_to_wallet_action_and_challenge() {
+    let keypair = Keypair::new();
+    let wallet = keypair.pubkey().to_string();
+    let challenges = ChallengeStore::new(Duration::from_secs(60));
+    let (challenge, _) = challenges.issue(&wallet, "client").unwrap();
+    let message = wallet_signatures::message("set beneficiaries", &challenge);
+    let signature = keypair.sign_message(message.as_bytes()).to_string();
+
+    assert!(wallet_signatures::is_signed_by(&wallet, &message, &signature));
+    assert!(!wallet_signatures::is_signed_by(&Pubkey::new_unique().to_string(), &message, &signature));
+
+    let hex = fr_to_hex(&challenge);
+    assert!(matches!(
+        wallet_signatures::authorize(&challenges, &wallet, "another action", &hex, &signature),
+        Err(WalletSignatureError::InvalidSignature)
+    ));
+    wallet_signatures::authorize(&challenges, &wallet, "set beneficiaries", &hex, &signature).unwrap();
+    assert!(matches!(
+        wallet_signatures::authorize(&challenges, &wallet, "set beneficiaries", &hex, &signature),
+        Err(WalletSignatureError::InvalidChallenge)
+    ));
+}
+
+#[actix_web::test]
+async fn beneficiaries_are_validated_before_they_are_written() {
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(ChallengeStore::new(Duration::from_secs(60))))
+            .service(web::scope("/api/projects").configure(projects::projects_config)),
+    )
+    .await;
+    let (a, b) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+
+    let cases = [
+        (json!([{ "wallet": a, "share_bps": 6_000 }, { "wallet": b, "share_bps": 3_000 }]), "sum to 10000"),
+        (json!([{ "wallet": a, "share_bps": 10_000 }, { "wallet": a, "share_bps": 0 }]), "Duplicate"),
+        (json!([{ "wallet": "not-a-wallet", "share_bps": 10_000 }]), "Invalid beneficiary wallet"),
+        (json!([]), "no beneficiaries"),
+    ];
+    for (beneficiaries, message) in cases {
+        let req = TestRequest::put()
+            .uri("/api/projects/1/beneficiaries")
+            .set_json(json!({ "challenge": "00", "signature": "sig", "beneficiaries": beneficiaries }))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST, "{}", message);
+        let body: Value = read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains(message), "{}", body);
+    }
+
+    // valid shares get as far as looking up the project wallet
+    let req = TestRequest::put()
+        .uri("/api/projects/1/beneficiaries")
+        .set_json(json!({
+            "challenge": "00",
+            "signature": "sig",
+            "beneficiaries": [{ "wallet": a, "share_bps": 7_000 }, { "wallet": b, "share_bps": 3_000 }]
+        }))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
@@ -0,0 +1,54 @@
+//! Database pools shared by the test suites.
+
+// every suite uses only some of these
+#![allow(dead_code)]
+
+use std::sync::OnceLock;
+use std::time::Duration;
+use diesel::r2d2::{self, ConnectionManager, CustomizeConnection, Pool};
+use diesel::{Connection, PgConnection};
+use soulana_backend::{migrations, DbPool};
+
+/// A pool for an address nothing listens on, so checking out a connection fails once the
+/// timeout passes.
+pub fn unreachable_pool() -> DbPool {
+    let manager = ConnectionManager::<PgConnection>::new("postgres://127.0.0.1:1/unused");
+    Pool::builder()
+        .connection_timeout(Duration::from_millis(200))
+        .build_unchecked(manager)
+}
+
+/// A pool on the database at `TEST_DATABASE_URL` with the migrations applied, `None` when
+/// the variable isn't set.
+///
+/// The pool has a single connection that runs everything in a transaction that is rolled
+/// back when the pool is dropped, so tests don't see each other's rows and leave nothing
+/// behind.
+pub fn test_pool() -> Option<DbPool> {
+    let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+        eprintln!("TEST_DATABASE_URL not set, skipping");
+        return None;
+    };
+
+    static MIGRATED: OnceLock<()> = OnceLock::new();
+    MIGRATED.get_or_init(|| {
+        let mut conn = PgConnection::establish(&url).expect("TEST_DATABASE_URL is not reachable");
+        migrations::run_pending(&mut conn).expect("failed to migrate the test database");
+    });
+
+    let pool = Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(TestTransaction))
+        .build(ConnectionManager::<PgConnection>::new(url))
+        .expect("TEST_DATABASE_URL is not reachable");
+    Some(pool)
+}
+
+#[derive(Debug)]
+struct TestTransaction;
+
+impl CustomizeConnection<PgConnection, r2d2::Error> for TestTransaction {
+    fn on_acquire(&self, conn: &mut PgConnection) -> Result<(), r2d2::Error> {
+        conn.begin_test_transaction().map_err(r2d2::Error::QueryError)
+    }
+}
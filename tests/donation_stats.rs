@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_sdk::signature::Signature;
+use soulana_backend::repos::ProjectRepo;
+use soulana_backend::routes::projects::models::{DailyTotal, DonorTotal, NewDonation};
+use soulana_backend::schema::{donations, projects};
+use soulana_backend::services::donations::{fetch_transaction, DonationError, SignatureKey};
+use soulana_backend::DbPool;
+use uuid::Uuid;
+
+mod common;
+use common::test_pool;
+
+const ALICE: &str = "A1ice11111111111111111111111111111111111111";
+const BOB: &str = "Bob1111111111111111111111111111111111111111";
+const CAROL: &str = "Caro11111111111111111111111111111111111111";
+
+fn at(day: u32, hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 3, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+}
+
+/// A project seeded by the migrations, with the repo to query it through.
+fn project(pool: &DbPool) -> (ProjectRepo, i32) {
+    let project_id = projects::table.select(projects::id).first(&mut pool.get().unwrap()).unwrap();
+    (ProjectRepo::new(pool.clone(), SignatureKey::new("test_key")), project_id)
+}
+
+/// Records a donation from `donor_wallet`, or an anonymous one with `donor_nullifier`
+/// when there is no wallet.
+fn donate(
+    pool: &DbPool,
+    project_id: i32,
+    donor_wallet: Option<&str>,
+    donor_nullifier: Option<&str>,
+    amount_lamports: i64,
+    confirmed_at: NaiveDateTime,
+) {
+    let signature = donor_wallet.map(|_| Uuid::new_v4().to_string());
+    diesel::insert_into(donations::table)
+        .values(NewDonation {
+            project_id,
+            donor_wallet: donor_wallet.map(str::to_string),
+            amount_lamports,
+            signature: signature.clone(),
+            slot: signature.as_ref().map(|_| 1),
+            confirmed_at,
+            signature_hash: Uuid::new_v4().to_string(),
+            verified_anonymous: donor_wallet.is_none(),
+            donor_nullifier: donor_nullifier.map(str::to_string),
+        })
+        .execute(&mut pool.get().unwrap())
+        .unwrap();
+}
+
+#[actix_web::test]
+async fn stats_are_zero_without_donations() {
+    let Some(pool) = test_pool() else { return };
+    let (projects, project_id) = project(&pool);
+
+    let (totals, daily) = projects.stats(project_id).await.unwrap().unwrap();
+    assert_eq!(totals.total_lamports, 0);
+    assert_eq!(totals.donation_count, 0);
+    assert_eq!(totals.donor_count, 0);
+    assert_eq!(totals.average_lamports, 0.0);
+    assert_eq!(totals.median_lamports, 0.0);
+    assert!(daily.is_empty());
+    assert!(projects.top_donors(project_id, 10).await.unwrap().unwrap().is_empty());
+    assert!(projects.stats(-1).await.unwrap().is_none());
+    assert!(projects.top_donors(-1, 10).await.unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn stats_sum_totals_donors_and_days() {
+    let Some(pool) = test_pool() else { return };
+    let (projects, project_id) = project(&pool);
+    donate(&pool, project_id, Some(ALICE), None, 100, at(1, 9));
+    donate(&pool, project_id, Some(ALICE), None, 300, at(1, 18));
+    donate(&pool, project_id, Some(BOB), None, 200, at(2, 12));
+
+    let (totals, _) = projects.stats(project_id).await.unwrap().unwrap();
+    assert_eq!(totals.median_lamports, 200.0);
+
+    donate(&pool, project_id, None, Some("01"), 1_000, at(4, 0));
+    let (totals, daily) = projects.stats(project_id).await.unwrap().unwrap();
+    assert_eq!(totals.total_lamports, 1_600);
+    assert_eq!(totals.donation_count, 4);
+    // two wallets plus one anonymous donor
+    assert_eq!(totals.donor_count, 3);
+    assert_eq!(totals.anonymous_donation_count, 1);
+    assert_eq!(totals.average_lamports, 400.0);
+    // even count: mean of 200 and 300
+    assert_eq!(totals.median_lamports, 250.0);
+    assert_eq!(
+        daily,
+        vec![
+            DailyTotal { day: at(1, 0).date(), total_lamports: 400, donation_count: 2 },
+            DailyTotal { day: at(2, 0).date(), total_lamports: 200, donation_count: 1 },
+            DailyTotal { day: at(4, 0).date(), total_lamports: 1_000, donation_count: 1 },
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn anonymous_donors_are_counted_by_nullifier() {
+    let Some(pool) = test_pool() else { return };
+    let (projects, project_id) = project(&pool);
+    donate(&pool, project_id, None, Some("01"), 100, at(1, 0));
+    donate(&pool, project_id, None, Some("01"), 200, at(2, 0));
+    donate(&pool, project_id, None, Some("02"), 300, at(2, 0));
+    // recorded before nullifiers were kept
+    donate(&pool, project_id, None, None, 400, at(3, 0));
+
+    let (totals, _) = projects.stats(project_id).await.unwrap().unwrap();
+    assert_eq!(totals.donation_count, 4);
+    assert_eq!(totals.anonymous_donation_count, 4);
+    assert_eq!(totals.donor_count, 2);
+}
+
+#[actix_web::test]
+async fn donors_are_ranked_by_total_then_wallet() {
+    let Some(pool) = test_pool() else { return };
+    let (projects, project_id) = project(&pool);
+    donate(&pool, project_id, Some(BOB), None, 200, at(1, 9));
+    donate(&pool, project_id, Some(ALICE), None, 150, at(2, 9));
+    donate(&pool, project_id, Some(CAROL), None, 500, at(3, 9));
+    donate(&pool, project_id, Some(ALICE), None, 50, at(5, 9));
+    donate(&pool, project_id, None, Some("01"), 10_000, at(4, 0));
+
+    assert_eq!(
+        projects.top_donors(project_id, 10).await.unwrap().unwrap(),
+        vec![
+            DonorTotal { donor_wallet: CAROL.to_string(), total_lamports: 500, donation_count: 1, last_donated_at: at(3, 9) },
+            DonorTotal { donor_wallet: ALICE.to_string(), total_lamports: 200, donation_count: 2, last_donated_at: at(5, 9) },
+            DonorTotal { donor_wallet: BOB.to_string(), total_lamports: 200, donation_count: 1, last_donated_at: at(1, 9) },
+        ]
+    );
+    assert_eq!(projects.top_donors(project_id, 1).await.unwrap().unwrap().len(), 1);
+}
+
+#[test]
+fn signature_hashes_are_keyed() {
+    let signature = Signature::from([7u8; 64]);
+    let key = SignatureKey::new("key");
+
+    assert_eq!(key.hash(&signature), SignatureKey::new("key").hash(&signature));
+    assert_ne!(key.hash(&signature), SignatureKey::new("other key").hash(&signature));
+    assert_ne!(key.hash(&signature), key.hash(&Signature::from([8u8; 64])));
+    // a plain hash of the public signature would lead back to the transaction
+    assert_ne!(key.hash(&signature), hex::encode(Sha256::digest(signature.to_string())));
+}
+
+#[test]
+fn unknown_transactions_are_not_confirmed() {
+    let signature = Signature::default();
+
+    // the node answers `null` for signatures it hasn't confirmed
+    let rpc_client = RpcClient::new_mock("fails".to_string());
+    assert!(matches!(fetch_transaction(&rpc_client, &signature), Err(DonationError::NotConfirmed)));
+
+    let rpc_client = RpcClient::new_mock("succeeds".to_string());
+    assert!(fetch_transaction(&rpc_client, &signature).is_ok());
+
+    // anything else that doesn't parse is an RPC error, not a missing transaction
+    let mocks = HashMap::from([(RpcRequest::GetTransaction, json!({ "unexpected": true }))]);
+    let rpc_client = RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks);
+    assert!(matches!(fetch_transaction(&rpc_client, &signature), Err(DonationError::Rpc(_))));
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use serde_json::Value;
+use soulana_backend::config::SolanaCluster;
+use soulana_backend::routes::health;
+use soulana_backend::services::readiness::ReadinessChecks;
+use soulana_backend::zk::mock::MockZKVerifier;
+
+mod common;
+use common::unreachable_pool;
+
+#[actix_web::test]
+async fn health_is_registered() {
+    let app = init_service(App::new().configure(health::health_config)).await;
+
+    let resp = call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["status"], "healthy");
+}
+
+#[actix_web::test]
+async fn ready_reports_each_dependency() {
+    let readiness = ReadinessChecks::new(
+        unreachable_pool(),
+        Arc::new(MockZKVerifier::new()),
+        SolanaCluster::Custom("http://127.0.0.1:1".to_string()),
+    );
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(readiness))
+            .configure(health::health_config),
+    )
+    .await;
+
+    let resp = call_service(&app, TestRequest::get().uri("/ready").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = read_body_json(resp).await;
+    assert_eq!(body["status"], "not_ready");
+
+    let checks = &body["checks"];
+    assert_eq!(checks["database"]["status"], "down");
+    assert_eq!(checks["database"]["critical"], true);
+    assert_eq!(checks["migrations"]["status"], "down");
+    assert_eq!(checks["zk_keys"]["status"], "up");
+    assert_eq!(checks["solana_rpc"]["status"], "down");
+    assert_eq!(checks["solana_rpc"]["critical"], false);
+    for name in ["database", "migrations", "zk_keys", "solana_rpc"] {
+        assert!(checks[name]["latency_ms"].is_number(), "{}", name);
+    }
+}
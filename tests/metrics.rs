@@ -0,0 +1,53 @@
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body, TestRequest};
+use actix_web::{middleware, web, App};
+use ark_bn254::Fr;
+use soulana_backend::metrics;
+use soulana_backend::routes::health;
+use soulana_backend::routes::metrics::get_metrics;
+use soulana_backend::zk::circuits::CircuitId;
+use soulana_backend::zk::metered::Metered;
+use soulana_backend::zk::mock::MockZKVerifier;
+use soulana_backend::zk::ZKVerifier;
+
+mod common;
+use common::unreachable_pool;
+
+#[actix_web::test]
+async fn metrics_are_exported_in_text_format() {
+    let pool = unreachable_pool();
+    let max_connections = format!("db_pool_max_connections {}", pool.max_size());
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(pool))
+            .wrap(middleware::from_fn(metrics::track_requests))
+            .configure(health::health_config)
+            .service(get_metrics),
+    )
+    .await;
+
+    call_service(&app, TestRequest::get().uri("/health").to_request()).await;
+    call_service(&app, TestRequest::get().uri("/wp-login.php").to_request()).await;
+
+    let verifier = Metered::new(MockZKVerifier::new());
+    verifier.verify_wallet(&CircuitId::current(), "wallet", Fr::from(1u64), "proof").unwrap();
+    metrics::donation_transaction_built(7);
+
+    let resp = call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("content-type").unwrap().to_str().unwrap().starts_with("text/plain"));
+    let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+
+    for expected in [
+        r#"http_requests_total{method="GET",route="/health",status="200"}"#,
+        r#"http_requests_total{method="GET",route="unmatched",status="404"}"#,
+        r#"http_request_duration_seconds_bucket{method="GET",route="/health",status="200""#,
+        r#"zk_proofs_total{kind="wallet",operation="verify",outcome="ok"}"#,
+        r#"zk_proof_duration_seconds_count{kind="wallet",operation="verify"}"#,
+        r#"donation_transactions_built_total{project_id="7"}"#,
+        r#"db_pool_connections{state="in_use"}"#,
+        &max_connections,
+    ] {
+        assert!(body.contains(expected), "missing {} in\n{}", expected, body);
+    }
+}
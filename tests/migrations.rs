@@ -0,0 +1,29 @@
+use std::fs;
+use std::time::Duration;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use soulana_backend::migrations::{self, MigrationError};
+
+#[test]
+fn every_migration_is_embedded_in_order() {
+    let mut on_disk: Vec<String> = fs::read_dir("migrations")
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.path().join("up.sql").exists())
+        .map(|entry| entry.file_name().into_string().unwrap())
+        .collect();
+    on_disk.sort();
+
+    let embedded = migrations::embedded().unwrap();
+    assert_eq!(embedded, on_disk);
+    assert!(embedded.iter().any(|name| name.ends_with("create_donation_commitments")));
+}
+
+#[test]
+fn startup_fails_without_a_database() {
+    let manager = ConnectionManager::<PgConnection>::new("postgres://127.0.0.1:1/unused");
+    let pool = Pool::builder()
+        .connection_timeout(Duration::from_millis(200))
+        .build_unchecked(manager);
+    assert!(matches!(migrations::prepare(&pool, true), Err(MigrationError::Pool(_))));
+}
@@ -0,0 +1,261 @@
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use serde_json::{json, Value};
+use soulana_backend::config::SolanaCluster;
+use soulana_backend::repos::{IdentityRepo, PledgeRepo, ProjectRepo};
+use soulana_backend::routes::pledges::{self, models::Pledge};
+use soulana_backend::routes::projects::models::Donation;
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::cache::ProjectCache;
+use soulana_backend::services::donations::SignatureKey;
+use soulana_backend::services::notifier::{
+    check_webhook_url, is_public, LogNotifier, Notifier, Notifiers, NotifyError, PledgeNotice, WebhookNotifier,
+    EMAIL_CHANNEL, WEBHOOK_CHANNEL,
+};
+use soulana_backend::services::pledges::{
+    check_fulfilment, next_due_at, FulfilmentError, PledgeScheduler, SchedulerError,
+};
+use uuid::Uuid;
+
+mod common;
+use common::unreachable_pool;
+
+const WALLET: &str = "F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW";
+
+fn at(day: u32, hour: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 1, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+}
+
+fn pledge(identity_id: Uuid, amount: f64) -> Pledge {
+    Pledge {
+        id: Uuid::new_v4(),
+        identity_id,
+        project_id: 1,
+        amount,
+        interval_days: 7,
+        notify_channel: WEBHOOK_CHANNEL.to_string(),
+        notify_target: "https://hooks.example.com/pledges".to_string(),
+        next_due_at: at(1, 0),
+        active: true,
+        created_at: at(1, 0),
+    }
+}
+
+fn donation(donor_wallet: Option<&str>, amount_lamports: i64) -> Donation {
+    Donation {
+        id: Uuid::new_v4(),
+        project_id: 1,
+        donor_wallet: donor_wallet.map(str::to_string),
+        amount_lamports,
+        signature: None,
+        slot: donor_wallet.map(|_| 1),
+        confirmed_at: at(1, 0),
+        created_at: at(1, 0),
+        signature_hash: String::new(),
+        verified_anonymous: donor_wallet.is_none(),
+        donor_nullifier: donor_wallet.is_none().then(|| "01".to_string()),
+    }
+}
+
+fn notice() -> PledgeNotice {
+    PledgeNotice {
+        pledge_id: Uuid::new_v4(),
+        reminder_id: Uuid::new_v4(),
+        project_id: 1,
+        project_title: "Clean water".to_string(),
+        amount: 0.5,
+        due_at: at(1, 0),
+        action_url: "https://soulana.example/api/blink-chain?id=1&amount=0.5".to_string(),
+    }
+}
+
+#[test]
+fn next_due_dates_skip_missed_periods() {
+    let cases = [
+        ("due now", at(1, 0), 7, at(1, 0), at(8, 0)),
+        ("run late", at(1, 0), 7, at(3, 12), at(8, 0)),
+        ("one period missed", at(1, 0), 7, at(9, 0), at(15, 0)),
+        ("due date lands on now", at(1, 0), 7, at(8, 0), at(15, 0)),
+        ("daily after an outage", at(1, 0), 1, at(5, 6), at(6, 0)),
+    ];
+    for (name, due_at, interval_days, now, expected) in cases {
+        assert_eq!(next_due_at(due_at, interval_days, now), expected, "{}", name);
+    }
+}
+
+#[test]
+fn only_the_pledger_can_fulfil_a_pledge() {
+    let pledger = Uuid::new_v4();
+    let pledge = pledge(pledger, 0.5);
+
+    assert!(check_fulfilment(&pledge, &donation(Some(WALLET), 500_000_000), Some(pledger)).is_ok());
+    assert!(check_fulfilment(&pledge, &donation(Some(WALLET), 900_000_000), Some(pledger)).is_ok());
+    assert!(matches!(
+        check_fulfilment(&pledge, &donation(Some(WALLET), 499_999_999), Some(pledger)),
+        Err(FulfilmentError::TooSmall(_))
+    ));
+    assert!(matches!(
+        check_fulfilment(&pledge, &donation(Some(WALLET), 500_000_000), Some(Uuid::new_v4())),
+        Err(FulfilmentError::NotPledger)
+    ));
+    assert!(matches!(
+        check_fulfilment(&pledge, &donation(Some(WALLET), 500_000_000), None),
+        Err(FulfilmentError::NotPledger)
+    ));
+    assert!(matches!(
+        check_fulfilment(&pledge, &donation(None, 500_000_000), Some(pledger)),
+        Err(FulfilmentError::Anonymous)
+    ));
+}
+
+#[test]
+fn webhook_targets_must_be_public_https_urls() {
+    let accepted = ["https://hooks.example.com/pledges", "https://93.184.216.34/hook", "https://[2606:4700::1111]/"];
+    for target in accepted {
+        assert!(check_webhook_url(target).is_ok(), "{}", target);
+    }
+
+    let rejected = [
+        "http://hooks.example.com/pledges",
+        "file:///etc/passwd",
+        "not a url",
+        "https://localhost/hook",
+        "https://api.localhost/hook",
+        "https://127.0.0.1/hook",
+        "https://10.0.0.8/hook",
+        "https://172.16.0.1/hook",
+        "https://192.168.1.1/hook",
+        "https://169.254.169.254/latest/meta-data",
+        "https://100.64.0.1/hook",
+        "https://0.0.0.0/hook",
+        "https://[::1]/hook",
+        "https://[fd00::1]/hook",
+        "https://[fe80::1]/hook",
+        "https://[::ffff:127.0.0.1]/hook",
+    ];
+    for target in rejected {
+        assert!(matches!(check_webhook_url(target), Err(NotifyError::InvalidTarget(_))), "{}", target);
+    }
+
+    assert!(is_public("8.8.8.8".parse::<IpAddr>().unwrap()));
+    assert!(!is_public("255.255.255.255".parse::<IpAddr>().unwrap()));
+}
+
+#[test]
+fn webhooks_are_not_sent_to_local_hosts() {
+    let result = WebhookNotifier::new().notify("https://127.0.0.1:8443/hook", &notice());
+    assert!(matches!(result, Err(NotifyError::InvalidTarget(_))));
+}
+
+struct Recording(Arc<Mutex<Vec<(String, Uuid)>>>);
+
+impl Notifier for Recording {
+    fn notify(&self, target: &str, notice: &PledgeNotice) -> Result<(), NotifyError> {
+        self.0.lock().unwrap().push((target.to_string(), notice.reminder_id));
+        Ok(())
+    }
+}
+
+#[test]
+fn notifiers_dispatch_by_channel() {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let notifiers = Notifiers::new()
+        .register(EMAIL_CHANNEL, Recording(sent.clone()))
+        .register(WEBHOOK_CHANNEL, WebhookNotifier::new());
+    let notice = notice();
+
+    notifiers.notify(EMAIL_CHANNEL, "donor@example.com", &notice).unwrap();
+    assert_eq!(*sent.lock().unwrap(), vec![("donor@example.com".to_string(), notice.reminder_id)]);
+
+    assert!(matches!(notifiers.notify("sms", "+15550100", &notice), Err(NotifyError::UnknownChannel(_))));
+    assert!(matches!(notifiers.check_target("sms", "+15550100"), Err(NotifyError::UnknownChannel(_))));
+    assert!(notifiers.check_target(WEBHOOK_CHANNEL, "https://hooks.example.com/pledges").is_ok());
+    assert!(matches!(
+        notifiers.check_target(WEBHOOK_CHANNEL, "http://hooks.example.com/pledges"),
+        Err(NotifyError::InvalidTarget(_))
+    ));
+    assert!(LogNotifier.notify("donor@example.com", &notice).is_ok());
+}
+
+#[test]
+fn scheduler_reports_an_unavailable_database() {
+    let scheduler = PledgeScheduler::new(
+        unreachable_pool(),
+        Arc::new(Notifiers::new()),
+        "https://soulana.example/".to_string(),
+    );
+    assert!(matches!(scheduler.run_due(Utc::now().naive_utc()), Err(SchedulerError::Pool(_))));
+}
+
+#[actix_web::test]
+async fn pledges_require_the_pledgers_token() {
+    let auth_service = AuthService::new(unreachable_pool(), "test_secret".to_string());
+    let (token, _) = auth_service.create_auth_token(WALLET).await.unwrap();
+    let notifiers = Notifiers::new().register(WEBHOOK_CHANNEL, WebhookNotifier::new());
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(auth_service))
+            .app_data(web::Data::new(IdentityRepo::new(unreachable_pool())))
+            .app_data(web::Data::new(PledgeRepo::new(unreachable_pool())))
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(notifiers))
+            .app_data(web::Data::new(ProjectCache::<Value>::new(Duration::from_secs(60))))
+            .app_data(web::Data::new(SolanaCluster::Devnet))
+            .service(web::scope("/api/pledges").configure(pledges::pledges_config)),
+    )
+    .await;
+    let pledge = |target: &str| {
+        json!({
+            "project_id": 1,
+            "amount": "0.5",
+            "interval_days": 7,
+            "notify_channel": WEBHOOK_CHANNEL,
+            "notify_target": target,
+        })
+    };
+    let bearer = ("Authorization", format!("Bearer {}", token));
+
+    let req = TestRequest::post()
+        .uri("/api/pledges")
+        .set_json(pledge("https://hooks.example.com/pledges"))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = TestRequest::post()
+        .uri("/api/pledges")
+        .insert_header(bearer.clone())
+        .set_json(pledge("https://169.254.169.254/latest/meta-data"))
+        .to_request();
+    let resp = call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = read_body_json(resp).await;
+    assert!(body["message"].as_str().unwrap().contains("public"), "{}", body);
+
+    // a valid pledge gets as far as looking up the caller's identity
+    let req = TestRequest::post()
+        .uri("/api/pledges")
+        .insert_header(bearer.clone())
+        .set_json(pledge("https://hooks.example.com/pledges"))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    let uri = format!("/api/pledges/{}", Uuid::new_v4());
+    let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let req = TestRequest::delete()
+        .uri(&uri)
+        .insert_header(("Authorization", "Bearer forged"))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+    let req = TestRequest::post()
+        .uri(&format!("/api/pledges/reminders/{}/confirm", Uuid::new_v4()))
+        .set_json(json!({ "signature": "not-a-signature" }))
+        .to_request();
+    assert_eq!(call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
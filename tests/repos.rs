@@ -0,0 +1,173 @@
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::http::StatusCode;
+use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+use actix_web::{web, App};
+use ark_bn254::Fr;
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::Value;
+use solana_sdk::signature::Signature;
+use soulana_backend::config::SolanaCluster;
+use soulana_backend::error::AppError;
+use soulana_backend::repos::{IdentityRepo, PledgeRepo, ProjectRepo, RepoError, UserRepo};
+use soulana_backend::routes::blink_chain::models::NewBeneficiary;
+use soulana_backend::routes::pledges::models::NewPledge;
+use soulana_backend::routes::{pledges, projects};
+use soulana_backend::schema::{identities, projects as projects_table, wallet_identities};
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::cache::ProjectCache;
+use soulana_backend::services::donations::{DonationError, SignatureKey};
+use soulana_backend::services::membership::{MembershipError, MembershipGroup};
+use soulana_backend::DbPool;
+use uuid::Uuid;
+
+mod common;
+use common::{test_pool, unreachable_pool};
+
+const WALLET: &str = "F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW";
+
+#[actix_web::test]
+async fn repos_report_an_unavailable_database() {
+    let projects = ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"));
+    assert!(matches!(projects.find(1).await, Err(RepoError::Pool(_))));
+    assert!(matches!(projects.stats(1).await, Err(RepoError::Pool(_))));
+
+    let users = UserRepo::new(unreachable_pool());
+    assert!(matches!(users.list().await, Err(RepoError::Pool(_))));
+
+    let pledges = PledgeRepo::new(unreachable_pool());
+    assert!(matches!(pledges.find_with_reminders(Uuid::new_v4()).await, Err(RepoError::Pool(_))));
+}
+
+#[actix_web::test]
+async fn donations_are_looked_up_before_a_connection_is_checked_out() {
+    // with neither the RPC node nor the database reachable, only the RPC is tried
+    let projects = ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"));
+    let cluster: SolanaCluster = "http://127.0.0.1:1".parse().unwrap();
+    let recorded = projects.record_donation(&cluster, 1, Signature::default(), None).await;
+    assert!(matches!(recorded, Err(DonationError::Rpc(_))));
+}
+
+#[actix_web::test]
+async fn donation_errors_map_to_app_errors() {
+    assert!(matches!(AppError::from(DonationError::ProjectNotFound), AppError::NotFound(_)));
+    assert!(matches!(AppError::from(DonationError::AlreadyRecorded), AppError::Conflict(_)));
+    assert!(matches!(AppError::from(DonationError::NotADonation), AppError::Validation(_)));
+    assert!(matches!(
+        AppError::from(DonationError::Db(diesel::result::Error::NotFound)),
+        AppError::Db(_)
+    ));
+}
+
+#[actix_web::test]
+async fn routes_answer_503_without_a_database() {
+    let auth_service = AuthService::new(unreachable_pool(), "test_secret".to_string());
+    let (token, _) = auth_service.create_auth_token("donor@example.com").await.unwrap();
+    let app = init_service(
+        App::new()
+            .app_data(web::Data::new(auth_service))
+            .app_data(web::Data::new(IdentityRepo::new(unreachable_pool())))
+            .app_data(web::Data::new(ProjectRepo::new(unreachable_pool(), SignatureKey::new("test_key"))))
+            .app_data(web::Data::new(PledgeRepo::new(unreachable_pool())))
+            .app_data(web::Data::new(ProjectCache::<Value>::new(Duration::from_secs(60))))
+            .service(web::scope("/api/projects").configure(projects::projects_config))
+            .service(web::scope("/api/pledges").configure(pledges::pledges_config)),
+    )
+    .await;
+
+    for uri in ["/api/projects/1/stats".to_string(), format!("/api/pledges/{}", Uuid::new_v4())] {
+        let req = TestRequest::get()
+            .uri(&uri)
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        let resp = call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE, "{}", uri);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "database_unavailable");
+    }
+}
+
+/// The id of a project seeded by the migrations.
+fn seeded_project(pool: &DbPool) -> i32 {
+    let mut conn = pool.get().unwrap();
+    projects_table::table.select(projects_table::id).first(&mut conn).unwrap()
+}
+
+/// Inserts an identity that logs in with `wallet`, returning its id.
+fn wallet_identity(pool: &DbPool, wallet: &str) -> Uuid {
+    let mut conn = pool.get().unwrap();
+    let identity_id = diesel::insert_into(identities::table)
+        .default_values()
+        .returning(identities::id)
+        .get_result(&mut conn)
+        .unwrap();
+    diesel::insert_into(wallet_identities::table)
+        .values((
+            wallet_identities::identity_id.eq(identity_id),
+            wallet_identities::wallet_address.eq(wallet),
+            wallet_identities::nonce.eq("nonce"),
+        ))
+        .execute(&mut conn)
+        .unwrap();
+    identity_id
+}
+
+#[actix_web::test]
+async fn repos_write_and_read_the_new_tables() {
+    let Some(pool) = test_pool() else { return };
+    let project_id = seeded_project(&pool);
+    let beneficiary = |wallet: &str, share_bps: i32| NewBeneficiary {
+        project_id,
+        wallet: wallet.to_string(),
+        share_bps,
+        label: None,
+    };
+
+    let projects = ProjectRepo::new(pool.clone(), SignatureKey::new("test_key"));
+    projects
+        .replace_beneficiaries(project_id, vec![beneficiary("alice", 6_000), beneficiary("bob", 4_000)])
+        .await
+        .unwrap();
+    let (_, found) = projects.find_with_beneficiaries(project_id).await.unwrap().unwrap();
+    let shares: Vec<_> = found.iter().map(|b| (b.wallet.as_str(), b.share_bps)).collect();
+    assert_eq!(shares, [("alice", 6_000), ("bob", 4_000)]);
+    projects.replace_beneficiaries(project_id, vec![beneficiary("carol", 10_000)]).await.unwrap();
+    let (_, found) = projects.find_with_beneficiaries(project_id).await.unwrap().unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].wallet, "carol");
+
+    let identity_id = wallet_identity(&pool, WALLET);
+    let pledges = PledgeRepo::new(pool.clone());
+    let pledge = pledges
+        .create(NewPledge {
+            identity_id,
+            project_id,
+            amount: 0.5,
+            interval_days: 30,
+            notify_channel: "email".to_string(),
+            notify_target: "donor@example.com".to_string(),
+            next_due_at: Utc::now().naive_utc(),
+        })
+        .await
+        .unwrap();
+    let (found, reminders) = pledges.find_with_reminders(pledge.id).await.unwrap().unwrap();
+    assert_eq!((found.identity_id, found.amount, found.active), (identity_id, 0.5, true));
+    assert!(reminders.is_empty());
+    // only the identity that made the pledge can cancel it
+    assert!(pledges.cancel(pledge.id, Uuid::new_v4()).await.unwrap().is_none());
+    assert!(!pledges.cancel(pledge.id, identity_id).await.unwrap().unwrap().active);
+
+    let identities = IdentityRepo::new(pool.clone());
+    assert_eq!(identities.find_by_subject(WALLET).await.unwrap(), Some(identity_id));
+    let group = Arc::new(MembershipGroup::load(&mut pool.get().unwrap(), 10).unwrap());
+    let (index, root) = identities.register_commitment(group.clone(), WALLET, Fr::from(5u64)).await.unwrap();
+    assert_eq!(root, group.root());
+    assert!(matches!(
+        identities.register_commitment(group.clone(), WALLET, Fr::from(6u64)).await,
+        Err(MembershipError::AlreadyRegistered)
+    ));
+    // the group rebuilt from the table matches the one registered into
+    let reloaded = MembershipGroup::load(&mut pool.get().unwrap(), 10).unwrap();
+    assert_eq!((reloaded.root(), reloaded.size()), (root, index + 1));
+}
@@ -0,0 +1,59 @@
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemError;
+use solana_sdk::transaction::TransactionError;
+use soulana_backend::routes::blink_chain::simulation::describe_simulation_error;
+
+#[test]
+fn simulation_errors_are_described_for_the_donor() {
+    let payer = Pubkey::new_unique();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let recipients = [first, second];
+    let negative_lamports = InstructionError::Custom(SystemError::ResultWithNegativeLamports as u32);
+
+    let cases = [
+        (TransactionError::AccountNotFound, format!("Account {} has no SOL to pay for this donation", payer)),
+        (TransactionError::InvalidAccountForFee, format!("Account {} has no SOL to pay for this donation", payer)),
+        (TransactionError::InsufficientFundsForFee, "Insufficient funds for fee".to_string()),
+        (
+            TransactionError::InsufficientFundsForRent { account_index: 0 },
+            "Donation would leave your account below the rent-exempt minimum, try a smaller amount".to_string(),
+        ),
+        (
+            TransactionError::InsufficientFundsForRent { account_index: 2 },
+            "Donation is too small to make the recipient account rent-exempt".to_string(),
+        ),
+        (
+            TransactionError::BlockhashNotFound,
+            "Transaction expired before it could be checked, please try again".to_string(),
+        ),
+        (TransactionError::InstructionError(0, negative_lamports), "Insufficient balance for donation".to_string()),
+        (
+            TransactionError::InstructionError(1, InstructionError::InsufficientFunds),
+            "Insufficient balance for donation".to_string(),
+        ),
+        (
+            TransactionError::InstructionError(1, InstructionError::InvalidAccountOwner),
+            format!("Recipient account {} invalid", second),
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::ReadonlyLamportChange),
+            format!("Recipient account {} invalid", first),
+        ),
+        (
+            TransactionError::InstructionError(5, InstructionError::InvalidAccountData),
+            "Recipient account unknown invalid".to_string(),
+        ),
+        (
+            TransactionError::InstructionError(0, InstructionError::Custom(42)),
+            format!("Transfer to {} would fail: {}", first, InstructionError::Custom(42)),
+        ),
+        (
+            TransactionError::AlreadyProcessed,
+            format!("Donation transaction would fail: {}", TransactionError::AlreadyProcessed),
+        ),
+    ];
+    for (err, expected) in cases {
+        assert_eq!(describe_simulation_error(&err, &payer, &recipients), expected, "{:?}", err);
+    }
+}
@@ -0,0 +1,206 @@
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_snark::SNARK;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use soulana_backend::routes::auth::auth_routes;
+use soulana_backend::routes::zk::zk_routes;
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::email_commitments::{EmailCommitmentStore, MemoryEmailCommitmentStore};
+use soulana_backend::services::email_verification::{EmailVerificationError, EmailVerifications, MAX_ATTEMPTS};
+use soulana_backend::services::notifier::{CodeSender, NotifyError};
+use soulana_backend::zk::attributes::{split_email, EmailDomainCircuit, EmailDomainProver, EmailOpening};
+use soulana_backend::zk::ceremony;
+use soulana_backend::zk::field::{fr_from_hex, fr_to_hex};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::{ZKProver, ZKVerifier};
+
+const EMAIL: &str = "ada@Uni.edu";
+
+/// Keeps the last code instead of emailing it.
+#[derive(Default)]
+struct Inbox(Mutex<Option<(String, String)>>);
+
+impl CodeSender for Inbox {
+    fn send_code(&self, email: &str, code: &str) -> Result<(), NotifyError> {
+        *self.0.lock().unwrap() = Some((email.to_string(), code.to_string()));
+        Ok(())
+    }
+}
+
+fn prover() -> &'static EmailDomainProver {
+    static PROVER: OnceLock<EmailDomainProver> = OnceLock::new();
+    PROVER.get_or_init(|| {
+        let (proving_key, _) =
+            Groth16::<Bn254>::circuit_specific_setup(EmailDomainCircuit::blank(), &mut StdRng::seed_from_u64(1))
+                .unwrap();
+        EmailDomainProver::from_keys(proving_key)
+    })
+}
+
+#[actix_web::test]
+async fn splits_emails_at_the_last_at_sign() {
+    assert_eq!(split_email("ada@Uni.EDU"), Some(("ada", "uni.edu".to_string())));
+    assert_eq!(split_email("\"a@b\"@uni.edu"), Some(("\"a@b\"", "uni.edu".to_string())));
+    assert_eq!(split_email("F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW"), None);
+    assert_eq!(split_email("@uni.edu"), None);
+    assert_eq!(split_email("ada@"), None);
+}
+
+#[actix_web::test]
+async fn proof_reveals_only_the_domain() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let opening = EmailOpening::new(EMAIL, &mut rng).unwrap();
+    let other = EmailOpening::new("bob@uni.edu", &mut rng).unwrap();
+    assert_eq!(opening.domain_hash, other.domain_hash);
+    assert_ne!(opening.commitment(), EmailOpening::new(EMAIL, &mut rng).unwrap().commitment());
+
+    let challenge = Fr::from(11u64);
+    let proof = prover().create_proof(&opening, challenge).unwrap();
+    let verifier = prover().verifier();
+    let commitment = opening.commitment();
+
+    assert!(verifier.verify(&proof, commitment, "uni.edu", challenge));
+    assert!(verifier.verify(&proof, commitment, "UNI.edu", challenge));
+    assert!(!verifier.verify(&proof, commitment, "evil.edu", challenge));
+    assert!(!verifier.verify(&proof, commitment, "uni.edu", Fr::from(12u64)));
+    assert!(!verifier.verify(&proof, other.commitment(), "uni.edu", challenge));
+}
+
+#[actix_web::test]
+async fn issued_commitments_prove_their_domain_once_per_challenge() {
+    let manager = ConnectionManager::<PgConnection>::new("postgres://localhost/unused");
+    let auth_service = AuthService::new(Pool::builder().build_unchecked(manager), "test_secret".to_string());
+    let (email_token, _) = auth_service.create_auth_token(EMAIL).await.unwrap();
+    let (wallet_token, _) = auth_service.create_auth_token("F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW").await.unwrap();
+
+    let identity_keys = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(3))).unwrap();
+    let verifier: Arc<dyn ZKVerifier> = Arc::new(
+        RealZKVerifier::from_prover(ZKProver::from_keys(identity_keys.proving_key))
+            .with_email_domain(prover().verifier()),
+    );
+    let commitments: Arc<dyn EmailCommitmentStore> = Arc::new(MemoryEmailCommitmentStore::new());
+    let inbox = Arc::new(Inbox::default());
+    let sender: Arc<dyn CodeSender> = inbox.clone();
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(auth_service))
+            .app_data(web::Data::new(EmailVerifications::new(Duration::from_secs(300))))
+            .app_data(web::Data::from(sender))
+            .app_data(web::Data::new(ChallengeStore::new(Duration::from_secs(300))))
+            .app_data(web::Data::from(verifier))
+            .app_data(web::Data::from(commitments))
+            .service(auth_routes())
+            .service(zk_routes()),
+    )
+    .await;
+
+    let send_code = |token: &str| {
+        test::TestRequest::post()
+            .uri("/auth/zk/email-commitment/code")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request()
+    };
+    let commit = |token: &str, code: &str| {
+        test::TestRequest::post()
+            .uri("/auth/zk/email-commitment")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({ "code": code }))
+            .to_request()
+    };
+
+    assert_eq!(test::call_service(&app, send_code(&wallet_token)).await.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(test::call_service(&app, send_code(&email_token)).await.status(), StatusCode::ACCEPTED);
+    assert_eq!(test::call_service(&app, send_code(&email_token)).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    let (sent_to, code) = inbox.0.lock().unwrap().clone().unwrap();
+    assert_eq!(sent_to, EMAIL);
+
+    // a login alone doesn't show the caller owns the address
+    let wrong = if code == "000000" { "000001" } else { "000000" };
+    assert_eq!(test::call_service(&app, commit(&email_token, wrong)).await.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(test::call_service(&app, commit(&wallet_token, &code)).await.status(), StatusCode::UNAUTHORIZED);
+
+    let resp = test::call_service(&app, commit(&email_token, &code)).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let issued: Value = test::read_body_json(resp).await;
+    assert_eq!(issued["domain"], "uni.edu");
+    // codes are single use
+    assert_eq!(test::call_service(&app, commit(&email_token, &code)).await.status(), StatusCode::UNAUTHORIZED);
+    let opening = EmailOpening {
+        local_hash: fr_from_hex(issued["local_hash"].as_str().unwrap()).unwrap(),
+        domain_hash: fr_from_hex(issued["domain_hash"].as_str().unwrap()).unwrap(),
+        salt: fr_from_hex(issued["salt"].as_str().unwrap()).unwrap(),
+    };
+    assert_eq!(fr_to_hex(&opening.commitment()), issued["commitment"]);
+
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/challenge")
+        .set_json(json!({ "identity": issued["commitment"] }))
+        .to_request();
+    let challenge: Value = test::call_and_read_body_json(&app, req).await;
+    let challenge = challenge["challenge"].as_str().unwrap();
+    let proof = prover().create_proof(&opening, fr_from_hex(challenge).unwrap()).unwrap();
+    let verify = |commitment: &Value, domain: &str| {
+        test::TestRequest::post()
+            .uri("/zk/email-domain/verify")
+            .set_json(json!({
+                "commitment": commitment,
+                "domain": domain,
+                "challenge": challenge,
+                "proof": BASE64.encode(&proof),
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, verify(&issued["commitment"], "evil.edu")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, verify(&issued["commitment"], "uni.edu")).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["valid"], true);
+    let resp = test::call_service(&app, verify(&issued["commitment"], "uni.edu")).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // a self-made commitment to the same address wasn't issued after verifying the email
+    let forged = EmailOpening::new(EMAIL, &mut StdRng::seed_from_u64(4)).unwrap();
+    let forged = json!(fr_to_hex(&forged.commitment()));
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/challenge")
+        .set_json(json!({ "identity": forged }))
+        .to_request();
+    let forged_challenge: Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/zk/email-domain/verify")
+        .set_json(json!({
+            "commitment": forged,
+            "domain": "uni.edu",
+            "challenge": forged_challenge["challenge"],
+            "proof": BASE64.encode(&proof),
+        }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn email_codes_expire_and_allow_few_guesses() {
+    let verifications = EmailVerifications::new(Duration::from_secs(300));
+    let (code, _) = verifications.start(EMAIL).unwrap();
+    let wrong = if code == "000000" { "000001" } else { "000000" };
+    for _ in 0..MAX_ATTEMPTS {
+        assert!(matches!(verifications.confirm(EMAIL, wrong), Err(EmailVerificationError::InvalidCode)));
+    }
+    // the code is gone after too many wrong guesses
+    assert!(matches!(verifications.confirm(EMAIL, &code), Err(EmailVerificationError::InvalidCode)));
+
+    let verifications = EmailVerifications::new(Duration::ZERO);
+    let (code, _) = verifications.start(EMAIL).unwrap();
+    assert!(matches!(verifications.confirm(EMAIL, &code), Err(EmailVerificationError::InvalidCode)));
+}
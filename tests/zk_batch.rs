@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::Fr;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use soulana_backend::routes::zk::{zk_routes, MAX_BATCH_SIZE};
+use soulana_backend::zk::circuits::{CircuitError, CircuitId};
+use soulana_backend::zk::error::ZkError;
+use soulana_backend::zk::membership::{MembershipInputs, MembershipVerifier};
+use soulana_backend::zk::field::fr_to_hex;
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::{ceremony, ZKProver, ZKProverBackend, ZKVerifier};
+
+fn backend() -> Arc<RealZKVerifier> {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(1))).unwrap();
+    Arc::new(RealZKVerifier::from_prover(ZKProver::from_keys(params.proving_key)))
+}
+
+macro_rules! app {
+    ($backend:expr) => {{
+        let verifier: Arc<dyn ZKVerifier> = $backend.clone();
+        test::init_service(
+            App::new()
+                .app_data(web::Data::from(verifier))
+                .service(zk_routes()),
+        )
+        .await
+    }};
+}
+
+#[actix_web::test]
+async fn prover_batch_pinpoints_invalid_proofs() {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(2))).unwrap();
+    let prover = ZKProver::from_keys(params.proving_key);
+    let inputs = [Fr::from(5u64), Fr::from(9u64)];
+    let wrong = [Fr::from(5u64), Fr::from(10u64)];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let other = prover.create_proof(inputs[0], inputs[1]).unwrap();
+
+    assert_eq!(prover.verify_proofs(&[(&proof, &inputs), (&other, &inputs)]), vec![true, true]);
+    assert_eq!(
+        prover.verify_proofs(&[(&proof, &inputs), (&other, &wrong), (b"garbage", &inputs)]),
+        vec![true, false, false]
+    );
+}
+
+#[actix_web::test]
+async fn verify_batch_reports_each_proof() {
+    let backend = backend();
+    let challenge = Fr::from(77u64);
+    let wallet_proof = backend.create_wallet_proof("wallet", challenge).unwrap();
+    let email_proof = backend.create_email_proof("a@b.c", challenge).unwrap();
+    let mut forged = BASE64.decode(&wallet_proof).unwrap();
+    forged[0] ^= 1;
+    let app = app!(backend);
+
+    let circuit = CircuitId::current().to_string();
+    let challenge = fr_to_hex(&challenge);
+    let req = test::TestRequest::post()
+        .uri("/zk/verify-batch")
+        .set_json(json!({ "proofs": [
+            { "kind": "wallet", "identity": "wallet", "proof": wallet_proof, "challenge": challenge, "circuit_id": circuit },
+            { "kind": "wallet", "identity": "wallet", "proof": BASE64.encode(forged), "challenge": challenge, "circuit_id": circuit },
+            { "kind": "email", "identity": "a@b.c", "proof": email_proof, "challenge": challenge, "circuit_id": circuit },
+            { "kind": "email", "identity": "a@b.c", "proof": "not base64!", "challenge": challenge, "circuit_id": circuit },
+            { "kind": "wallet", "identity": "wallet", "proof": wallet_proof, "circuit_id": circuit },
+        ]}))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let body: Value = test::read_body_json(resp).await;
+    let valid: Vec<bool> = body["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["valid"].as_bool().unwrap())
+        .collect();
+    assert_eq!(valid, vec![true, false, true, false, false]);
+    assert_eq!(body["valid_count"], 2);
+    assert_eq!(body["invalid_count"], 3);
+}
+
+#[actix_web::test]
+async fn verify_batch_rejects_empty_and_oversized_batches() {
+    let backend = backend();
+    let app = app!(backend);
+
+    let req = test::TestRequest::post()
+        .uri("/zk/verify-batch")
+        .set_json(json!({ "proofs": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let proofs: Vec<Value> = (0..=MAX_BATCH_SIZE)
+        .map(|_| json!({ "kind": "wallet", "proof": "" }))
+        .collect();
+    let req = test::TestRequest::post()
+        .uri("/zk/verify-batch")
+        .set_json(json!({ "proofs": proofs }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn real_verifier_reports_why_proofs_fail() {
+    let backend = backend();
+    let circuit = CircuitId::current();
+    let challenge = Fr::from(3u64);
+    let proof = backend.create_wallet_proof("wallet", challenge).unwrap();
+
+    assert_eq!(backend.verify_wallet(&circuit, "wallet", challenge, &proof), Ok(()));
+    assert_eq!(
+        backend.verify_wallet(&circuit, "other", challenge, &proof),
+        Err(ZkError::PairingCheckFailed)
+    );
+    assert_eq!(
+        backend.verify_wallet(&circuit, "wallet", challenge, "not base64!"),
+        Err(ZkError::MalformedBase64)
+    );
+    assert_eq!(
+        backend.verify_wallet(&circuit, "wallet", challenge, &BASE64.encode(b"garbage")),
+        Err(ZkError::MalformedProof)
+    );
+    let unknown = CircuitId::new("identity", 99);
+    assert_eq!(
+        backend.verify_wallet(&unknown, "wallet", challenge, &proof),
+        Err(ZkError::Circuit(CircuitError::Unknown(unknown)))
+    );
+
+    let inputs = MembershipInputs {
+        root: Fr::from(1u64),
+        nullifier_hash: Fr::from(2u64),
+        external_nullifier: Fr::from(3u64),
+        signal_hash: Fr::from(4u64),
+    };
+    let missing = backend.verify_membership(&proof, &inputs).unwrap_err();
+    assert_eq!(missing, ZkError::KeyNotLoaded(MembershipVerifier::circuit_id()));
+    assert_eq!(missing.code(), "key_not_loaded");
+}
@@ -0,0 +1,190 @@
+use std::fs;
+use std::path::PathBuf;
+use ark_bn254::{Bn254, Fr, G1Projective, G2Projective};
+use ark_ec::Group;
+use ark_ff::{One, UniformRand};
+use ark_groth16::Groth16;
+use ark_serialize::CanonicalSerialize;
+use rand::{rngs::StdRng, SeedableRng};
+use soulana_backend::zk::ceremony::{self, CeremonyError, MAX_BEACON_ITERATIONS};
+use soulana_backend::zk::keys::{read_key_file, write_key_file, KeyFileError, KeyKind};
+use soulana_backend::zk::ptau::{PowersOfTau, PtauError};
+use soulana_backend::zk::{ZKCircuit, ZKProver};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soulana-zk-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn ceremony_keys_prove_and_verify() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let ptau = PowersOfTau::generate(3, &mut rng);
+    let initial = ceremony::init(&ptau).unwrap();
+
+    let mut first = initial.clone();
+    ceremony::contribute(&mut first, "alice", &mut rng);
+    ceremony::verify_contribution(&initial, &first).unwrap();
+
+    let mut sealed = first.clone();
+    ceremony::contribute_beacon(&mut sealed, b"block 123456", 4).unwrap();
+    ceremony::verify_contribution(&first, &sealed).unwrap();
+    ceremony::verify_transcript(&sealed, &ptau).unwrap();
+
+    let dir = temp_dir("roundtrip");
+    let mut proving_key = Vec::new();
+    sealed.proving_key.serialize_compressed(&mut proving_key).unwrap();
+    let mut verifying_key = Vec::new();
+    sealed.proving_key.vk.serialize_compressed(&mut verifying_key).unwrap();
+    write_key_file(&dir.join("proving_key.bin"), KeyKind::Proving, &proving_key).unwrap();
+    write_key_file(&dir.join("verifying_key.bin"), KeyKind::Verifying, &verifying_key).unwrap();
+
+    let prover = ZKProver::load_from(&dir.join("proving_key.bin"), &dir.join("verifying_key.bin")).unwrap();
+    let (input, challenge) = (Fr::from(42u64), Fr::from(7u64));
+    let proof = prover.create_proof(input, challenge).unwrap();
+    assert!(prover.verify_proof(&proof, &[input, challenge]));
+    assert!(!prover.verify_proof(&proof, &[input, Fr::from(8u64)]));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn initial_keys_match_ark_groth16_for_the_same_secrets() {
+    // `PowersOfTau::generate` draws tau, alpha and beta in that order, and ark-groth16's
+    // generator draws its point of evaluation first
+    let rng = StdRng::seed_from_u64(11);
+    let ptau = PowersOfTau::generate(3, &mut rng.clone());
+    let mut secrets = rng.clone();
+    let (_tau, alpha, beta) = (Fr::rand(&mut secrets), Fr::rand(&mut secrets), Fr::rand(&mut secrets));
+
+    let expected = Groth16::<Bn254>::generate_parameters_with_qap(
+        ZKCircuit::<Fr> { input: Some(Fr::from(1u64)), challenge: Some(Fr::from(1u64)) },
+        alpha,
+        beta,
+        Fr::one(),
+        Fr::one(),
+        G1Projective::generator(),
+        G2Projective::generator(),
+        &mut rng.clone(),
+    )
+    .unwrap();
+    assert_eq!(ceremony::init(&ptau).unwrap().proving_key, expected);
+}
+
+#[test]
+fn beacon_contribution_is_reproducible() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let mut params = ceremony::init(&PowersOfTau::generate(3, &mut rng)).unwrap();
+    ceremony::contribute(&mut params, "alice", &mut rng);
+
+    let mut a = params.clone();
+    let mut b = params.clone();
+    assert_eq!(
+        ceremony::contribute_beacon(&mut a, b"beacon", 2).unwrap(),
+        ceremony::contribute_beacon(&mut b, b"beacon", 2).unwrap(),
+    );
+
+    let iterations = MAX_BEACON_ITERATIONS + 1;
+    assert!(matches!(
+        ceremony::contribute_beacon(&mut a, b"beacon", iterations),
+        Err(CeremonyError::BeaconIterations(n)) if n == iterations
+    ));
+    assert_eq!(a.contributions.len(), 2);
+}
+
+#[test]
+fn transcripts_are_checked_against_their_powers_of_tau() {
+    let mut rng = StdRng::seed_from_u64(17);
+    let ptau = PowersOfTau::generate(3, &mut rng);
+    let mut params = ceremony::init(&ptau).unwrap();
+    ceremony::contribute(&mut params, "alice", &mut rng);
+    ceremony::verify_transcript(&params, &ptau).unwrap();
+
+    // initial keys set up from a tau someone knows don't pass for the public one
+    let mut swapped = ceremony::init(&PowersOfTau::generate(3, &mut rng)).unwrap();
+    ceremony::contribute(&mut swapped, "alice", &mut rng);
+    assert!(matches!(
+        ceremony::verify_transcript(&swapped, &ptau),
+        Err(CeremonyError::NotFromPowersOfTau)
+    ));
+
+    let too_small = PowersOfTau::generate(1, &mut rng);
+    assert!(matches!(
+        ceremony::init(&too_small),
+        Err(CeremonyError::Ptau(PtauError::TooSmall { needed: 3, available: 1 }))
+    ));
+}
+
+#[test]
+fn ptau_files_round_trip_and_are_checked() {
+    let ptau = PowersOfTau::generate(2, &mut StdRng::seed_from_u64(19));
+    let bytes = ptau.to_bytes();
+    let read = PowersOfTau::read(&bytes).unwrap();
+    assert_eq!(read.power, 2);
+    assert_eq!(read.tau_g1, ptau.tau_g1);
+    assert_eq!(read.tau_g2, ptau.tau_g2);
+    assert_eq!(read.beta_g2, ptau.beta_g2);
+
+    let mut swapped = ptau.clone();
+    swapped.tau_g1.swap(2, 3);
+    assert!(matches!(
+        PowersOfTau::read(&swapped.to_bytes()),
+        Err(PtauError::Inconsistent(_))
+    ));
+
+    let mut mixed = ptau.clone();
+    mixed.alpha_tau_g1 = PowersOfTau::generate(2, &mut StdRng::seed_from_u64(23)).alpha_tau_g1;
+    assert!(matches!(PowersOfTau::read(&mixed.to_bytes()), Err(PtauError::Inconsistent(_))));
+
+    assert!(matches!(PowersOfTau::read(b"zkey"), Err(PtauError::Malformed(_))));
+    assert!(matches!(
+        PowersOfTau::read(&bytes[..bytes.len() - 1]),
+        Err(PtauError::Malformed(_))
+    ));
+}
+
+#[test]
+fn tampered_contribution_is_rejected() {
+    let mut rng = StdRng::seed_from_u64(13);
+    let initial = ceremony::init(&PowersOfTau::generate(3, &mut rng)).unwrap();
+    let mut after = initial.clone();
+    ceremony::contribute(&mut after, "alice", &mut rng);
+
+    // a contributor swapping in an unrelated delta can't produce a matching proof
+    let mut forged = after.clone();
+    let mut other = initial.clone();
+    ceremony::contribute(&mut other, "mallory", &mut rng);
+    forged.proving_key.delta_g1 = other.proving_key.delta_g1;
+    forged.contributions[0].delta_after = other.proving_key.delta_g1;
+    assert!(ceremony::verify_contribution(&initial, &forged).is_err());
+
+    let mut renamed = after.clone();
+    renamed.contributions[0].name = "bob".to_string();
+    assert!(matches!(
+        ceremony::verify_contribution(&initial, &renamed),
+        Err(CeremonyError::TranscriptMismatch(0))
+    ));
+}
+
+#[test]
+fn corrupted_or_missing_key_files_are_rejected() {
+    let dir = temp_dir("corrupt");
+    let path = dir.join("verifying_key.bin");
+    write_key_file(&path, KeyKind::Verifying, b"payload").unwrap();
+    assert_eq!(read_key_file(&path, KeyKind::Verifying).unwrap(), b"payload");
+
+    assert!(matches!(read_key_file(&path, KeyKind::Proving), Err(KeyFileError::WrongKind(..))));
+
+    let mut bytes = fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 1;
+    fs::write(&path, bytes).unwrap();
+    assert!(matches!(read_key_file(&path, KeyKind::Verifying), Err(KeyFileError::ChecksumMismatch(_))));
+
+    assert!(matches!(
+        read_key_file(&dir.join("absent.bin"), KeyKind::Proving),
+        Err(KeyFileError::Missing(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
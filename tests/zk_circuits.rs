@@ -0,0 +1,125 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use ark_bn254::Fr;
+use ark_serialize::CanonicalSerialize;
+use chrono::{Duration, Utc};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::json;
+use soulana_backend::routes::auth::ZKProofRequest;
+use soulana_backend::zk::ceremony;
+use soulana_backend::zk::circuits::{CircuitError, CircuitId, CircuitRegistry};
+use soulana_backend::zk::keys::{write_key_file, write_key_file_for, KeyFileError, KeyKind};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::{BatchProof, CircuitVerifier, ZKProver};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soulana-circuits-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn encode<T: CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes).unwrap();
+    bytes
+}
+
+/// Writes keys for a circuit version from a fresh setup, returning a prover for it.
+fn write_keys(keys_dir: &Path, id: &CircuitId, seed: u64) -> ZKProver {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(seed))).unwrap();
+    let dir = id.key_dir(keys_dir);
+    write_key_file_for(&dir.join("verifying_key.bin"), KeyKind::Verifying, id.version, &encode(&params.proving_key.vk)).unwrap();
+    if *id == CircuitId::current() {
+        write_key_file(&dir.join("proving_key.bin"), KeyKind::Proving, &encode(&params.proving_key)).unwrap();
+    }
+    ZKProver::from_keys(params.proving_key)
+}
+
+#[test]
+fn circuit_ids_round_trip() {
+    let id: CircuitId = "identity@v2".parse().unwrap();
+    assert_eq!(id, CircuitId::new("identity", 2));
+    assert_eq!(id.to_string(), "identity@v2");
+    assert_eq!(serde_json::to_value(&id).unwrap(), json!("identity@v2"));
+
+    for invalid in ["identity", "identity@2", "@v1", "identity@vx", "../x@v1"] {
+        assert!(matches!(invalid.parse::<CircuitId>(), Err(CircuitError::InvalidId(_))), "{}", invalid);
+    }
+
+    // a missing id is the current circuit, never an older one without the login challenge
+    let request: ZKProofRequest = serde_json::from_value(json!({ "proof": "p", "public_inputs": [] })).unwrap();
+    assert_eq!(request.circuit_id, CircuitId::current());
+    let batch: BatchProof = serde_json::from_value(json!({ "kind": "wallet", "proof": "p" })).unwrap();
+    assert_eq!(batch.circuit_id, CircuitId::current());
+}
+
+#[test]
+fn deprecated_circuits_verify_until_their_window_ends() {
+    let keys_dir = temp_dir("window");
+    let old = CircuitId::new("identity", 0);
+    let old_prover = write_keys(&keys_dir, &old, 1);
+    let current_prover = write_keys(&keys_dir, &CircuitId::current(), 2);
+
+    let verify_until = Utc::now() + Duration::days(30);
+    fs::write(
+        keys_dir.join("circuits.json"),
+        json!({ "deprecated": [{ "circuit_id": "identity@v0", "verify_until": verify_until }] }).to_string(),
+    )
+    .unwrap();
+    let registry = CircuitRegistry::load(&keys_dir).unwrap();
+
+    let inputs = [Fr::from(1u64), Fr::from(2u64)];
+    let old_proof = old_prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let current_proof = current_prover.create_proof(inputs[0], inputs[1]).unwrap();
+
+    assert!(registry.verifier(&old).unwrap().verify_proof(&old_proof, &inputs));
+    assert!(registry.verifier(&CircuitId::current()).unwrap().verify_proof(&current_proof, &inputs));
+    // keys are per version, a proof only verifies under the circuit it was made for
+    assert!(!registry.verifier(&CircuitId::current()).unwrap().verify_proof(&old_proof, &inputs));
+
+    let after_window = verify_until + Duration::seconds(1);
+    assert!(matches!(registry.verifier_at(&old, after_window), Err(CircuitError::Retired(..))));
+    assert!(registry.verifier_at(&CircuitId::current(), after_window).is_ok());
+    assert!(matches!(
+        registry.verifier(&CircuitId::new("identity", 99)),
+        Err(CircuitError::Unknown(_))
+    ));
+
+    fs::remove_dir_all(keys_dir).unwrap();
+}
+
+#[test]
+fn registry_without_manifest_only_accepts_current_circuit() {
+    let keys_dir = temp_dir("current-only");
+    write_keys(&keys_dir, &CircuitId::current(), 3);
+    write_keys(&keys_dir, &CircuitId::new("identity", 0), 4);
+
+    let registry = CircuitRegistry::load(&keys_dir).unwrap();
+    assert!(registry.verifier(&CircuitId::current()).is_ok());
+    assert!(registry.verifier(&CircuitId::new("identity", 0)).is_err());
+
+    fs::remove_dir_all(keys_dir).unwrap();
+}
+
+#[test]
+fn registry_rejects_keys_for_the_wrong_version() {
+    let keys_dir = temp_dir("wrong-version");
+    write_keys(&keys_dir, &CircuitId::current(), 5);
+
+    // verifying key for v0 copied into the v2 directory
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(6))).unwrap();
+    let path = CircuitId::new("identity", 2).key_dir(&keys_dir).join("verifying_key.bin");
+    write_key_file_for(&path, KeyKind::Verifying, 0, &encode(&params.proving_key.vk)).unwrap();
+    assert!(matches!(CircuitVerifier::load(&path, 2), Err(KeyFileError::WrongCircuitVersion(_, 0, 2))));
+
+    let verify_until = Utc::now() + Duration::days(1);
+    fs::write(
+        keys_dir.join("circuits.json"),
+        json!({ "deprecated": [{ "circuit_id": "identity@v2", "verify_until": verify_until }] }).to_string(),
+    )
+    .unwrap();
+    assert!(CircuitRegistry::load(&keys_dir).is_err());
+
+    fs::remove_dir_all(keys_dir).unwrap();
+}
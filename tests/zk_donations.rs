@@ -0,0 +1,181 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+use ark_snark::SNARK;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use uuid::Uuid;
+use solana_sdk::signature::{Keypair, Signer};
+use soulana_backend::routes::auth::{auth_routes, DONATION_COMMITMENT_ACTION};
+use soulana_backend::routes::zk::zk_routes;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::wallet_signatures;
+use soulana_backend::services::donation_commitments::{
+    CommittedDonation, DonationCommitmentStore, MemoryDonationCommitmentStore,
+};
+use soulana_backend::zk::ceremony;
+use soulana_backend::zk::donations::{DonationOpening, DonationThresholdCircuit, DonationThresholdProver};
+use soulana_backend::zk::field::{fr_from_hex, fr_to_hex};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::{ZKProver, ZKVerifier};
+
+const SOL: u64 = 1_000_000_000;
+
+fn prover() -> &'static DonationThresholdProver {
+    static PROVER: OnceLock<DonationThresholdProver> = OnceLock::new();
+    PROVER.get_or_init(|| {
+        let (proving_key, _) = Groth16::<Bn254>::circuit_specific_setup(
+            DonationThresholdCircuit::blank(),
+            &mut StdRng::seed_from_u64(1),
+        )
+        .unwrap();
+        DonationThresholdProver::from_keys(proving_key)
+    })
+}
+
+fn is_satisfied(opening: DonationOpening, threshold: u64) -> bool {
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let circuit = DonationThresholdCircuit {
+        opening: Some(opening),
+        commitment: Some(opening.commitment()),
+        threshold: Some(threshold),
+        challenge: Some(Fr::from(1u64)),
+    };
+    circuit.generate_constraints(cs.clone()).unwrap();
+    cs.is_satisfied().unwrap()
+}
+
+#[actix_web::test]
+async fn range_check_rejects_amounts_below_the_threshold() {
+    let opening = DonationOpening::new(7, 3 * SOL, &mut StdRng::seed_from_u64(2));
+    assert!(is_satisfied(opening, 0));
+    assert!(is_satisfied(opening, 3 * SOL));
+    assert!(!is_satisfied(opening, 3 * SOL + 1));
+    assert!(!is_satisfied(opening, u64::MAX));
+
+    let largest = DonationOpening { amount_lamports: u64::MAX, ..opening };
+    assert!(is_satisfied(largest, 1));
+}
+
+#[actix_web::test]
+async fn proof_reveals_only_the_threshold() {
+    let opening = DonationOpening::new(7, 3 * SOL, &mut StdRng::seed_from_u64(3));
+    let commitment = opening.commitment();
+    let challenge = Fr::from(11u64);
+    let proof = prover().create_proof(&opening, 2 * SOL, challenge).unwrap();
+    let verifier = prover().verifier();
+
+    assert!(verifier.verify(&proof, commitment, 7, 2 * SOL, challenge));
+    assert!(!verifier.verify(&proof, commitment, 7, 3 * SOL, challenge));
+    assert!(!verifier.verify(&proof, commitment, 8, 2 * SOL, challenge));
+    assert!(!verifier.verify(&proof, commitment, 7, 2 * SOL, Fr::from(12u64)));
+    let other = DonationOpening::new(7, 3 * SOL, &mut StdRng::seed_from_u64(4));
+    assert!(!verifier.verify(&proof, other.commitment(), 7, 2 * SOL, challenge));
+
+    assert!(prover().create_proof(&opening, 3 * SOL + 1, challenge).is_err());
+}
+
+#[actix_web::test]
+async fn donors_prove_thresholds_over_issued_commitments() {
+    let keypair = Keypair::new();
+    let wallet = keypair.pubkey().to_string();
+
+    let identity_keys = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(5))).unwrap();
+    let verifier: Arc<dyn ZKVerifier> = Arc::new(
+        RealZKVerifier::from_prover(ZKProver::from_keys(identity_keys.proving_key))
+            .with_donation_threshold(prover().verifier()),
+    );
+    let donation_id = Uuid::new_v4();
+    let store = MemoryDonationCommitmentStore::new();
+    store.add_donation(donation_id, &wallet, CommittedDonation { project_id: 7, amount_lamports: 3 * SOL });
+    let commitments: Arc<dyn DonationCommitmentStore> = Arc::new(store);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(ChallengeStore::new(Duration::from_secs(300))))
+            .app_data(web::Data::from(verifier))
+            .app_data(web::Data::from(commitments))
+            .service(auth_routes())
+            .service(zk_routes()),
+    )
+    .await;
+
+    let challenge_for = |identity: &Value| {
+        test::TestRequest::post()
+            .uri("/auth/zk/challenge")
+            .set_json(json!({ "identity": identity }))
+            .to_request()
+    };
+    let issue = |signer: &Keypair, donation_id: Uuid, challenge: &str| {
+        let message = wallet_signatures::message(DONATION_COMMITMENT_ACTION, &fr_from_hex(challenge).unwrap());
+        test::TestRequest::post()
+            .uri("/auth/zk/donation-commitment")
+            .set_json(json!({
+                "donation_id": donation_id,
+                "wallet": wallet,
+                "challenge": challenge,
+                "signature": signer.sign_message(message.as_bytes()).to_string(),
+            }))
+            .to_request()
+    };
+    let challenge: Value = test::call_and_read_body_json(&app, challenge_for(&json!(wallet))).await;
+    let challenge = challenge["challenge"].as_str().unwrap().to_string();
+
+    // a signature from another key doesn't show the caller holds the wallet
+    let resp = test::call_service(&app, issue(&Keypair::new(), donation_id, &challenge)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, issue(&keypair, Uuid::new_v4(), &challenge)).await;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    // the challenge was used up
+    let resp = test::call_service(&app, issue(&keypair, donation_id, &challenge)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    let challenge: Value = test::call_and_read_body_json(&app, challenge_for(&json!(wallet))).await;
+    let resp = test::call_service(&app, issue(&keypair, donation_id, challenge["challenge"].as_str().unwrap())).await;
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let issued: Value = test::read_body_json(resp).await;
+    let opening = DonationOpening {
+        project_id: 7,
+        amount_lamports: issued["amount_lamports"].as_u64().unwrap(),
+        salt: fr_from_hex(issued["salt"].as_str().unwrap()).unwrap(),
+    };
+    assert_eq!(opening.amount_lamports, 3 * SOL);
+    assert_eq!(fr_to_hex(&opening.commitment()), issued["commitment"]);
+
+    let challenge: Value = test::call_and_read_body_json(&app, challenge_for(&issued["commitment"])).await;
+    let challenge = challenge["challenge"].as_str().unwrap().to_string();
+    let proof = prover().create_proof(&opening, SOL, fr_from_hex(&challenge).unwrap()).unwrap();
+    let verify = |commitment: &Value, challenge: &str, threshold: u64| {
+        test::TestRequest::post()
+            .uri("/zk/donation-threshold/verify")
+            .set_json(json!({
+                "commitment": commitment,
+                "project_id": 7,
+                "threshold_lamports": threshold,
+                "challenge": challenge,
+                "proof": BASE64.encode(&proof),
+            }))
+            .to_request()
+    };
+
+    let resp = test::call_service(&app, verify(&issued["commitment"], &challenge, 2 * SOL)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, verify(&issued["commitment"], &challenge, SOL)).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["threshold_lamports"], SOL);
+    let resp = test::call_service(&app, verify(&issued["commitment"], &challenge, SOL)).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+    // a self-made commitment to a larger donation wasn't issued for a recorded one
+    let forged = DonationOpening::new(7, 100 * SOL, &mut StdRng::seed_from_u64(6));
+    let forged = json!(fr_to_hex(&forged.commitment()));
+    let forged_challenge: Value = test::call_and_read_body_json(&app, challenge_for(&forged)).await;
+    let resp = test::call_service(&app, verify(&forged, forged_challenge["challenge"].as_str().unwrap(), SOL)).await;
+    assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,185 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::Fr;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{Duration, Utc};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use soulana_backend::routes::zk::zk_routes;
+use soulana_backend::zk::circuits::{CircuitError, CircuitId, CircuitRegistry};
+use soulana_backend::zk::envelope::{ProofEnvelope, ProofSystem};
+use soulana_backend::zk::error::ZkError;
+use soulana_backend::zk::field::fr_to_hex;
+use soulana_backend::zk::halo2::{Halo2Prover, Halo2Verifier};
+use soulana_backend::zk::keys::KeyFileError;
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::{ceremony, ZKProver, ZKProverBackend, ZKVerifier};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("soulana-halo2-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn groth16_prover(seed: u64) -> ZKProver {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(seed))).unwrap();
+    ZKProver::from_keys(params.proving_key)
+}
+
+#[actix_web::test]
+async fn halo2_proofs_are_bound_to_their_inputs() {
+    let prover = Halo2Prover::for_circuit(&CircuitId::current()).unwrap();
+    let inputs = [Fr::from(7u64), Fr::from(11u64)];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+
+    // keys only depend on the circuit, so another server derives a verifier for the same proofs
+    let verifier = Halo2Verifier::for_circuit(&CircuitId::current()).unwrap();
+    assert_eq!(verifier.check_proof(&proof, &inputs), Ok(()));
+    assert_eq!(
+        verifier.check_proof(&proof, &[inputs[0], Fr::from(12u64)]),
+        Err(ZkError::PairingCheckFailed)
+    );
+    assert_eq!(
+        verifier.check_proof(&proof, &[Fr::from(8u64), inputs[1]]),
+        Err(ZkError::PairingCheckFailed)
+    );
+    assert_eq!(verifier.check_proof(&proof[..proof.len() / 2], &inputs), Err(ZkError::MalformedProof));
+    assert_eq!(
+        verifier.check_proof(&proof, &inputs[..1]),
+        Err(ZkError::InputCount { expected: 2, actual: 1 })
+    );
+
+    assert!(matches!(
+        Halo2Verifier::for_circuit(&CircuitId::new("membership", 1)),
+        Err(CircuitError::Unknown(_))
+    ));
+}
+
+#[actix_web::test]
+async fn envelopes_name_their_proof_system() {
+    let envelope = ProofEnvelope::new(ProofSystem::Halo2, vec![1, 2, 3]);
+    let bytes = envelope.to_bytes();
+    assert_eq!(ProofEnvelope::from_bytes(&bytes), Ok(envelope));
+    assert_eq!(ProofEnvelope::open(&bytes, ProofSystem::Halo2), Ok(vec![1, 2, 3]));
+    assert_eq!(
+        ProofEnvelope::open(&bytes, ProofSystem::Groth16),
+        Err(ZkError::WrongProofSystem {
+            expected: ProofSystem::Groth16,
+            actual: ProofSystem::Halo2,
+        })
+    );
+
+    // bare bytes are a Groth16 proof, as sent before envelopes existed
+    assert_eq!(ProofEnvelope::open(&[4, 5], ProofSystem::Groth16), Ok(vec![4, 5]));
+    assert_eq!(ProofEnvelope::from_bytes(b"SLNZKPRF"), Err(ZkError::MalformedProof));
+    assert_eq!(ProofEnvelope::from_bytes(b"SLNZKPRF\x09"), Err(ZkError::MalformedProof));
+    assert_eq!(serde_json::to_value(ProofSystem::Halo2).unwrap(), json!("halo2"));
+}
+
+#[actix_web::test]
+async fn registry_sets_up_halo2_circuits_without_key_files() {
+    let keys_dir = temp_dir("registry");
+    fs::write(
+        keys_dir.join("circuits.json"),
+        json!({ "proof_systems": { "identity@v2": "halo2" } }).to_string(),
+    )
+    .unwrap();
+    let registry = CircuitRegistry::load(&keys_dir).unwrap();
+    assert_eq!(registry.prover().proof_system(), ProofSystem::Halo2);
+
+    let inputs = [Fr::from(1u64), Fr::from(2u64)];
+    let proof = registry.prover().create_proof(inputs[0], inputs[1]).unwrap();
+    let verifier = registry.verifier(&CircuitId::current()).unwrap();
+    assert!(verifier.verify_proof(&proof, &inputs));
+    assert!(verifier.groth16().is_none());
+
+    // a Groth16 proof for the same inputs is answered as such, not as a bad Halo 2 proof
+    let groth16 = groth16_prover(1).create_proof(inputs[0], inputs[1]).unwrap();
+    assert_eq!(
+        verifier.check_proof(&groth16, &inputs),
+        Err(ZkError::WrongProofSystem {
+            expected: ProofSystem::Halo2,
+            actual: ProofSystem::Groth16,
+        })
+    );
+    let other = registry.prover().create_proof(inputs[0], Fr::from(3u64)).unwrap();
+    assert_eq!(
+        verifier.verify_proofs(&[(&proof, &inputs), (&other, &inputs), (&groth16, &inputs)]),
+        vec![true, false, false]
+    );
+
+    // only circuits with a Halo 2 version can be set up with it
+    let verify_until = Utc::now() + Duration::days(1);
+    fs::write(
+        keys_dir.join("circuits.json"),
+        json!({
+            "proof_systems": { "identity@v2": "halo2", "identity@v0": "halo2" },
+            "deprecated": [{ "circuit_id": "identity@v0", "verify_until": verify_until }],
+        })
+        .to_string(),
+    )
+    .unwrap();
+    assert!(matches!(CircuitRegistry::load(&keys_dir), Err(KeyFileError::Manifest(..))));
+
+    fs::remove_dir_all(keys_dir).unwrap();
+}
+
+#[actix_web::test]
+async fn groth16_proofs_verify_bare_and_enveloped() {
+    let prover = groth16_prover(2);
+    let inputs = [Fr::from(5u64), Fr::from(6u64)];
+    let bare = prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let registry = CircuitRegistry::new(prover);
+    let enveloped = registry.prover().create_proof(inputs[0], inputs[1]).unwrap();
+    assert_eq!(ProofEnvelope::open(&enveloped, ProofSystem::Groth16).map(|_| ()), Ok(()));
+
+    let verifier = registry.verifier(&CircuitId::current()).unwrap();
+    assert!(verifier.verify_proof(&bare, &inputs));
+    assert!(verifier.verify_proof(&enveloped, &inputs));
+    assert_eq!(verifier.verify_proofs(&[(&bare, &inputs), (&enveloped, &inputs)]), vec![true, true]);
+}
+
+#[actix_web::test]
+async fn halo2_backend_verifies_logins_behind_zk_verifier() {
+    let prover = Halo2Prover::for_circuit(&CircuitId::current()).unwrap();
+    let backend = RealZKVerifier::from_prover(prover);
+    let current = CircuitId::current();
+    let challenge = Fr::from(42u64);
+
+    let proof = backend.create_wallet_proof("wallet-a", challenge).unwrap();
+    assert_eq!(backend.verify_wallet(&current, "wallet-a", challenge, &proof), Ok(()));
+    assert!(backend.verify_wallet(&current, "wallet-b", challenge, &proof).is_err());
+    assert!(backend.verify_wallet(&current, "wallet-a", Fr::from(43u64), &proof).is_err());
+    assert!(backend.verifying_key(&current).is_none());
+
+    let verifier: Arc<dyn ZKVerifier> = Arc::new(backend);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(verifier))
+            .service(zk_routes()),
+    )
+    .await;
+
+    // groth16-solana can't check Halo 2 proofs, so they aren't converted
+    let req = test::TestRequest::post()
+        .uri("/zk/solana/proof")
+        .set_json(json!({
+            "proof": proof,
+            "public_inputs": [fr_to_hex(&Fr::from(1u64)), fr_to_hex(&challenge)],
+        }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "wrong_proof_system");
+
+    let uri = format!("/zk/solana/verifying-key/{}", current);
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+    assert!(BASE64.decode(&proof).unwrap().starts_with(b"SLNZKPRF"));
+}
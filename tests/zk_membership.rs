@@ -0,0 +1,265 @@
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::{Bn254, Fr};
+use ark_groth16::Groth16;
+use ark_snark::SNARK;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use solana_sdk::signature::{Keypair, Signer};
+use soulana_backend::repos::IdentityRepo;
+use soulana_backend::routes::zk::{zk_routes, REGISTER_COMMITMENT_ACTION};
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::services::email_verification::EmailVerifications;
+use soulana_backend::services::membership::{MembershipGroup, MemoryNullifierStore, NullifierStore};
+use soulana_backend::services::wallet_signatures;
+use soulana_backend::zk::ceremony::{self, CeremonyError};
+use soulana_backend::zk::circuits::CircuitId;
+use soulana_backend::zk::field::fr_to_hex;
+use soulana_backend::zk::membership::{
+    scope_to_field, signal_to_field, MembershipCircuit, MembershipIdentity, MembershipInputs, MembershipProver,
+};
+use soulana_backend::zk::merkle::MerkleTree;
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::{ZKProver, ZKVerifier};
+
+mod common;
+use common::unreachable_pool;
+
+// small trees keep the setup and proving fast in debug builds
+const DEPTH: usize = 4;
+const EMAIL: &str = "ada@uni.edu";
+
+fn prover() -> &'static MembershipProver {
+    static PROVER: OnceLock<MembershipProver> = OnceLock::new();
+    PROVER.get_or_init(|| {
+        let (proving_key, _) = Groth16::<Bn254>::circuit_specific_setup(
+            MembershipCircuit::blank(DEPTH),
+            &mut StdRng::seed_from_u64(1),
+        )
+        .unwrap();
+        MembershipProver::from_keys(proving_key, DEPTH)
+    })
+}
+
+fn members(rng: &mut StdRng) -> (MembershipIdentity, Vec<Fr>) {
+    let identity = MembershipIdentity::random(rng);
+    let others = (0..4).map(|_| MembershipIdentity::random(rng).commitment());
+    let mut commitments: Vec<Fr> = others.collect();
+    commitments.insert(2, identity.commitment());
+    (identity, commitments)
+}
+
+#[actix_web::test]
+async fn merkle_paths_lead_to_the_root() {
+    let mut tree = MerkleTree::new(3);
+    let empty_root = tree.root();
+    assert!(tree.path(0).is_none());
+
+    let leaves: Vec<Fr> = (1..=5u64).map(Fr::from).collect();
+    for leaf in &leaves {
+        tree.push(*leaf).unwrap();
+    }
+    assert_ne!(tree.root(), empty_root);
+    for (index, leaf) in leaves.iter().enumerate() {
+        let path = tree.path(index).unwrap();
+        assert_eq!(path.siblings.len(), 3);
+        assert_eq!(path.root(*leaf), tree.root());
+        assert_ne!(path.root(Fr::from(99u64)), tree.root());
+    }
+
+    for leaf in 6..=8u64 {
+        tree.push(Fr::from(leaf)).unwrap();
+    }
+    assert_eq!(tree.push(Fr::from(9u64)), None);
+}
+
+#[actix_web::test]
+async fn proof_binds_root_nullifier_scope_and_signal() {
+    let mut rng = StdRng::seed_from_u64(2);
+    let (identity, commitments) = members(&mut rng);
+    let mut tree = MerkleTree::new(DEPTH);
+    for commitment in &commitments {
+        tree.push(*commitment);
+    }
+
+    let scope = scope_to_field("poll:1");
+    let (proof, inputs) = prover()
+        .create_proof(&identity, &tree, scope, signal_to_field("yes"))
+        .unwrap()
+        .unwrap();
+    assert_eq!(inputs.root, tree.root());
+    assert_eq!(inputs.nullifier_hash, identity.nullifier_hash(scope));
+
+    let verifier = prover().verifier();
+    assert!(verifier.verify(&proof, &inputs));
+    for tampered in [
+        MembershipInputs { root: Fr::from(1u64), ..inputs },
+        MembershipInputs { nullifier_hash: identity.nullifier_hash(scope_to_field("poll:2")), ..inputs },
+        MembershipInputs { external_nullifier: scope_to_field("poll:2"), ..inputs },
+        MembershipInputs { signal_hash: signal_to_field("no"), ..inputs },
+    ] {
+        assert!(!verifier.verify(&proof, &tampered));
+    }
+
+    let outsider = MembershipIdentity::random(&mut rng);
+    assert!(prover().create_proof(&outsider, &tree, scope, Fr::from(0u64)).unwrap().is_none());
+}
+
+#[actix_web::test]
+async fn verify_endpoint_rejects_reused_nullifiers() {
+    let mut rng = StdRng::seed_from_u64(3);
+    let (identity, commitments) = members(&mut rng);
+    let group = MembershipGroup::from_commitments(DEPTH, commitments.clone());
+    let identity_keys = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(4))).unwrap();
+    let verifier: Arc<dyn ZKVerifier> = Arc::new(
+        RealZKVerifier::from_prover(ZKProver::from_keys(identity_keys.proving_key))
+            .with_membership(prover().verifier()),
+    );
+    let nullifiers: Arc<dyn NullifierStore> = Arc::new(MemoryNullifierStore::new());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(group))
+            .app_data(web::Data::from(verifier))
+            .app_data(web::Data::from(nullifiers))
+            .service(zk_routes()),
+    )
+    .await;
+
+    let req = test::TestRequest::get().uri("/zk/membership/root").to_request();
+    let root: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(root["depth"], DEPTH);
+    assert_eq!(root["size"], commitments.len());
+
+    let commitment = fr_to_hex(&identity.commitment());
+    let req = test::TestRequest::get().uri(&format!("/zk/membership/path/{}", commitment)).to_request();
+    let path: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(path["leaf_index"], 2);
+    assert_eq!(path["root"], root["root"]);
+    assert_eq!(path["siblings"].as_array().unwrap().len(), DEPTH);
+
+    let req = test::TestRequest::get().uri(&format!("/zk/membership/path/{}", fr_to_hex(&Fr::from(7u64)))).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get().uri("/zk/membership/path/not-hex").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let mut tree = MerkleTree::new(DEPTH);
+    for commitment in &commitments {
+        tree.push(*commitment);
+    }
+    let (proof, inputs) = prover()
+        .create_proof(&identity, &tree, scope_to_field("poll:1"), signal_to_field("yes"))
+        .unwrap()
+        .unwrap();
+    let body = |signal: &str, root: &str| json!({
+        "proof": BASE64.encode(&proof),
+        "root": root,
+        "nullifier_hash": fr_to_hex(&inputs.nullifier_hash),
+        "scope": "poll:1",
+        "signal": signal,
+    });
+    let root = root["root"].as_str().unwrap();
+
+    let req = test::TestRequest::post().uri("/zk/membership/verify").set_json(body("no", root)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+    let req = test::TestRequest::post()
+        .uri("/zk/membership/verify")
+        .set_json(body("yes", &fr_to_hex(&Fr::from(1u64))))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post().uri("/zk/membership/verify").set_json(body("yes", root)).to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+    let verified: Value = test::read_body_json(resp).await;
+    assert_eq!(verified["nullifier_hash"], fr_to_hex(&inputs.nullifier_hash));
+
+    let req = test::TestRequest::post().uri("/zk/membership/verify").set_json(body("yes", root)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn commitments_are_registered_only_by_the_identity_owner() {
+    let auth_service = AuthService::new(unreachable_pool(), "test_secret".to_string());
+    let owner = Keypair::new();
+    let wallet = owner.pubkey().to_string();
+    // login tokens can be had for any identity, so both tokens here may be the squatter's
+    let (wallet_token, _) = auth_service.create_auth_token(&wallet).await.unwrap();
+    let (email_token, _) = auth_service.create_auth_token(EMAIL).await.unwrap();
+
+    let challenges = web::Data::new(ChallengeStore::new(Duration::from_secs(300)));
+    let verifications = web::Data::new(EmailVerifications::new(Duration::from_secs(300)));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(auth_service))
+            .app_data(challenges.clone())
+            .app_data(verifications.clone())
+            .app_data(web::Data::new(MembershipGroup::new(DEPTH)))
+            .app_data(web::Data::new(IdentityRepo::new(unreachable_pool())))
+            .service(zk_routes()),
+    )
+    .await;
+
+    let register = |token: &str, body: Value| {
+        test::TestRequest::post()
+            .uri("/zk/membership/commitments")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(body)
+            .to_request()
+    };
+    let commitment = fr_to_hex(&Fr::from(5u64));
+    let signed_by = |signer: &Keypair| {
+        let (challenge, _) = challenges.issue(&wallet, "test").unwrap();
+        let message = wallet_signatures::message(REGISTER_COMMITMENT_ACTION, &challenge);
+        json!({
+            "commitment": commitment,
+            "challenge": fr_to_hex(&challenge),
+            "signature": signer.sign_message(message.as_bytes()).to_string(),
+        })
+    };
+
+    let resp = test::call_service(&app, register(&wallet_token, json!({ "commitment": commitment }))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    // a squatter with a token for the wallet can't sign for it
+    let resp = test::call_service(&app, register(&wallet_token, signed_by(&Keypair::new()))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    // the owner's signature gets as far as the database
+    let resp = test::call_service(&app, register(&wallet_token, signed_by(&owner))).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    // and a squatter on an email address doesn't get the code sent to it
+    let (code, _) = verifications.start(EMAIL).unwrap();
+    let wrong = if code == "000000" { "000001" } else { "000000" };
+    let resp = test::call_service(&app, register(&email_token, json!({ "commitment": commitment, "code": wrong }))).await;
+    assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+    let resp = test::call_service(&app, register(&email_token, json!({ "commitment": commitment, "code": code }))).await;
+    assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[actix_web::test]
+async fn group_accepts_recent_roots() {
+    let group = MembershipGroup::from_commitments(DEPTH, [Fr::from(1u64)]);
+    let old_root = group.root();
+    let grown = MembershipGroup::from_commitments(DEPTH, [Fr::from(1u64), Fr::from(2u64)]);
+    assert!(grown.is_known_root(&old_root));
+    assert!(grown.is_known_root(&grown.root()));
+    assert!(!group.is_known_root(&grown.root()));
+}
+
+#[actix_web::test]
+async fn ceremony_only_knows_compiled_circuits() {
+    let unknown = CircuitId::new("membership", 9);
+    assert!(matches!(
+        ceremony::init_for(&unknown, &PowersOfTau::generate(3, &mut StdRng::seed_from_u64(5))),
+        Err(CeremonyError::UnknownCircuit(id)) if id == unknown
+    ));
+
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(6))).unwrap();
+    assert_eq!(params.circuit_id().unwrap(), CircuitId::current());
+    let mut relabelled = params.clone();
+    relabelled.circuit = "membership@v1".to_string();
+    assert!(matches!(relabelled.circuit_id(), Err(CeremonyError::CircuitMismatch(_))));
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInt, PrimeField, UniformRand, Zero};
+use ark_groth16::r1cs_to_qap::{evaluate_constraint, LibsnarkReduction, R1CSToQAP};
+use ark_groth16::{Groth16, Proof, ProvingKey, VerifyingKey};
+use ark_poly::EvaluationDomain;
+use ark_relations::r1cs::{ConstraintMatrices, ConstraintSystemRef, SynthesisError};
+use ark_serialize::CanonicalDeserialize;
+use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::PgConnection;
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use soulana_backend::routes::auth::auth_routes;
+use soulana_backend::services::auth::AuthService;
+use soulana_backend::services::challenges::ChallengeStore;
+use soulana_backend::zk::circuits::CircuitId;
+use soulana_backend::zk::error::ZkError;
+use soulana_backend::zk::field::{fr_from_hex, identity_to_field};
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::snarkjs::{
+    g1_from_json, g2_from_json, public_signals, SnarkjsError, SnarkjsProof, SnarkjsVerifyingKey,
+};
+use soulana_backend::zk::{ceremony, CircuitVerifier, ZKProver, ZKVerifier};
+
+/// Written by snarkjs for `fixtures/snarkjs/multiplier.circom`, see the README next to them.
+const VERIFICATION_KEY: &str = include_str!("fixtures/snarkjs/verification_key.json");
+const ZKEY: &[u8] = include_bytes!("fixtures/snarkjs/multiplier.zkey");
+const WALLET: &str = "F1rstn82GYYuWVPYBg7YKUZ2fZskDFg27ocXBx88pcgW";
+
+fn strings<const N: usize>(values: [&str; N]) -> [String; N] {
+    values.map(str::to_string)
+}
+
+/// A base field element in the Montgomery form snarkjs writes.
+fn fq(bytes: &[u8]) -> Fq {
+    let mut limbs = [0u64; 4];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+    Fq::new_unchecked(BigInt::new(limbs))
+}
+
+fn g1(bytes: &[u8]) -> G1Affine {
+    let (x, y) = (fq(&bytes[..32]), fq(&bytes[32..64]));
+    if x.is_zero() && y.is_zero() { G1Affine::zero() } else { G1Affine::new(x, y) }
+}
+
+fn g2(bytes: &[u8]) -> G2Affine {
+    let x = Fq2::new(fq(&bytes[..32]), fq(&bytes[32..64]));
+    let y = Fq2::new(fq(&bytes[64..96]), fq(&bytes[96..128]));
+    if x.is_zero() && y.is_zero() { G2Affine::zero() } else { G2Affine::new(x, y) }
+}
+
+/// The proving key and the A and B matrices of a snarkjs `.zkey`, laid out as ark-circom's
+/// `read_zkey` does.
+fn read_zkey(bytes: &[u8]) -> (ProvingKey<Bn254>, ConstraintMatrices<Fr>) {
+    assert_eq!(&bytes[..4], b"zkey");
+    let u32_at = |data: &[u8], at: usize| u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize;
+    let mut sections = HashMap::new();
+    let mut at = 12;
+    for _ in 0..u32_at(bytes, 8) {
+        let len = u64::from_le_bytes(bytes[at + 4..at + 12].try_into().unwrap()) as usize;
+        sections.insert(u32_at(bytes, at), &bytes[at + 12..at + 12 + len]);
+        at += 12 + len;
+    }
+    let g1s = |section: usize, count: usize| sections[&section].chunks(64).take(count).map(g1).collect::<Vec<_>>();
+    let g2s = |section: usize, count: usize| sections[&section].chunks(128).take(count).map(g2).collect::<Vec<_>>();
+
+    // the sizes follow the two moduli and their lengths, then come the points of the key
+    let header = sections[&2];
+    let (n_vars, n_public, domain_size) = (u32_at(header, 72), u32_at(header, 76), u32_at(header, 80));
+    let points = &header[84..];
+    let proving_key = ProvingKey {
+        vk: VerifyingKey {
+            alpha_g1: g1(points),
+            beta_g2: g2(&points[128..]),
+            gamma_g2: g2(&points[256..]),
+            delta_g2: g2(&points[448..]),
+            gamma_abc_g1: g1s(3, n_public + 1),
+        },
+        beta_g1: g1(&points[64..]),
+        delta_g1: g1(&points[384..]),
+        a_query: g1s(5, n_vars),
+        b_g1_query: g1s(6, n_vars),
+        b_g2_query: g2s(7, n_vars),
+        l_query: g1s(8, n_vars - n_public - 1),
+        h_query: g1s(9, domain_size),
+    };
+
+    // (matrix, constraint, signal, value) with the value multiplied by R once more
+    let coefficients = sections[&4];
+    let mut rows = vec![vec![Vec::new(); domain_size]; 2];
+    let mut last_constraint = 0;
+    for entry in coefficients[4..].chunks(44).take(u32_at(coefficients, 0)) {
+        let (matrix, constraint, signal) = (u32_at(entry, 0), u32_at(entry, 4), u32_at(entry, 8));
+        let limbs = std::array::from_fn(|i| u64::from_le_bytes(entry[12 + 8 * i..20 + 8 * i].try_into().unwrap()));
+        let value = Fr::new_unchecked(Fr::new_unchecked(BigInt::new(limbs)).into_bigint());
+        rows[matrix][constraint].push((value, signal));
+        last_constraint = last_constraint.max(constraint);
+    }
+    // snarkjs lists the constraints it adds per public input too, ark-groth16 adds them itself
+    let num_constraints = last_constraint - n_public;
+    let [mut a, mut b] = <[_; 2]>::try_from(rows).unwrap();
+    a.truncate(num_constraints);
+    b.truncate(num_constraints);
+    let matrices = ConstraintMatrices {
+        num_instance_variables: n_public + 1,
+        num_witness_variables: n_vars - n_public - 1,
+        num_constraints,
+        a_num_non_zero: a.iter().map(Vec::len).sum(),
+        b_num_non_zero: b.iter().map(Vec::len).sum(),
+        c_num_non_zero: 0,
+        a,
+        b,
+        c: Vec::new(),
+    };
+    (proving_key, matrices)
+}
+
+/// The witness map of snarkjs, as in ark-circom's `CircomReduction`: its H query holds
+/// the odd Lagrange points of a domain twice as large, so the prover evaluates `AB - C`
+/// on the coset of the odd roots of unity instead of dividing by Z.
+struct SnarkjsReduction;
+
+impl R1CSToQAP for SnarkjsReduction {
+    #[allow(clippy::type_complexity)]
+    fn instance_map_with_evaluation<F: PrimeField, D: EvaluationDomain<F>>(
+        cs: ConstraintSystemRef<F>,
+        t: &F,
+    ) -> Result<(Vec<F>, Vec<F>, Vec<F>, F, usize, usize), SynthesisError> {
+        LibsnarkReduction::instance_map_with_evaluation::<F, D>(cs, t)
+    }
+
+    fn witness_map_from_matrices<F: PrimeField, D: EvaluationDomain<F>>(
+        matrices: &ConstraintMatrices<F>,
+        num_inputs: usize,
+        num_constraints: usize,
+        full_assignment: &[F],
+    ) -> Result<Vec<F>, SynthesisError> {
+        let domain = D::new(num_constraints + num_inputs).ok_or(SynthesisError::PolynomialDegreeTooLarge)?;
+        let odd_root = D::new(2 * domain.size()).ok_or(SynthesisError::PolynomialDegreeTooLarge)?.element(1);
+        let mut a = vec![F::zero(); domain.size()];
+        let mut b = vec![F::zero(); domain.size()];
+        for (i, (a_row, b_row)) in matrices.a.iter().zip(&matrices.b).enumerate() {
+            a[i] = evaluate_constraint(a_row, full_assignment);
+            b[i] = evaluate_constraint(b_row, full_assignment);
+        }
+        a[num_constraints..num_constraints + num_inputs].copy_from_slice(&full_assignment[..num_inputs]);
+        let mut c: Vec<F> = a.iter().zip(&b).take(num_constraints).map(|(a, b)| *a * b).collect();
+        c.resize(domain.size(), F::zero());
+
+        let on_odd_coset = |values: &mut Vec<F>| {
+            domain.ifft_in_place(values);
+            D::distribute_powers_and_mul_by_const(values, odd_root, F::one());
+            domain.fft_in_place(values);
+        };
+        on_odd_coset(&mut a);
+        on_odd_coset(&mut b);
+        on_odd_coset(&mut c);
+        Ok(a.iter().zip(&b).zip(&c).map(|((a, b), c)| *a * b - c).collect())
+    }
+
+    fn h_query_scalars<F: PrimeField, D: EvaluationDomain<F>>(
+        max_power: usize,
+        t: F,
+        zt: F,
+        delta_inverse: F,
+    ) -> Result<Vec<F>, SynthesisError> {
+        LibsnarkReduction::h_query_scalars::<F, D>(max_power, t, zt, delta_inverse)
+    }
+}
+
+/// A proof of `3 * 11 = 33` made with the proving key snarkjs wrote, in its JSON layout.
+fn multiplier_proof() -> SnarkjsProof {
+    let (proving_key, matrices) = read_zkey(ZKEY);
+    // the signals of the circuit: one, c, a and b
+    let assignment = [1u64, 33, 3, 11].map(Fr::from);
+    let mut rng = StdRng::seed_from_u64(3);
+    let proof = Groth16::<Bn254, SnarkjsReduction>::create_proof_with_reduction_and_matrices(
+        &proving_key,
+        Fr::rand(&mut rng),
+        Fr::rand(&mut rng),
+        &matrices,
+        matrices.num_instance_variables,
+        matrices.num_constraints,
+        &assignment,
+    )
+    .unwrap();
+    SnarkjsProof::from_proof(&proof)
+}
+
+#[actix_web::test]
+async fn snarkjs_verification_keys_import() {
+    let vk = SnarkjsVerifyingKey::from_json(VERIFICATION_KEY).unwrap();
+    let (proving_key, _) = read_zkey(ZKEY);
+    assert_eq!(vk.to_vk(), Ok(proving_key.vk.clone()));
+    assert_eq!(SnarkjsVerifyingKey::from_vk(&proving_key.vk), vk);
+    // `snarkjs zkey new` leaves gamma and delta at one until contributions
+    assert_eq!(proving_key.vk.gamma_g2, G2Affine::generator());
+    assert_eq!(proving_key.vk.delta_g2, G2Affine::generator());
+}
+
+#[actix_web::test]
+async fn proofs_from_snarkjs_keys_verify_after_import() {
+    let verifier = CircuitVerifier::from_snarkjs(&SnarkjsVerifyingKey::from_json(VERIFICATION_KEY).unwrap()).unwrap();
+    let proof = multiplier_proof();
+    let public = public_signals(&[Fr::from(33u64)]);
+    assert_eq!(public, ["33"]);
+
+    assert_eq!(verifier.check_snarkjs_proof(&proof, &public), Ok(()));
+    assert_eq!(verifier.check_snarkjs_proof(&proof, &strings(["34"])), Err(ZkError::PairingCheckFailed));
+    let compressed = proof.to_compressed().unwrap();
+    assert!(verifier.verify_proof(&compressed, &[Fr::from(33u64)]));
+
+    // converting back gives the same JSON
+    let decoded = Proof::<Bn254>::deserialize_compressed(&compressed[..]).unwrap();
+    assert_eq!(SnarkjsProof::from_proof(&decoded), proof);
+    let json = serde_json::to_string(&proof).unwrap();
+    assert_eq!(SnarkjsProof::from_json(&json), Ok(proof));
+}
+
+#[actix_web::test]
+async fn coordinates_follow_snarkjs_order() {
+    // vk_gamma_2 of keys set up by snarkjs is the G2 generator, x and y as [c0, c1]
+    let generator = [
+        strings([
+            "10857046999023057135944570762232829481370756359578518086990519993285655852781",
+            "11559732032986387107991004021392285783925812861821192530917403151452391805634",
+        ]),
+        strings([
+            "8495653923123431417604973247489272438418190587263600148770280649306958101930",
+            "4082367875863433681332203403145435568316851327593401208105741076214120093531",
+        ]),
+        strings(["1", "0"]),
+    ];
+    assert_eq!(g2_from_json(&generator), Ok(G2Affine::generator()));
+    let swapped = generator.clone().map(|[c0, c1]| [c1, c0]);
+    assert_eq!(g2_from_json(&swapped), Err(SnarkjsError::InvalidPoint));
+
+    assert_eq!(g1_from_json(&strings(["1", "2", "1"])), Ok(G1Affine::generator()));
+    assert_eq!(g1_from_json(&strings(["0", "1", "0"])), Ok(G1Affine::zero()));
+    assert_eq!(g1_from_json(&strings(["1", "3", "1"])), Err(SnarkjsError::InvalidPoint));
+    // p + 1 is congruent to 1 but not canonical
+    let p_plus_one = "21888242871839275222246405745257275088696311157297823662689037894645226208584";
+    assert_eq!(
+        g1_from_json(&strings([p_plus_one, "2", "1"])),
+        Err(SnarkjsError::InvalidNumber(p_plus_one.to_string()))
+    );
+    assert_eq!(
+        g1_from_json(&strings(["01", "2", "1"])),
+        Err(SnarkjsError::InvalidNumber("01".to_string()))
+    );
+
+    let mut plonk = multiplier_proof();
+    plonk.protocol = "plonk".to_string();
+    assert_eq!(plonk.to_proof(), Err(SnarkjsError::UnsupportedProtocol("plonk".to_string())));
+    let mut vk = SnarkjsVerifyingKey::from_json(VERIFICATION_KEY).unwrap();
+    vk.n_public = 3;
+    assert_eq!(vk.to_vk(), Err(SnarkjsError::InputCountMismatch { declared: 3, actual: 2 }));
+}
+
+#[actix_web::test]
+async fn wallet_login_accepts_snarkjs_proofs() {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(1))).unwrap();
+    let prover = ZKProver::from_keys(params.proving_key.clone());
+    let verifier: Arc<dyn ZKVerifier> =
+        Arc::new(RealZKVerifier::from_prover(ZKProver::from_keys(params.proving_key)));
+    let manager = ConnectionManager::<PgConnection>::new("postgres://localhost/unused");
+    let auth_service = AuthService::new(Pool::builder().build_unchecked(manager), "test_secret".to_string());
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(auth_service))
+            .app_data(web::Data::new(ChallengeStore::new(Duration::from_secs(300))))
+            .app_data(web::Data::from(verifier))
+            .service(auth_routes()),
+    )
+    .await;
+
+    let req = test::TestRequest::post()
+        .uri("/auth/zk/challenge")
+        .set_json(json!({ "identity": WALLET }))
+        .to_request();
+    let challenge: Value = test::call_and_read_body_json(&app, req).await;
+    let challenge = challenge["challenge"].as_str().unwrap();
+    let inputs = [identity_to_field(WALLET), fr_from_hex(challenge).unwrap()];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let proof = SnarkjsProof::from_proof(&Proof::<Bn254>::deserialize_compressed(&proof[..]).unwrap());
+    let login = |proof: &SnarkjsProof, public: Vec<String>| {
+        test::TestRequest::post()
+            .uri("/auth/zk/wallet")
+            .set_json(json!({
+                "wallet_address": WALLET,
+                "challenge": challenge,
+                "zk_proof": { "proof": proof, "public_inputs": public, "circuit_id": CircuitId::current() },
+            }))
+            .to_request()
+    };
+
+    let mut off_curve = proof.clone();
+    off_curve.pi_a[1] = "3".to_string();
+    let resp = test::call_service(&app, login(&off_curve, public_signals(&inputs))).await;
+    assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "malformed_snarkjs");
+
+    let resp = test::call_service(&app, login(&proof, public_signals(&[inputs[0], Fr::from(1u64)]))).await;
+    let body: Value = test::read_body_json(resp).await;
+    assert_eq!(body["code"], "public_input_mismatch");
+
+    let resp = test::call_service(&app, login(&proof, public_signals(&inputs))).await;
+    assert_eq!(resp.status(), StatusCode::OK);
+}
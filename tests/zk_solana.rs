@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use actix_web::{http::StatusCode, test, web, App};
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_groth16::Proof;
+use ark_serialize::CanonicalDeserialize;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use rand::{rngs::StdRng, SeedableRng};
+use serde_json::{json, Value};
+use solana_program::alt_bn128::prelude::{alt_bn128_addition, alt_bn128_multiplication, alt_bn128_pairing};
+use soulana_backend::routes::zk::zk_routes;
+use soulana_backend::zk::circuits::CircuitId;
+use soulana_backend::zk::field::fr_to_hex;
+use soulana_backend::zk::ptau::PowersOfTau;
+use soulana_backend::zk::real::RealZKVerifier;
+use soulana_backend::zk::solana::{self, fr_to_bytes, g1_to_bytes, g2_to_bytes, SolanaExportError, SolanaProof};
+use soulana_backend::zk::{ceremony, ZKProver, ZKVerifier};
+
+fn prover(seed: u64) -> ZKProver {
+    let params = ceremony::init(&PowersOfTau::generate(3, &mut StdRng::seed_from_u64(seed))).unwrap();
+    ZKProver::from_keys(params.proving_key)
+}
+
+fn hex_array<const N: usize>(value: &Value) -> [u8; N] {
+    hex::decode(value.as_str().unwrap()).unwrap().try_into().unwrap()
+}
+
+#[actix_web::test]
+async fn point_encodings_are_accepted_by_the_syscalls() {
+    let g1 = G1Affine::generator();
+    let g2 = G2Affine::generator();
+    let zero = [0u8; 64];
+
+    assert_eq!(alt_bn128_addition(&[g1_to_bytes(&g1), zero].concat()).unwrap(), g1_to_bytes(&g1));
+    assert_eq!(alt_bn128_addition(&[g1_to_bytes(&g1), g1_to_bytes(&-g1)].concat()).unwrap(), zero);
+    let doubled = (g1 * Fr::from(2u64)).into_affine();
+    let scalar = fr_to_bytes(&Fr::from(2u64));
+    assert_eq!(alt_bn128_multiplication(&[&g1_to_bytes(&g1)[..], &scalar[..]].concat()).unwrap(), g1_to_bytes(&doubled));
+
+    // e(g1, g2) * e(-g1, g2) == 1 only holds if the G2 coordinates are read back as written
+    let pairing = |a: &G1Affine, b: &G1Affine| {
+        let input = [&g1_to_bytes(a)[..], &g2_to_bytes(&g2)[..], &g1_to_bytes(b)[..], &g2_to_bytes(&g2)[..]].concat();
+        alt_bn128_pairing(&input).unwrap()[31]
+    };
+    assert_eq!(pairing(&g1, &-g1), 1);
+    assert_eq!(pairing(&g1, &g1), 0);
+}
+
+#[actix_web::test]
+async fn on_chain_check_agrees_with_arkworks() {
+    let prover = prover(1);
+    let vk = prover.solana_verifying_key();
+    assert_eq!(vk.public_inputs(), 2);
+
+    let inputs = [Fr::from(5u64), Fr::from(9u64)];
+    let wrong = [Fr::from(5u64), Fr::from(10u64)];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let other = prover.create_proof(wrong[0], wrong[1]).unwrap();
+    let exported = SolanaProof::from_compressed(&proof).unwrap();
+    let input_bytes = |inputs: &[Fr]| inputs.iter().map(fr_to_bytes).collect::<Vec<_>>();
+
+    assert!(prover.verify_proof(&proof, &inputs));
+    assert_eq!(solana::verify(&vk, &exported, &input_bytes(&inputs)), Ok(true));
+
+    assert!(!prover.verify_proof(&proof, &wrong));
+    assert_eq!(solana::verify(&vk, &exported, &input_bytes(&wrong)), Ok(false));
+
+    // a valid C from another proof keeps every point on the curve but breaks the equation
+    let spliced = SolanaProof {
+        c: SolanaProof::from_compressed(&other).unwrap().c,
+        ..exported.clone()
+    };
+    assert_eq!(solana::verify(&vk, &spliced, &input_bytes(&inputs)), Ok(false));
+    let unnegated = SolanaProof {
+        a: g1_to_bytes(&Proof::<Bn254>::deserialize_compressed(&proof[..]).unwrap().a),
+        ..exported.clone()
+    };
+    assert_eq!(solana::verify(&vk, &unnegated, &input_bytes(&inputs)), Ok(false));
+
+    assert_eq!(
+        solana::verify(&vk, &exported, &[[0xff; 32], fr_to_bytes(&inputs[1])]),
+        Err(SolanaExportError::InputOutOfField(0))
+    );
+    assert_eq!(
+        solana::verify(&vk, &exported, &input_bytes(&inputs[..1])),
+        Err(SolanaExportError::WrongInputCount { expected: 2, actual: 1 })
+    );
+    assert_eq!(SolanaProof::from_compressed(b"garbage"), Err(SolanaExportError::MalformedProof));
+
+    let source = vk.to_rust_source("VERIFYINGKEY");
+    assert!(source.contains("pub const VERIFYINGKEY: Groth16Verifyingkey"));
+    assert!(source.contains("nr_pubinputs: 2,"));
+}
+
+#[actix_web::test]
+async fn endpoints_export_keys_and_proofs() {
+    let prover = prover(2);
+    let expected = prover.solana_verifying_key();
+    let inputs = [Fr::from(3u64), Fr::from(4u64)];
+    let proof = prover.create_proof(inputs[0], inputs[1]).unwrap();
+    let verifier: Arc<dyn ZKVerifier> = Arc::new(RealZKVerifier::from_prover(prover));
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::from(verifier))
+            .service(zk_routes()),
+    )
+    .await;
+
+    let uri = format!("/zk/solana/verifying-key/{}", CircuitId::current());
+    let req = test::TestRequest::get().uri(&uri).to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["nr_pubinputs"], 2);
+    assert_eq!(hex_array::<64>(&body["vk_alpha_g1"]), expected.alpha_g1);
+    assert_eq!(hex_array::<128>(&body["vk_delta_g2"]), expected.delta_g2);
+    assert_eq!(body["vk_ic"].as_array().unwrap().len(), 3);
+
+    let req = test::TestRequest::get().uri("/zk/solana/verifying-key/membership@v1").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    let req = test::TestRequest::get().uri("/zk/solana/verifying-key/identity").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/zk/solana/proof")
+        .set_json(json!({
+            "proof": BASE64.encode(&proof),
+            "public_inputs": [fr_to_hex(&inputs[0]), fr_to_hex(&inputs[1])],
+        }))
+        .to_request();
+    let body: Value = test::call_and_read_body_json(&app, req).await;
+    let exported = SolanaProof {
+        a: hex_array(&body["proof_a"]),
+        b: hex_array(&body["proof_b"]),
+        c: hex_array(&body["proof_c"]),
+    };
+    let public_inputs: Vec<[u8; 32]> = body["public_inputs"].as_array().unwrap().iter().map(hex_array).collect();
+    assert_eq!(solana::verify(&expected, &exported, &public_inputs), Ok(true));
+
+    let req = test::TestRequest::post()
+        .uri("/zk/solana/proof")
+        .set_json(json!({ "proof": BASE64.encode(b"garbage"), "public_inputs": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}